@@ -1,11 +1,18 @@
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{
-    process::{Command, Stdio},
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+    io::{BufRead, BufReader},
+    process::{Child, ChildStderr, ChildStdout, Command, Stdio},
     str::from_utf8,
+    sync::{Arc, LazyLock, Mutex},
+    thread,
 };
 use time::OffsetDateTime;
 
-use crate::path_tools::RclonePath;
+use crate::{path_tools::RclonePath, rcd::RcdClient};
 
 #[derive(Serialize, Deserialize)]
 pub struct ImportedFileListing {
@@ -37,7 +44,10 @@ pub struct RcloneFileListing {
 }
 
 impl RcloneFileListing {
-    fn from(imported_file_listing: &ImportedFileListing, parent_path: &RclonePath) -> Self {
+    pub(crate) fn from(
+        imported_file_listing: &ImportedFileListing,
+        parent_path: &RclonePath,
+    ) -> Self {
         Self {
             path: parent_path.join(&imported_file_listing.path),
             name: imported_file_listing.name.clone(),
@@ -58,10 +68,716 @@ impl RcloneFileListing {
     }
 }
 
+#[derive(Deserialize)]
+struct ImportedLogLine {
+    level: Option<String>,
+    msg: Option<String>,
+    stats: Option<TransferStats>,
+}
+
+impl ImportedLogLine {
+    fn is_error(&self) -> bool {
+        matches!(
+            self.level.as_deref(),
+            Some("error" | "critical" | "alert" | "emergency" | "fatal")
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TransferStats {
+    pub bytes: u64,
+    #[serde(rename = "totalBytes")]
+    pub total_bytes: u64,
+    pub speed: f64,
+    pub eta: Option<u64>,
+}
+
+impl TransferStats {
+    /// Extracts the stats block from a line of `--use-json-log` output, if it has one.
+    pub fn from_log_line(line: &str) -> Option<Self> {
+        serde_json::from_str::<ImportedLogLine>(line)
+            .ok()
+            .and_then(|l| l.stats)
+    }
+
+    pub fn fraction(&self) -> f64 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            (self.bytes as f64 / self.total_bytes as f64).min(1.0)
+        }
+    }
+
+    pub fn formatted_progress(&self) -> String {
+        format!(
+            "{}B of {}B ({}%)",
+            size_format::SizeFormatterSI::new(self.bytes),
+            size_format::SizeFormatterSI::new(self.total_bytes),
+            (self.fraction() * 100.0).floor()
+        )
+    }
+
+    pub fn formatted_speed(&self) -> String {
+        format!(
+            "{}B/s",
+            size_format::SizeFormatterSI::new(self.speed.max(0.0) as u64)
+        )
+    }
+
+    pub fn formatted_eta(&self) -> Option<String> {
+        let eta = self.eta?;
+        let (hours, minutes, seconds) = (eta / 3600, (eta % 3600) / 60, eta % 60);
+        Some(if hours > 0 {
+            format!("{}h {:02}m", hours, minutes)
+        } else if minutes > 0 {
+            format!("{}m {:02}s", minutes, seconds)
+        } else {
+            format!("{}s", seconds)
+        })
+    }
+}
+
+/// Reads one entry of `lsjson` output, which puts every entry on a line of its own.
+fn parse_lsjson_line(line: &str) -> Option<ImportedFileListing> {
+    serde_json::from_str(line.trim().trim_end_matches(',')).ok()
+}
+
+/// Turns a line of `--use-json-log` output back into a readable message.
+fn log_message(line: &str) -> String {
+    match serde_json::from_str::<ImportedLogLine>(line) {
+        Ok(ImportedLogLine { msg: Some(msg), .. }) => msg.trim().to_owned(),
+        _ => line.to_owned(),
+    }
+}
+
+/// Everything rclone reports about a file or folder, with nested fields like hashes flattened
+/// into keys such as `Hashes.md5`.
+pub type FileMetadata = BTreeMap<String, String>;
+
+/// Flattens the object `lsjson --stat` prints into readable key and value pairs.
+pub fn flatten_metadata(value: &Value) -> FileMetadata {
+    fn flatten_into(metadata: &mut FileMetadata, prefix: &str, value: &Value) {
+        let text = match value {
+            Value::Null => return,
+            Value::Object(fields) => {
+                for (key, field) in fields {
+                    let key = match prefix {
+                        "" => key.clone(),
+                        _ => format!("{}.{}", prefix, key),
+                    };
+                    flatten_into(metadata, &key, field);
+                }
+                return;
+            }
+            Value::String(text) => text.clone(),
+            Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Value::String(text) => text.clone(),
+                    _ => item.to_string(),
+                })
+                .collect::<Vec<String>>()
+                .join(", "),
+            _ => value.to_string(),
+        };
+        metadata.insert(prefix.to_owned(), text);
+    }
+
+    let mut metadata = FileMetadata::new();
+    flatten_into(&mut metadata, "", value);
+    metadata
+}
+
+#[derive(Debug, Default)]
+struct ProcessState {
+    child: Option<Child>,
+    cancelled: bool,
+}
+
+/// Shared handle to the rclone process behind a job, so it can be stopped from the UI.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessHandle {
+    state: Arc<Mutex<ProcessState>>,
+}
+
+impl ProcessHandle {
+    pub fn cancel(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.cancelled = true;
+        if let Some(child) = state.child.as_mut() {
+            let _ = child.kill();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().unwrap().cancelled
+    }
+}
+
+/// HTTP status codes of rate limits and server errors, as whole words.
+static TRANSIENT_STATUS_CODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(429|500|502|503|504)\b").unwrap());
+
+/// What went wrong in an rclone operation, sorted into the cases the UI can help with. Each
+/// variant holds rclone's own message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RcloneError {
+    NotFound(String),
+    PermissionDenied(String),
+    AuthExpired(String),
+    QuotaExceeded(String),
+    Network(String),
+    ConfigLocked(String),
+    Unknown(String),
+}
+
+impl RcloneError {
+    /// Sorts an error message by the phrases rclone and the storage providers use.
+    pub fn from_message(message: &str) -> Self {
+        let lowercase_message = message.to_lowercase();
+        let mentions = |patterns: &[&str]| patterns.iter().any(|p| lowercase_message.contains(p));
+        let message = message.trim().to_owned();
+        if mentions(&[
+            "couldn't decrypt config",
+            "unable to decrypt configuration",
+            "config file is encrypted",
+            "wrong password",
+        ]) {
+            Self::ConfigLocked(message)
+        } else if mentions(&[
+            "token expired",
+            "token has been expired",
+            "invalid_grant",
+            "couldn't fetch token",
+            "unauthorized",
+            "invalid authentication",
+        ]) {
+            Self::AuthExpired(message)
+        } else if mentions(&[
+            "quota",
+            "storage full",
+            "insufficient storage",
+            "insufficient_storage",
+            "no space left",
+        ]) {
+            Self::QuotaExceeded(message)
+        } else if mentions(&[
+            "permission denied",
+            "access denied",
+            "accessdenied",
+            "forbidden",
+            "insufficient permissions",
+        ]) {
+            Self::PermissionDenied(message)
+        } else if mentions(&[
+            "not found",
+            "doesn't exist",
+            "does not exist",
+            "no such file",
+        ]) {
+            Self::NotFound(message)
+        } else if mentions(&[
+            "timeout",
+            "timed out",
+            "connection reset",
+            "connection refused",
+            "no such host",
+            "network is unreachable",
+            "temporary failure in name resolution",
+            "broken pipe",
+            "unexpected eof",
+            "tls handshake",
+        ]) {
+            Self::Network(message)
+        } else {
+            Self::Unknown(message)
+        }
+    }
+
+    /// Classifies the stderr of a failed rclone run with `--use-json-log`. The exit code settles
+    /// the cases the messages leave open.
+    pub fn from_output(stderr: &str, exit_code: Option<i32>) -> Self {
+        let lines = stderr
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .collect::<Vec<&str>>();
+        let error_messages = lines
+            .iter()
+            .filter_map(|line| match serde_json::from_str::<ImportedLogLine>(line) {
+                Ok(log_line) if log_line.is_error() => log_line.msg.map(|m| m.trim().to_owned()),
+                Ok(_) => None,
+                Err(_) => Some(line.trim().to_owned()),
+            })
+            .collect::<Vec<String>>();
+        let message = match error_messages.is_empty() {
+            true => lines
+                .iter()
+                .map(|line| log_message(line))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            false => error_messages.join("\n"),
+        };
+        // See https://rclone.org/docs/#exit-code
+        match (Self::from_message(&message), exit_code) {
+            (Self::Unknown(message), Some(3 | 4)) => Self::NotFound(message),
+            (Self::Unknown(message), Some(5)) => Self::Network(message),
+            (error, _) => error,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotFound(message)
+            | Self::PermissionDenied(message)
+            | Self::AuthExpired(message)
+            | Self::QuotaExceeded(message)
+            | Self::Network(message)
+            | Self::ConfigLocked(message)
+            | Self::Unknown(message) => message,
+        }
+    }
+
+    /// Whether running the operation again may succeed, as with dropped connections, rate limits
+    /// and server errors.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Network(_) => true,
+            Self::Unknown(message) => {
+                let lowercase_message = message.to_lowercase();
+                // Status codes only count as whole words, so that names like IMG_4291.jpg don't
+                // read as a rate limit
+                ["rate limit", "ratelimit", "too many requests"]
+                    .iter()
+                    .any(|p| lowercase_message.contains(p))
+                    || TRANSIENT_STATUS_CODE.is_match(message)
+            }
+            _ => false,
+        }
+    }
+
+    /// The same kind of error, with a different message.
+    pub fn with_message(&self, message: String) -> Self {
+        match self {
+            Self::NotFound(_) => Self::NotFound(message),
+            Self::PermissionDenied(_) => Self::PermissionDenied(message),
+            Self::AuthExpired(_) => Self::AuthExpired(message),
+            Self::QuotaExceeded(_) => Self::QuotaExceeded(message),
+            Self::Network(_) => Self::Network(message),
+            Self::ConfigLocked(_) => Self::ConfigLocked(message),
+            Self::Unknown(_) => Self::Unknown(message),
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "Not found",
+            Self::PermissionDenied(_) => "Permission denied",
+            Self::AuthExpired(_) => "Login expired",
+            Self::QuotaExceeded(_) => "Storage full",
+            Self::Network(_) => "Connection problem",
+            Self::ConfigLocked(_) => "Config locked",
+            Self::Unknown(_) => "Something went wrong",
+        }
+    }
+
+    /// A short explanation with a suggested fix, fit for an error dialog.
+    pub fn description(&self) -> String {
+        String::from(match self {
+            Self::NotFound(_) => "The file or folder may have been moved or deleted in the meantime. Refresh the folder and try again.",
+            Self::PermissionDenied(_) => "The account behind this remote is not allowed to do this. Check its permissions or the sharing settings of the folder.",
+            Self::AuthExpired(_) => "The login for this remote has expired or was revoked. Run 'rclone config reconnect' for this remote in a terminal to sign in again.",
+            Self::QuotaExceeded(_) => "The storage provider reports that there is no space left. Free up some space or upgrade your plan, then try again.",
+            Self::Network(_) => "The storage provider could not be reached. Check your internet connection and try again.",
+            Self::ConfigLocked(_) => "The rclone config could not be decrypted. Make sure the config password is correct.",
+            Self::Unknown(message) => {
+                return match message.lines().rev().find(|l| !l.trim().is_empty()) {
+                    Some(last_line) => format!("Rclone reported: {}", last_line.trim()),
+                    None => String::from("Rclone did not say what went wrong."),
+                }
+            }
+        })
+    }
+}
+
+impl Display for RcloneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n\n{}", self.title(), self.message())
+    }
+}
+
+fn command_did_not_start() -> RcloneError {
+    RcloneError::Unknown(String::from("Command did not start"))
+}
+
 #[derive(Debug)]
 pub enum MkdirError {
     NotAvailableHere,
-    Generic(String),
+    Generic(RcloneError),
+}
+
+/// What to look for in a recursive search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    /// A part of the name, or a glob like `*.pdf` that has to match the whole name.
+    pub pattern: String,
+    /// How many folder levels to go down, counting the one searched in. Unlimited if `None`.
+    pub max_depth: Option<u32>,
+}
+
+impl SearchQuery {
+    /// The pattern as an rclone filter rule.
+    pub fn glob(&self) -> String {
+        match self.pattern.contains(['*', '?', '[', '{']) {
+            true => self.pattern.clone(),
+            false => format!("*{}*", self.pattern),
+        }
+    }
+
+    /// Matches names the way the filter rule does, ignoring case. Rclone's filters let folders
+    /// through whatever their name, so those are checked with this.
+    pub fn name_regex(&self) -> Result<regex::Regex, RcloneError> {
+        regex::Regex::new(&format!("(?i){}", name_pattern(&self.pattern))).map_err(|_| {
+            RcloneError::Unknown(format!(
+                "\"{}\" is not a valid search pattern",
+                self.pattern
+            ))
+        })
+    }
+}
+
+/// A regex for names that contain `pattern`, or match it as a whole if it is a glob.
+pub fn name_pattern(pattern: &str) -> String {
+    match pattern.contains(['*', '?', '[', '{']) {
+        true => glob_to_regex(pattern),
+        false => regex::escape(pattern),
+    }
+}
+
+/// Translates a glob on a single name, as used by rclone's filters, into a regex.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let (mut in_class, mut in_alternation) = (false, false);
+    for c in glob.chars() {
+        match c {
+            ']' if in_class => {
+                in_class = false;
+                regex.push(c);
+            }
+            c if in_class => regex.push(c),
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                in_class = true;
+                regex.push(c);
+            }
+            '{' => {
+                in_alternation = true;
+                regex.push('(');
+            }
+            '}' if in_alternation => {
+                in_alternation = false;
+                regex.push(')');
+            }
+            ',' if in_alternation => regex.push('|'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// The settings of a remote as stored in the rclone config, including its `type`.
+pub type RemoteConfig = BTreeMap<String, String>;
+
+/// A storage backend as described by `rclone config providers`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RemoteProvider {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Description")]
+    pub description: String,
+    #[serde(rename = "Options", default)]
+    pub options: Vec<ProviderOption>,
+    #[serde(rename = "Hide", default)]
+    pub hide: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProviderOption {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Help", default)]
+    pub help: String,
+    /// Limits the option to some variants of the backend, like `AWS,Minio` or `!AWS`.
+    #[serde(rename = "Provider", default)]
+    pub provider: String,
+    #[serde(rename = "DefaultStr", default)]
+    pub default: String,
+    #[serde(rename = "Type", default)]
+    pub value_type: String,
+    #[serde(rename = "Hide", default)]
+    pub hide: u8,
+    #[serde(rename = "Required", default)]
+    pub required: bool,
+    #[serde(rename = "IsPassword", default)]
+    pub is_password: bool,
+    /// Whether the value is a secret, like an access key, that rclone stores as it is.
+    #[serde(rename = "Sensitive", default)]
+    pub sensitive: bool,
+    #[serde(rename = "Advanced", default)]
+    pub advanced: bool,
+    /// Whether only the examples are valid values.
+    #[serde(rename = "Exclusive", default)]
+    pub exclusive: bool,
+    #[serde(rename = "Examples", default)]
+    pub examples: Option<Vec<OptionExample>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct OptionExample {
+    #[serde(rename = "Value")]
+    pub value: String,
+    #[serde(rename = "Help", default)]
+    pub help: String,
+    #[serde(rename = "Provider", default)]
+    pub provider: String,
+}
+
+impl ProviderOption {
+    /// Whether the value must stay hidden on screen, as passwords and other secrets do.
+    pub fn is_secret(&self) -> bool {
+        self.is_password || self.sensitive
+    }
+
+    /// Whether `rclone config` would ask for this option, given the chosen variant of the
+    /// backend (the value of its `provider` option, if it has one).
+    pub fn is_shown_for(&self, provider: &str) -> bool {
+        self.hide == 0 && matches_provider(&self.provider, provider)
+    }
+
+    pub fn examples_for(&self, provider: &str) -> Vec<&OptionExample> {
+        self.examples
+            .iter()
+            .flatten()
+            .filter(|e| matches_provider(&e.provider, provider))
+            .collect()
+    }
+
+    /// The first line of the help text, short enough for a form label.
+    pub fn summary(&self) -> &str {
+        self.help.lines().next().unwrap_or(&self.name).trim()
+    }
+}
+
+/// Whether rclone accepts `name` as a remote name, which is written without the colon.
+pub fn is_valid_remote_name(name: &str) -> bool {
+    let pattern = regex::Regex::new(r"^[\w.+@]([\w.+@ -]*[\w.+@-])?$").unwrap();
+    pattern.is_match(name)
+}
+
+/// Mirrors the provider filter of `rclone config`.
+fn matches_provider(filter: &str, provider: &str) -> bool {
+    if filter.is_empty() || provider.is_empty() {
+        return true;
+    }
+    let (filter, negate) = match filter.strip_prefix('!') {
+        Some(stripped) => (stripped, true),
+        None => (filter, false),
+    };
+    filter.split(',').any(|p| p == provider) != negate
+}
+
+/// What rclone answers to a non-interactive `config create` or `config update`.
+#[derive(Deserialize)]
+pub(crate) struct ImportedConfigState {
+    #[serde(rename = "State", default)]
+    state: String,
+    #[serde(rename = "Error", default)]
+    error: String,
+}
+
+impl ImportedConfigState {
+    /// A remote that still has questions left, usually an OAuth login, can't be finished here.
+    pub(crate) fn into_result(self) -> Result<(), RcloneError> {
+        if !self.error.is_empty() {
+            Err(RcloneError::from_message(&self.error))
+        } else if !self.state.is_empty() {
+            Err(RcloneError::AuthExpired(String::from(
+                "The remote was saved, but it still needs you to sign in.",
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// How much a folder holds, counting everything below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct FolderSize {
+    /// The number of files, not counting folders.
+    pub count: i64,
+    pub bytes: i64,
+}
+
+impl FolderSize {
+    pub fn formatted_bytes(&self) -> String {
+        format!(
+            "{}B",
+            size_format::SizeFormatterSI::new(self.bytes.max(0) as u64)
+        )
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ImportedBackendFeatures {
+    #[serde(rename = "Hashes", default)]
+    pub hashes: Vec<String>,
+}
+
+/// The space on a remote as reported by `rclone about`. Backends leave out what they don't track.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub struct RemoteQuota {
+    pub total: Option<u64>,
+    pub used: Option<u64>,
+    pub free: Option<u64>,
+}
+
+impl RemoteQuota {
+    /// How full the remote is, if it reports enough to tell.
+    pub fn fraction_used(&self) -> Option<f64> {
+        let total = self.total.or_else(|| Some(self.used? + self.free?))?;
+        let used = self.used.or_else(|| total.checked_sub(self.free?))?;
+        match total {
+            0 => None,
+            total => Some((used as f64 / total as f64).min(1.0)),
+        }
+    }
+
+    /// A short line about the space left, or used if that is all the remote tells.
+    pub fn summary(&self) -> Option<String> {
+        let format = |bytes: u64| format!("{}B", size_format::SizeFormatterSI::new(bytes));
+        match (self.used, self.free, self.total) {
+            (_, Some(free), Some(total)) => {
+                Some(format!("{} free of {}", format(free), format(total)))
+            }
+            (Some(used), None, Some(total)) => {
+                Some(format!("{} of {} used", format(used), format(total)))
+            }
+            (_, Some(free), None) => Some(format!("{} free", format(free))),
+            (Some(used), None, None) => Some(format!("{} used", format(used))),
+            _ => None,
+        }
+    }
+}
+
+/// Picks the checksum out of `hashsum` output, which lists "<checksum>  <name>" per file.
+pub(crate) fn parse_hashsum(output: &str) -> Result<String, RcloneError> {
+    match output.split_whitespace().next() {
+        Some(checksum) if checksum != "UNSUPPORTED" => Ok(checksum.to_owned()),
+        _ => Err(RcloneError::Unknown(String::from(
+            "The remote did not return a checksum for this file.",
+        ))),
+    }
+}
+
+/// The file operations the app needs from a storage backend.
+pub trait StorageClient: Debug + Send + Sync {
+    fn list_remotes(&self) -> Result<Vec<String>, RcloneError>;
+
+    fn ls(&self, path: &RclonePath) -> Result<Vec<RcloneFileListing>, RcloneError>;
+
+    fn copy(
+        &self,
+        source_path: &RclonePath,
+        target_path: &RclonePath,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError>;
+
+    fn mv(
+        &self,
+        source_path: &RclonePath,
+        target_path: &RclonePath,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError>;
+
+    fn rename(
+        &self,
+        source_path: &RclonePath,
+        new_filename: &str,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError>;
+
+    fn rm(
+        &self,
+        path: &RclonePath,
+        is_dir: bool,
+        process: &ProcessHandle,
+    ) -> Result<(), RcloneError>;
+
+    fn mkdir(&self, path: &RclonePath) -> Result<(), MkdirError>;
+
+    /// Reads at most `max_bytes` from the start of a file.
+    fn cat(&self, path: &RclonePath, max_bytes: u64) -> Result<Vec<u8>, RcloneError>;
+
+    /// Everything the backend knows about a file or folder, including its metadata.
+    fn stat(&self, path: &RclonePath) -> Result<FileMetadata, RcloneError>;
+
+    /// Looks through `path` and the folders below it for names that match `query`, reporting
+    /// hits as they come in.
+    fn search(
+        &self,
+        path: &RclonePath,
+        query: &SearchQuery,
+        process: &ProcessHandle,
+        on_hit: &dyn Fn(RcloneFileListing),
+    ) -> Result<(), RcloneError>;
+
+    /// The checksums the remote behind `path` can compute, like "md5" or "sha1".
+    fn hash_types(&self, path: &RclonePath) -> Result<Vec<String>, RcloneError>;
+
+    /// Computes the checksum of a file, as a lowercase hex string.
+    fn hashsum(&self, path: &RclonePath, hash_type: &str) -> Result<String, RcloneError>;
+
+    /// Counts the files below a folder and adds up their sizes.
+    fn size(&self, path: &RclonePath) -> Result<FolderSize, RcloneError>;
+
+    /// The space used and left on a remote, named with the colon. Not every backend can tell.
+    fn about(&self, remote: &str) -> Result<RemoteQuota, RcloneError>;
+
+    /// The storage backends rclone can set up, along with their options.
+    fn providers(&self) -> Result<Vec<RemoteProvider>, RcloneError>;
+
+    /// The stored settings of a remote, named without the colon. Passwords come back obscured.
+    fn remote_config(&self, remote: &str) -> Result<RemoteConfig, RcloneError>;
+
+    /// Adds a remote. `values_obscured` tells whether the passwords among `values` are already
+    /// obscured, as they are when copied from another remote.
+    fn create_remote(
+        &self,
+        remote: &str,
+        provider: &str,
+        values: &RemoteConfig,
+        values_obscured: bool,
+    ) -> Result<(), RcloneError>;
+
+    fn update_remote(&self, remote: &str, values: &RemoteConfig) -> Result<(), RcloneError>;
+
+    fn delete_remote(&self, remote: &str) -> Result<(), RcloneError>;
+
+    /// Rclone can't rename remotes, so this sets up a copy under the new name and deletes the
+    /// old one.
+    fn rename_remote(&self, remote: &str, new_name: &str) -> Result<(), RcloneError> {
+        let mut values = self.remote_config(remote)?;
+        let provider = values.remove("type").ok_or_else(|| {
+            RcloneError::Unknown(format!("Remote {} has no type in the config", remote))
+        })?;
+        self.create_remote(new_name, &provider, &values, true)?;
+        self.delete_remote(remote)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -74,7 +790,7 @@ impl RcloneClient {
     pub fn new(
         password: Option<String>,
         custom_config_path: Option<String>,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, RcloneError> {
         let client = RcloneClient {
             password,
             custom_config_path,
@@ -82,10 +798,17 @@ impl RcloneClient {
         client.list_remotes().map(|_| client)
     }
 
+    /// Starts an `rclone rcd` process that uses the same config as this client.
+    pub fn start_rcd(&self) -> Result<RcdClient, RcloneError> {
+        RcdClient::start(|| self.build_command())
+    }
+
     fn build_command(&self) -> Command {
         let mut cmd = Command::new("rclone");
         if let Some(password) = self.password.clone() {
-            cmd.env("RCLONE_PASSWORD_COMMAND", format!("echo \"{}\"", password))
+            // Read by rclone itself, so the password never passes through a shell
+            cmd.env("RCLONE_CONFIG_PASS", password)
+                .env_remove("RCLONE_PASSWORD_COMMAND")
                 .stdin(Stdio::null());
         }
         if let Some(custom_config_path) = self.custom_config_path.clone() {
@@ -94,24 +817,118 @@ impl RcloneClient {
         cmd
     }
 
-    pub fn is_password_required(custom_config_path: &Option<String>) -> Result<bool, String> {
+    /// Starts a command that `process` can stop, and hands over its output streams.
+    fn spawn_tracked(
+        &self,
+        args: &[&str],
+        stdout: Stdio,
+        process: &ProcessHandle,
+    ) -> Result<(Option<ChildStdout>, Option<ChildStderr>), RcloneError> {
+        let mut state = process.state.lock().unwrap();
+        if state.cancelled {
+            return Err(RcloneError::Unknown(String::from("Cancelled before start")));
+        }
+        let mut child = self
+            .build_command()
+            .args(args)
+            .arg("--use-json-log")
+            .stdout(stdout)
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|_| command_did_not_start())?;
+        let streams = (child.stdout.take(), child.stderr.take());
+        state.child = Some(child);
+        Ok(streams)
+    }
+
+    /// Waits for a command started with `spawn_tracked` to exit, and classifies its log if it
+    /// failed.
+    fn wait_tracked(process: &ProcessHandle, log_lines: &[String]) -> Result<(), RcloneError> {
+        let mut child = process
+            .state
+            .lock()
+            .unwrap()
+            .child
+            .take()
+            .expect("Job process went missing");
+        let status = child
+            .wait()
+            .map_err(|_| RcloneError::Unknown(String::from("Command did not finish")))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(RcloneError::from_output(
+                &log_lines.join("\n"),
+                status.code(),
+            ))
+        }
+    }
+
+    /// Runs a job command, reporting rclone's periodic stats as they come in.
+    fn run_job(
+        &self,
+        args: &[&str],
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        let args = [args, &["--stats=1s", "--stats-log-level=NOTICE"]].concat();
+        let (_, stderr) = self.spawn_tracked(&args, Stdio::null(), process)?;
+
+        let mut log_lines = vec![];
+        if let Some(stderr) = stderr {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                match TransferStats::from_log_line(&line) {
+                    Some(stats) => on_progress(stats),
+                    None => log_lines.push(line),
+                }
+            }
+        }
+        Self::wait_tracked(process, &log_lines)
+    }
+
+    /// Runs a command and returns what it printed.
+    fn read_output(&self, args: &[&str]) -> Result<Vec<u8>, RcloneError> {
+        let output = self
+            .build_command()
+            .args(args)
+            .arg("--use-json-log")
+            .output()
+            .map_err(|_| command_did_not_start())?;
+        match output.status.success() {
+            true => Ok(output.stdout),
+            false => Err(RcloneError::from_output(
+                &String::from_utf8_lossy(&output.stderr),
+                output.status.code(),
+            )),
+        }
+    }
+
+    /// Runs a command that prints JSON, and decodes it.
+    fn read_json<T: serde::de::DeserializeOwned>(&self, args: &[&str]) -> Result<T, RcloneError> {
+        let output = self.read_output(args)?;
+        serde_json::from_slice(&output).map_err(|err| {
+            RcloneError::Unknown(format!("Could not decode the output of rclone. {}", err))
+        })
+    }
+
+    pub fn is_password_required(custom_config_path: &Option<String>) -> Result<bool, RcloneError> {
         let mut cmd = Command::new("rclone");
         cmd.args(["config", "show"]).stdin(Stdio::null());
         if let Some(custom_config_path) = custom_config_path.clone() {
             cmd.args([format!("--config={}", &custom_config_path)]);
         }
-        let output = cmd
-            .output()
-            .map_err(|_| "Password requirement check could not start")?;
+        let output = cmd.output().map_err(|_| command_did_not_start())?;
         Ok(!output.status.success())
     }
+}
 
-    pub fn list_remotes(&self) -> Result<Vec<String>, String> {
+impl StorageClient for RcloneClient {
+    fn list_remotes(&self) -> Result<Vec<String>, RcloneError> {
         let output = self
             .build_command()
             .args(["listremotes", "--use-json-log"])
             .output()
-            .map_err(|_| "Command did not start")?;
+            .map_err(|_| command_did_not_start())?;
 
         if output.status.success() {
             let raw_remotes = from_utf8(&output.stdout)
@@ -126,138 +943,119 @@ impl RcloneClient {
             );
             Ok(remotes)
         } else {
-            Err(format!("Rclone command failed - {}", output.status))
+            Err(RcloneError::from_output(
+                &String::from_utf8_lossy(&output.stderr),
+                output.status.code(),
+            ))
         }
     }
 
-    pub fn ls(&self, path: &RclonePath) -> Result<Vec<RcloneFileListing>, String> {
+    fn ls(&self, path: &RclonePath) -> Result<Vec<RcloneFileListing>, RcloneError> {
         let output = self
             .build_command()
-            .args(["lsjson", &path.to_string()])
+            .args(["lsjson", "--use-json-log", &path.to_string()])
             .output()
-            .map_err(|_| "Command did not start")?;
+            .map_err(|_| command_did_not_start())?;
 
         if output.status.success() {
             let utf8_output = from_utf8(&output.stdout)
                 .expect("Rclone output encode to UTF8 failed")
                 .to_owned();
             let imported_listings: Vec<ImportedFileListing> = serde_json::from_str(&utf8_output)
-                .unwrap_or_else(|_| panic!("Could not decode {}", utf8_output));
+                .map_err(|err| {
+                    RcloneError::Unknown(format!("Could not decode the file listing. {}", err))
+                })?;
             let rclone_listings = imported_listings
                 .iter()
                 .map(|l| RcloneFileListing::from(l, path))
                 .collect();
             Ok(rclone_listings)
         } else {
-            Err(format!(
-                "Rclone command failed with {}\n\n{}",
-                output.status,
-                from_utf8(&output.stderr).unwrap(),
+            Err(RcloneError::from_output(
+                &String::from_utf8_lossy(&output.stderr),
+                output.status.code(),
             ))
         }
     }
 
-    pub fn copy(&self, source_path: &RclonePath, target_path: &RclonePath) -> Result<(), String> {
-        let output = self
-            .build_command()
-            .args(["copyto", &source_path.to_string(), &target_path.to_string()])
-            .output()
-            .map_err(|_| "Command did not start")?;
-
-        if output.status.success() {
-            Ok(())
-        } else {
-            Err(format!(
-                "Copy failed with {}\n\n{}",
-                output.status,
-                from_utf8(&output.stderr).unwrap(),
-            ))
-        }
+    fn copy(
+        &self,
+        source_path: &RclonePath,
+        target_path: &RclonePath,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        self.run_job(
+            &["copyto", &source_path.to_string(), &target_path.to_string()],
+            process,
+            on_progress,
+        )
     }
 
-    pub fn mv(&self, source_path: &RclonePath, target_path: &RclonePath) -> Result<(), String> {
-        let target_directory = target_path.resolve_to_parent();
-        let output = self
-            .build_command()
-            .args([
-                "move",
-                &source_path.to_string(),
-                &target_directory.to_string(),
-            ])
-            .output()
-            .map_err(|_| "Command did not start")?;
-
-        if output.status.success() {
-            Ok(())
-        } else {
-            println!("{}", from_utf8(&output.stderr).unwrap());
-            Err(format!(
-                "Copy failed with {}\n\n{}",
-                output.status,
-                from_utf8(&output.stderr).unwrap(),
-            ))
-        }
+    fn mv(
+        &self,
+        source_path: &RclonePath,
+        target_path: &RclonePath,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        // Unlike `move`, `moveto` puts a folder itself at the target, not just its contents
+        self.run_job(
+            &["moveto", &source_path.to_string(), &target_path.to_string()],
+            process,
+            on_progress,
+        )
     }
 
-    pub fn rename(&self, source_path: &RclonePath, new_filename: &str) -> Result<(), String> {
+    fn rename(
+        &self,
+        source_path: &RclonePath,
+        new_filename: &str,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
         let target_path = source_path.resolve_to_parent().join(new_filename);
-        let output = self
-            .build_command()
-            .args(["moveto", &source_path.to_string(), &target_path.to_string()])
-            .output()
-            .map_err(|_| "Command did not start")?;
-
-        if output.status.success() {
-            Ok(())
-        } else {
-            println!("{}", from_utf8(&output.stderr).unwrap());
-            Err(format!(
-                "Copy failed with {}\n\n{}",
-                output.status,
-                from_utf8(&output.stderr).unwrap(),
-            ))
-        }
+        self.run_job(
+            &["moveto", &source_path.to_string(), &target_path.to_string()],
+            process,
+            on_progress,
+        )
     }
 
-    pub fn rm(&self, path: &RclonePath, is_dir: bool) -> Result<(), String> {
-        let output = self
-            .build_command()
-            .args([
+    fn rm(
+        &self,
+        path: &RclonePath,
+        is_dir: bool,
+        process: &ProcessHandle,
+    ) -> Result<(), RcloneError> {
+        self.run_job(
+            &[
                 match is_dir {
                     true => "purge",
                     false => "delete",
                 },
                 &path.to_string(),
-            ])
-            .output()
-            .map_err(|_| "Command did not start")?;
-
-        match output.status.success() {
-            true => Ok(()),
-            false => Err(format!(
-                "Rclone command failed with {}\n\n{}",
-                output.status,
-                from_utf8(&output.stderr).unwrap(),
-            )),
-        }
+            ],
+            process,
+            &|_| {},
+        )
     }
 
-    pub fn mkdir(&self, path: &RclonePath) -> Result<(), MkdirError> {
+    fn mkdir(&self, path: &RclonePath) -> Result<(), MkdirError> {
         let output = self
             .build_command()
-            .args(["mkdir", &path.to_string()])
+            .args(["mkdir", "--use-json-log", &path.to_string()])
             .output()
-            .map_err(|_| MkdirError::Generic(String::from("Command did not start")))?;
+            .map_err(|_| MkdirError::Generic(command_did_not_start()))?;
 
+        let stderr_str = String::from_utf8_lossy(&output.stderr);
         if !output.status.success() {
-            return Err(MkdirError::Generic(format!(
-                "Rclone command failed with {}\n\n{}",
-                output.status,
-                from_utf8(&output.stderr).unwrap()
+            return Err(MkdirError::Generic(RcloneError::from_output(
+                &stderr_str,
+                output.status.code(),
             )));
         }
 
-        let stderr_str = from_utf8(&output.stderr).expect("UTF8 decode failed");
         match stderr_str.contains(
             "Warning: running mkdir on a remote which can't have empty directories does nothing",
         ) {
@@ -265,4 +1063,463 @@ impl RcloneClient {
             false => Ok(()),
         }
     }
+
+    fn cat(&self, path: &RclonePath, max_bytes: u64) -> Result<Vec<u8>, RcloneError> {
+        self.read_output(&["cat", "--count", &max_bytes.to_string(), &path.to_string()])
+    }
+
+    fn stat(&self, path: &RclonePath) -> Result<FileMetadata, RcloneError> {
+        let value: Value =
+            self.read_json(&["lsjson", "--stat", "--metadata", &path.to_string()])?;
+        Ok(flatten_metadata(&value))
+    }
+
+    fn hash_types(&self, path: &RclonePath) -> Result<Vec<String>, RcloneError> {
+        let remote = path.remote().unwrap_or_else(|| path.to_string());
+        let features: ImportedBackendFeatures =
+            self.read_json(&["backend", "features", &remote])?;
+        Ok(features.hashes)
+    }
+
+    fn hashsum(&self, path: &RclonePath, hash_type: &str) -> Result<String, RcloneError> {
+        let output = self.read_output(&["hashsum", hash_type, &path.to_string()])?;
+        parse_hashsum(&String::from_utf8_lossy(&output))
+    }
+
+    fn size(&self, path: &RclonePath) -> Result<FolderSize, RcloneError> {
+        self.read_json(&["size", "--json", &path.to_string()])
+    }
+
+    fn about(&self, remote: &str) -> Result<RemoteQuota, RcloneError> {
+        self.read_json(&["about", "--json", remote])
+    }
+
+    fn search(
+        &self,
+        path: &RclonePath,
+        query: &SearchQuery,
+        process: &ProcessHandle,
+        on_hit: &dyn Fn(RcloneFileListing),
+    ) -> Result<(), RcloneError> {
+        let name_regex = query.name_regex()?;
+        let (glob, path_string) = (query.glob(), path.to_string());
+        let max_depth = query.max_depth.map(|depth| depth.to_string());
+        let mut args = vec!["lsjson", "--recursive", "--ignore-case", "--include", &glob];
+        if let Some(max_depth) = &max_depth {
+            args.extend(["--max-depth", max_depth]);
+        }
+        args.push(&path_string);
+        let (stdout, stderr) = self.spawn_tracked(&args, Stdio::piped(), process)?;
+
+        // Read on the side, so a long log can't hold up the listing
+        let log_reader = thread::spawn(move || match stderr {
+            Some(stderr) => BufReader::new(stderr)
+                .lines()
+                .map_while(Result::ok)
+                .collect(),
+            None => vec![],
+        });
+        if let Some(stdout) = stdout {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                match parse_lsjson_line(&line) {
+                    Some(listing) if name_regex.is_match(&listing.name) => {
+                        on_hit(RcloneFileListing::from(&listing, path))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let log_lines = log_reader.join().unwrap_or_default();
+        Self::wait_tracked(process, &log_lines)
+    }
+
+    fn providers(&self) -> Result<Vec<RemoteProvider>, RcloneError> {
+        self.read_json(&["config", "providers"])
+    }
+
+    fn remote_config(&self, remote: &str) -> Result<RemoteConfig, RcloneError> {
+        let mut remotes: BTreeMap<String, RemoteConfig> = self.read_json(&["config", "dump"])?;
+        remotes
+            .remove(remote)
+            .ok_or_else(|| RcloneError::NotFound(format!("Remote {} not found", remote)))
+    }
+
+    fn create_remote(
+        &self,
+        remote: &str,
+        provider: &str,
+        values: &RemoteConfig,
+        values_obscured: bool,
+    ) -> Result<(), RcloneError> {
+        // As `key=value` arguments, passwords and access keys would show up in the process list,
+        // so they go through the JSON body of a short-lived rclone rcd instead
+        self.start_rcd()?
+            .create_remote(remote, provider, values, values_obscured)
+    }
+
+    fn update_remote(&self, remote: &str, values: &RemoteConfig) -> Result<(), RcloneError> {
+        self.start_rcd()?.update_remote(remote, values)
+    }
+
+    fn delete_remote(&self, remote: &str) -> Result<(), RcloneError> {
+        let output = self
+            .build_command()
+            .args(["config", "delete", remote, "--use-json-log"])
+            .output()
+            .map_err(|_| command_did_not_start())?;
+        match output.status.success() {
+            true => Ok(()),
+            false => Err(RcloneError::from_output(
+                &String::from_utf8_lossy(&output.stderr),
+                output.status.code(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsStr, process::Command};
+
+    use serde_json::json;
+
+    use crate::client::{
+        flatten_metadata, is_valid_remote_name, log_message, matches_provider, parse_hashsum,
+        parse_lsjson_line, FolderSize, ImportedBackendFeatures, ImportedConfigState, ProcessHandle,
+        RcloneClient, RcloneError, RemoteProvider, RemoteQuota, SearchQuery, StorageClient,
+        TransferStats,
+    };
+    use crate::{path_tools::RclonePath, rcd::RcdClient};
+    use test_case::test_case;
+
+    #[test_case("report", "Q3 Report.pdf", true ; "part of the name")]
+    #[test_case("a+b", "a+b.txt", true ; "special characters")]
+    #[test_case("report", "summary.pdf", false ; "other name")]
+    #[test_case("*.pdf", "scan.PDF", true ; "glob ignores case")]
+    #[test_case("*.pdf", "scan.pdf.txt", false ; "glob matches whole name")]
+    #[test_case("photo_??.jpg", "photo_01.jpg", true ; "single characters")]
+    #[test_case("*.{jpg,png}", "cat.png", true ; "alternatives")]
+    #[test_case("[ab]*", "cat.png", false ; "character class")]
+    fn search_query_matches_names(pattern: &str, name: &str, expected: bool) {
+        let query = SearchQuery {
+            pattern: String::from(pattern),
+            max_depth: None,
+        };
+        assert_eq!(query.name_regex().unwrap().is_match(name), expected);
+    }
+
+    #[test]
+    fn search_query_glob() {
+        let query = |pattern: &str| SearchQuery {
+            pattern: String::from(pattern),
+            max_depth: Some(2),
+        };
+        assert_eq!(query("report").glob(), "*report*");
+        assert_eq!(query("*.pdf").glob(), "*.pdf");
+    }
+
+    #[test]
+    fn lsjson_lines() {
+        assert!(parse_lsjson_line("[").is_none());
+        assert!(parse_lsjson_line("]").is_none());
+        let listing = parse_lsjson_line(
+            r#"{"Path":"docs/a.txt","Name":"a.txt","Size":3,"MimeType":"text/plain","ModTime":"2024-03-01T10:00:00Z","IsDir":false},"#,
+        )
+        .unwrap();
+        assert_eq!(listing.path, "docs/a.txt");
+    }
+
+    #[test]
+    fn metadata_is_flattened() {
+        let metadata = flatten_metadata(&json!({
+            "Name": "a.txt",
+            "Size": 3,
+            "IsDir": false,
+            "Tier": null,
+            "Hashes": { "md5": "900150983cd24fb0d6963f7d28e17f72" },
+            "Metadata": { "mtime": "2024-03-01T10:00:00Z", "tags": ["red", "blue"] },
+        }));
+        assert_eq!(metadata["Name"], "a.txt");
+        assert_eq!(metadata["Size"], "3");
+        assert_eq!(metadata["IsDir"], "false");
+        assert!(!metadata.contains_key("Tier"));
+        assert_eq!(metadata["Hashes.md5"], "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(metadata["Metadata.tags"], "red, blue");
+    }
+
+    #[test_case("d41d8cd98f00b204e9800998ecf8427e  a.txt\n", Some("d41d8cd98f00b204e9800998ecf8427e") ; "checksum")]
+    #[test_case("UNSUPPORTED  a.txt\n", None ; "unsupported")]
+    #[test_case("", None ; "no output")]
+    fn hashsum_output(output: &str, expected: Option<&str>) {
+        assert_eq!(parse_hashsum(output).ok().as_deref(), expected);
+    }
+
+    #[test]
+    fn backend_features_and_size_from_json() {
+        let features: ImportedBackendFeatures =
+            serde_json::from_value(json!({ "Name": "foo", "Hashes": ["md5", "sha1"] })).unwrap();
+        assert_eq!(features.hashes, vec!["md5", "sha1"]);
+        let size: FolderSize =
+            serde_json::from_value(json!({ "count": 3, "bytes": 2048, "sizeless": 0 })).unwrap();
+        assert_eq!(
+            size,
+            FolderSize {
+                count: 3,
+                bytes: 2048
+            }
+        );
+        assert_eq!(size.formatted_bytes(), "2.0kB");
+    }
+
+    #[test]
+    fn quota_from_json() {
+        let quota: RemoteQuota = serde_json::from_value(json!({
+            "total": 15_000_000_000u64,
+            "used": 5_000_000_000u64,
+            "trashed": 10,
+            "free": 10_000_000_000u64,
+        }))
+        .unwrap();
+        assert_eq!(quota.fraction_used(), Some(1.0 / 3.0));
+        assert_eq!(quota.summary().as_deref(), Some("10.0GB free of 15.0GB"));
+    }
+
+    #[test_case(RemoteQuota { total: None, used: Some(25), free: Some(75) }, Some(0.25), Some("75B free") ; "no total")]
+    #[test_case(RemoteQuota { total: Some(100), used: None, free: Some(75) }, Some(0.25), Some("75B free of 100B") ; "no used")]
+    #[test_case(RemoteQuota { total: Some(100), used: Some(40), free: None }, Some(0.4), Some("40B of 100B used") ; "no free")]
+    #[test_case(RemoteQuota { total: None, used: Some(40), free: None }, None, Some("40B used") ; "only used")]
+    #[test_case(RemoteQuota::default(), None, None ; "nothing known")]
+    fn partial_quota(quota: RemoteQuota, fraction: Option<f64>, summary: Option<&str>) {
+        assert_eq!(quota.fraction_used(), fraction);
+        assert_eq!(quota.summary().as_deref(), summary);
+    }
+
+    #[test]
+    fn transfer_stats_from_stats_line() {
+        let line = r#"{"level":"notice","msg":"\nTransferred: 1 MiB / 4 MiB, 25%\n","stats":{"bytes":1048576,"checks":0,"elapsedTime":1.5,"errors":0,"eta":3,"speed":699050.6,"totalBytes":4194304,"transfers":0},"time":"2024-06-01T12:00:00.000000+02:00"}"#;
+        let stats = TransferStats::from_log_line(line).unwrap();
+        assert_eq!(stats.bytes, 1048576);
+        assert_eq!(stats.total_bytes, 4194304);
+        assert_eq!(stats.eta, Some(3));
+        assert_eq!(stats.fraction(), 0.25);
+    }
+
+    #[test]
+    fn transfer_stats_without_eta() {
+        let line = r#"{"level":"notice","msg":"","stats":{"bytes":0,"eta":null,"speed":0,"totalBytes":0}}"#;
+        let stats = TransferStats::from_log_line(line).unwrap();
+        assert_eq!(stats.eta, None);
+        assert_eq!(stats.fraction(), 0.0);
+        assert_eq!(stats.formatted_eta(), None);
+    }
+
+    #[test]
+    fn transfer_stats_from_regular_log_line() {
+        let line = r#"{"level":"error","msg":"Failed to copy: directory not found","source":"slog/logger.go:256"}"#;
+        assert_eq!(TransferStats::from_log_line(line), None);
+        assert_eq!(TransferStats::from_log_line("not json"), None);
+    }
+
+    #[test]
+    fn formatted_eta() {
+        let mut stats = TransferStats {
+            bytes: 0,
+            total_bytes: 0,
+            speed: 0.0,
+            eta: Some(42),
+        };
+        assert_eq!(stats.formatted_eta(), Some(String::from("42s")));
+        stats.eta = Some(125);
+        assert_eq!(stats.formatted_eta(), Some(String::from("2m 05s")));
+        stats.eta = Some(3 * 3600 + 7 * 60 + 9);
+        assert_eq!(stats.formatted_eta(), Some(String::from("3h 07m")));
+    }
+
+    #[test]
+    fn log_message_from_json_line() {
+        let line = r#"{"level":"error","msg":"Failed to copy: permission denied\n"}"#;
+        assert_eq!(log_message(line), "Failed to copy: permission denied");
+        assert_eq!(log_message("plain text"), "plain text");
+    }
+
+    #[test_case("Failed to copy: directory not found", RcloneError::NotFound(String::new()); "not found")]
+    #[test_case("open /root/x: permission denied", RcloneError::PermissionDenied(String::new()); "permission denied")]
+    #[test_case("couldn't fetch token: invalid_grant: maybe token expired?", RcloneError::AuthExpired(String::new()); "auth expired")]
+    #[test_case("googleapi: Error 403: The user's Drive storage quota has been exceeded., storageQuotaExceeded", RcloneError::QuotaExceeded(String::new()); "quota exceeded")]
+    #[test_case("dial tcp: lookup example.com: no such host", RcloneError::Network(String::new()); "network")]
+    #[test_case("Couldn't decrypt configuration, most likely wrong password.", RcloneError::ConfigLocked(String::new()); "config locked")]
+    #[test_case("something else entirely", RcloneError::Unknown(String::new()); "unknown")]
+    fn rclone_error_categories(message: &str, expected: RcloneError) {
+        assert_eq!(
+            std::mem::discriminant(&RcloneError::from_message(message)),
+            std::mem::discriminant(&expected)
+        );
+    }
+
+    #[test_case("Failed to copy: Post \"https://example.com\": dial tcp: i/o timeout"; "timeout")]
+    #[test_case("googleapi: Error 429: Rate Limit Exceeded, rateLimitExceeded"; "rate limit")]
+    #[test_case("read tcp 10.0.0.2:51234->1.2.3.4:443: read: connection reset by peer"; "reset")]
+    #[test_case("HTTP error 503 (503 Service Unavailable) returned body"; "unavailable")]
+    fn transient_errors(message: &str) {
+        assert!(RcloneError::from_message(message).is_transient());
+    }
+
+    #[test_case("Failed to copy: directory not found"; "not found")]
+    #[test_case("couldn't decrypt config: wrong password"; "password")]
+    #[test_case("IMG_4291.jpg: permission denied"; "permission")]
+    #[test_case("IMG_4291.jpg: corrupted on transfer: sizes differ 5003 vs 5002"; "number in path")]
+    fn permanent_errors(message: &str) {
+        assert!(!RcloneError::from_message(message).is_transient());
+    }
+
+    #[test]
+    fn rclone_error_from_json_log() {
+        let stderr = [
+            r#"{"level":"notice","msg":"Config file not found - using defaults"}"#,
+            r#"{"level":"error","msg":"file.txt: Failed to copy: permission denied\n"}"#,
+            r#"{"level":"critical","msg":"Attempt 1/3 failed with 1 errors"}"#,
+        ]
+        .join("\n");
+        assert_eq!(
+            RcloneError::from_output(&stderr, Some(1)),
+            RcloneError::PermissionDenied(String::from(
+                "file.txt: Failed to copy: permission denied\nAttempt 1/3 failed with 1 errors"
+            ))
+        );
+    }
+
+    #[test]
+    fn rclone_error_falls_back_to_exit_code() {
+        let stderr = r#"{"level":"error","msg":"error reading source root directory"}"#;
+        assert!(matches!(
+            RcloneError::from_output(stderr, Some(3)),
+            RcloneError::NotFound(_)
+        ));
+        assert!(matches!(
+            RcloneError::from_output(stderr, Some(1)),
+            RcloneError::Unknown(_)
+        ));
+    }
+
+    #[test]
+    fn rclone_error_description_of_unknown_error() {
+        let error = RcloneError::Unknown(String::from("first\nsecond\n"));
+        assert_eq!(error.description(), "Rclone reported: second");
+    }
+
+    #[test]
+    fn password_is_passed_verbatim() {
+        let password = r#"a "quoted" $pass`word`"#;
+        let client = RcloneClient {
+            password: Some(String::from(password)),
+            custom_config_path: None,
+        };
+        let cmd = client.build_command();
+        let envs = cmd.get_envs().collect::<Vec<_>>();
+        assert!(envs.contains(&(OsStr::new("RCLONE_CONFIG_PASS"), Some(OsStr::new(password)))));
+        assert!(envs.contains(&(OsStr::new("RCLONE_PASSWORD_COMMAND"), None)));
+    }
+
+    /// Moving a folder puts the folder itself at the target rather than merging its contents into
+    /// the target's parent, which is what `rclone move` would do. Both backends have to agree.
+    #[test]
+    #[ignore = "needs rclone"]
+    fn mv_moves_folder_itself() {
+        let clients: Vec<(&str, Box<dyn StorageClient>)> = vec![
+            (
+                "cli",
+                Box::new(RcloneClient {
+                    password: None,
+                    custom_config_path: None,
+                }),
+            ),
+            (
+                "rcd",
+                Box::new(RcdClient::start(|| Command::new("rclone")).unwrap()),
+            ),
+        ];
+        for (backend, client) in clients {
+            let root = std::env::temp_dir().join(format!(
+                "rclone-shuttle-mv-{}-{}",
+                backend,
+                std::process::id()
+            ));
+            std::fs::create_dir_all(root.join("folder/sub")).unwrap();
+            std::fs::write(root.join("folder/sub/a.txt"), "hello").unwrap();
+            std::fs::create_dir_all(root.join("target")).unwrap();
+            let root_path = RclonePath::from(root.to_str().unwrap());
+
+            client
+                .mv(
+                    &root_path.join("folder"),
+                    &root_path.join("target/folder"),
+                    &ProcessHandle::default(),
+                    &|_| {},
+                )
+                .unwrap();
+            assert_eq!(
+                std::fs::read_to_string(root.join("target/folder/sub/a.txt")).unwrap(),
+                "hello",
+                "{}",
+                backend
+            );
+            assert!(!root.join("target/sub").exists(), "{}", backend);
+            assert!(!root.join("folder/sub/a.txt").exists(), "{}", backend);
+            std::fs::remove_dir_all(&root).unwrap();
+        }
+    }
+
+    #[test_case("gdrive", true ; "plain")]
+    #[test_case("my drive-2", true ; "space and dash")]
+    #[test_case("", false ; "empty")]
+    #[test_case("-drive", false ; "leading dash")]
+    #[test_case("drive ", false ; "trailing space")]
+    #[test_case("dri:ve", false ; "colon")]
+    fn remote_name_validation(name: &str, expected: bool) {
+        assert_eq!(is_valid_remote_name(name), expected);
+    }
+
+    #[test_case("", "AWS", true ; "no filter")]
+    #[test_case("AWS,Minio", "Minio", true ; "listed")]
+    #[test_case("AWS,Minio", "Ceph", false ; "not listed")]
+    #[test_case("!AWS,Minio", "Ceph", true ; "negated and not listed")]
+    #[test_case("!AWS,Minio", "AWS", false ; "negated and listed")]
+    #[test_case("AWS", "", true ; "no provider chosen yet")]
+    fn provider_filter(filter: &str, provider: &str, expected: bool) {
+        assert_eq!(matches_provider(filter, provider), expected);
+    }
+
+    #[test]
+    fn providers_from_json() {
+        let raw = r#"[{"Name":"s3","Description":"Amazon S3 Compliant Storage Providers","Prefix":"s3","Options":[
+            {"Name":"provider","Help":"Choose your S3 provider.","Provider":"","Default":"","DefaultStr":"","Examples":[{"Value":"AWS","Help":"Amazon Web Services (AWS) S3","Provider":""}],"Hide":0,"Required":false,"IsPassword":false,"Advanced":false,"Exclusive":false,"Type":"string"},
+            {"Name":"secret_access_key","Help":"AWS Secret Access Key (password).\n\nLeave blank for anonymous access.","Provider":"","Default":"","DefaultStr":"","Examples":null,"Hide":0,"Required":false,"IsPassword":false,"Sensitive":true,"Advanced":false,"Exclusive":false,"Type":"string"},
+            {"Name":"region","Help":"Region to connect to.","Provider":"!AWS","Default":"","DefaultStr":"","Hide":0,"Required":false,"IsPassword":false,"Advanced":false,"Exclusive":false,"Type":"string"},
+            {"Name":"upload_cutoff","Help":"Cutoff for switching to chunked upload.","Default":209715200,"DefaultStr":"200Mi","Hide":0,"Required":false,"IsPassword":false,"Advanced":true,"Exclusive":false,"Type":"SizeSuffix"}
+        ],"CommandHelp":null,"Aliases":null,"Hide":false}]"#;
+        let providers: Vec<RemoteProvider> = serde_json::from_str(raw).unwrap();
+        let options = &providers[0].options;
+        assert_eq!(providers[0].name, "s3");
+        assert_eq!(options[0].examples_for("").len(), 1);
+        assert_eq!(options[1].summary(), "AWS Secret Access Key (password).");
+        assert!(options[1].is_secret());
+        assert!(!options[2].is_secret());
+        assert!(!options[2].is_shown_for("AWS"));
+        assert_eq!(options[3].default, "200Mi");
+        assert!(options[3].advanced);
+    }
+
+    #[test]
+    fn config_state_with_open_questions() {
+        let done: ImportedConfigState =
+            serde_json::from_str(r#"{"State":"","Option":null,"Error":"","Result":""}"#).unwrap();
+        assert!(done.into_result().is_ok());
+        let pending: ImportedConfigState =
+            serde_json::from_str(r#"{"State":"*oauth,choose_type,,","Option":{},"Error":""}"#)
+                .unwrap();
+        assert!(matches!(
+            pending.into_result(),
+            Err(RcloneError::AuthExpired(_))
+        ));
+    }
 }
@@ -0,0 +1,415 @@
+use adw::glib::clone;
+use adw::prelude::{
+    ActionRowExt, AdwDialogExt, ComboRowExt, EditableExt, NavigationPageExt, PreferencesGroupExt,
+    PreferencesRowExt,
+};
+use relm4::gtk::prelude::{BoxExt, ButtonExt, ObjectExt, OrientableExt, WidgetExt};
+use relm4::gtk::{self};
+use relm4::ComponentSender;
+use relm4::{adw, RelmWidgetExt};
+use relm4::{Component, ComponentParts};
+
+use crate::client::{is_valid_remote_name, RemoteConfig};
+use crate::password_tools::{generate_password, PasswordStrength};
+use crate::path_tools::RclonePath;
+
+const GENERATED_PASSWORD_LENGTH: usize = 32;
+
+/// The `filename_encryption` modes of rclone crypt, with a label for each.
+const FILENAME_ENCRYPTION_MODES: [(&str, &str); 3] = [
+    ("standard", "Encrypt"),
+    ("obfuscate", "Obfuscate"),
+    ("off", "Leave readable"),
+];
+
+/// A crypt remote as set up through the wizard.
+#[derive(Debug, Clone)]
+pub struct CryptSetup {
+    pub name: String,
+    /// The remote and folder where the encrypted files are stored.
+    pub target: RclonePath,
+    pub password: String,
+    pub salt: String,
+    pub filename_encryption: String,
+    pub directory_name_encryption: bool,
+}
+
+impl CryptSetup {
+    /// The options for `rclone config create` with the `crypt` type.
+    pub fn values(&self) -> RemoteConfig {
+        let mut values = RemoteConfig::from([
+            (String::from("remote"), self.target.to_string()),
+            (String::from("password"), self.password.clone()),
+            (
+                String::from("filename_encryption"),
+                self.filename_encryption.clone(),
+            ),
+            (
+                String::from("directory_name_encryption"),
+                self.directory_name_encryption.to_string(),
+            ),
+        ]);
+        if !self.salt.is_empty() {
+            values.insert(String::from("password2"), self.salt.clone());
+        }
+        values
+    }
+}
+
+#[derive(Debug)]
+pub struct CryptWizardDialog {
+    remotes: Vec<String>,
+    navigation_view: adw::NavigationView,
+    remote_row: adw::ComboRow,
+    folder_row: adw::EntryRow,
+    name_row: adw::EntryRow,
+    password_row: adw::PasswordEntryRow,
+    salt_row: adw::PasswordEntryRow,
+    filename_encryption_row: adw::ComboRow,
+    directory_name_encryption_row: adw::SwitchRow,
+    password_strength: PasswordStrength,
+    salt_strength: PasswordStrength,
+}
+
+#[derive(Debug)]
+pub struct CryptWizardDialogInit {
+    /// All configured remotes, with the colon.
+    pub remotes: Vec<String>,
+    /// Where the wizard starts out, normally the folder that is open in the browser.
+    pub path: RclonePath,
+}
+
+#[derive(Debug)]
+pub enum CryptWizardDialogInMsg {
+    LocationConfirmed,
+    PasswordsConfirmed,
+    PasswordChanged,
+    PasswordGenerationRequested,
+    SaltGenerationRequested,
+    CreateRequested,
+}
+
+#[derive(Debug)]
+pub enum CryptWizardDialogOutMsg {
+    Submitted(CryptSetup),
+}
+
+impl CryptWizardDialog {
+    fn name(&self) -> String {
+        self.name_row.text().to_string()
+    }
+
+    fn target(&self) -> Option<RclonePath> {
+        let remote = self.remotes.get(self.remote_row.selected() as usize)?;
+        let folder = self.folder_row.text();
+        Some(match folder.trim_matches('/') {
+            "" => RclonePath::from(remote),
+            folder => RclonePath::from(remote).join(folder),
+        })
+    }
+}
+
+fn set_error(widget: &impl WidgetExt, has_error: bool) {
+    match has_error {
+        true => widget.add_css_class("error"),
+        false => widget.remove_css_class("error"),
+    }
+}
+
+#[relm4::component(pub)]
+impl Component for CryptWizardDialog {
+    type Init = CryptWizardDialogInit;
+    type Input = CryptWizardDialogInMsg;
+    type Output = CryptWizardDialogOutMsg;
+    type CommandOutput = ();
+
+    view! {
+        #[root]
+        adw::Dialog {
+            set_title: "New encrypted remote",
+            set_can_close: true,
+            set_content_width: 450,
+
+            #[wrap(Some)]
+            set_child = &gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+
+                #[local_ref]
+                navigation_view -> adw::NavigationView {
+                    set_vexpand: true,
+
+                    add = &adw::NavigationPage {
+                        set_title: "Location",
+                        set_tag: Some("location"),
+
+                        #[wrap(Some)]
+                        set_child = &adw::ToolbarView {
+                            add_top_bar = &adw::HeaderBar {},
+
+                            #[wrap(Some)]
+                            set_content = &gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
+                                set_margin_all: 20,
+                                set_margin_top: 0,
+                                set_spacing: 20,
+
+                                adw::PreferencesGroup {
+                                    set_description: Some("Files are encrypted on your computer and stored in this folder. Other apps only see scrambled names and contents there."),
+                                    add: &model.remote_row,
+                                    add: &model.folder_row,
+                                },
+
+                                adw::PreferencesGroup {
+                                    set_description: Some("The encrypted remote shows up in the sidebar under this name."),
+                                    add: &model.name_row,
+                                },
+
+                                gtk::Button {
+                                    set_label: "Next",
+                                    set_halign: gtk::Align::Center,
+                                    add_css_class: "pill",
+                                    add_css_class: "suggested-action",
+                                    connect_clicked => Self::Input::LocationConfirmed,
+                                },
+                            }
+                        }
+                    },
+
+                    add = &adw::NavigationPage {
+                        set_title: "Passwords",
+                        set_tag: Some("passwords"),
+
+                        #[wrap(Some)]
+                        set_child = &adw::ToolbarView {
+                            add_top_bar = &adw::HeaderBar {},
+
+                            #[wrap(Some)]
+                            set_content = &gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
+                                set_margin_all: 20,
+                                set_margin_top: 0,
+                                set_spacing: 20,
+
+                                adw::PreferencesGroup {
+                                    set_description: Some("Keep both somewhere safe. Without them, the files can't be decrypted, not even by rclone."),
+                                    add: &model.password_row,
+
+                                    adw::ActionRow {
+                                        set_title: "Strength",
+                                        #[watch]
+                                        set_subtitle: model.password_strength.label(),
+                                        add_suffix = &gtk::LevelBar {
+                                            set_valign: gtk::Align::Center,
+                                            set_width_request: 120,
+                                            #[watch]
+                                            set_value: model.password_strength.fraction(),
+                                        },
+                                        add_suffix = &gtk::Button {
+                                            set_label: "Generate",
+                                            set_valign: gtk::Align::Center,
+                                            connect_clicked => Self::Input::PasswordGenerationRequested,
+                                        },
+                                    },
+
+                                    add: &model.salt_row,
+
+                                    adw::ActionRow {
+                                        set_title: "Salt strength",
+                                        set_tooltip_text: Some("The salt is optional, but makes the password harder to crack"),
+                                        #[watch]
+                                        set_subtitle: match model.salt_strength {
+                                            PasswordStrength::Empty => "No salt",
+                                            strength => strength.label(),
+                                        },
+                                        add_suffix = &gtk::LevelBar {
+                                            set_valign: gtk::Align::Center,
+                                            set_width_request: 120,
+                                            #[watch]
+                                            set_value: model.salt_strength.fraction(),
+                                        },
+                                        add_suffix = &gtk::Button {
+                                            set_label: "Generate",
+                                            set_valign: gtk::Align::Center,
+                                            connect_clicked => Self::Input::SaltGenerationRequested,
+                                        },
+                                    },
+                                },
+
+                                gtk::Button {
+                                    set_label: "Next",
+                                    set_halign: gtk::Align::Center,
+                                    add_css_class: "pill",
+                                    add_css_class: "suggested-action",
+                                    connect_clicked => Self::Input::PasswordsConfirmed,
+                                },
+                            }
+                        }
+                    },
+
+                    add = &adw::NavigationPage {
+                        set_title: "File names",
+                        set_tag: Some("file-names"),
+
+                        #[wrap(Some)]
+                        set_child = &adw::ToolbarView {
+                            add_top_bar = &adw::HeaderBar {},
+
+                            #[wrap(Some)]
+                            set_content = &gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
+                                set_margin_all: 20,
+                                set_margin_top: 0,
+                                set_spacing: 20,
+
+                                adw::PreferencesGroup {
+                                    set_description: Some("File contents are always encrypted. Obfuscated names are only lightly scrambled, but stay short enough for storage providers with strict limits on name length."),
+                                    add: &model.filename_encryption_row,
+                                    add: &model.directory_name_encryption_row,
+                                },
+
+                                gtk::Button {
+                                    set_label: "Create",
+                                    set_halign: gtk::Align::Center,
+                                    add_css_class: "pill",
+                                    add_css_class: "suggested-action",
+                                    connect_clicked => Self::Input::CreateRequested,
+                                },
+                            }
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let remote_names = init
+            .remotes
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>();
+        let initial_remote = init.path.remote().unwrap_or_default();
+        let remote_row = adw::ComboRow::builder()
+            .title("Remote")
+            .model(&gtk::StringList::new(&remote_names))
+            .selected(
+                init.remotes
+                    .iter()
+                    .position(|r| *r == initial_remote)
+                    .unwrap_or(0) as u32,
+            )
+            .build();
+        let folder_row = adw::EntryRow::builder().title("Folder").build();
+        folder_row.set_text(
+            init.path
+                .to_string()
+                .strip_prefix(&initial_remote)
+                .unwrap_or(""),
+        );
+        let name_row = adw::EntryRow::builder().title("Name").build();
+        name_row.set_text(&format!("{}-crypt", initial_remote.trim_end_matches(':')));
+
+        let password_row = adw::PasswordEntryRow::builder().title("Password").build();
+        let salt_row = adw::PasswordEntryRow::builder()
+            .title("Salt (second password)")
+            .build();
+        for row in [&password_row, &salt_row] {
+            row.connect_changed(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::PasswordChanged);
+                }
+            ));
+        }
+
+        let mode_labels = FILENAME_ENCRYPTION_MODES.map(|(_, label)| label);
+        let filename_encryption_row = adw::ComboRow::builder()
+            .title("File names")
+            .model(&gtk::StringList::new(&mode_labels))
+            .build();
+        let directory_name_encryption_row = adw::SwitchRow::builder()
+            .title("Encrypt folder names too")
+            .active(true)
+            .build();
+        filename_encryption_row
+            .bind_property("selected", &directory_name_encryption_row, "sensitive")
+            .transform_to(|_, selected: u32| {
+                Some(FILENAME_ENCRYPTION_MODES[selected as usize].0 != "off")
+            })
+            .sync_create()
+            .build();
+
+        let model = Self {
+            remotes: init.remotes,
+            navigation_view: adw::NavigationView::new(),
+            remote_row,
+            folder_row,
+            name_row,
+            password_row,
+            salt_row,
+            filename_encryption_row,
+            directory_name_encryption_row,
+            password_strength: PasswordStrength::Empty,
+            salt_strength: PasswordStrength::Empty,
+        };
+        let navigation_view = &model.navigation_view;
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match message {
+            Self::Input::LocationConfirmed => {
+                let name = self.name();
+                let name_valid =
+                    is_valid_remote_name(&name) && !self.remotes.contains(&format!("{}:", name));
+                set_error(&self.name_row, !name_valid);
+                if name_valid && self.target().is_some() {
+                    self.navigation_view.push_by_tag("passwords");
+                }
+            }
+            Self::Input::PasswordsConfirmed => {
+                let password_missing = self.password_row.text().is_empty();
+                set_error(&self.password_row, password_missing);
+                if !password_missing {
+                    self.navigation_view.push_by_tag("file-names");
+                }
+            }
+            Self::Input::PasswordChanged => {
+                self.password_strength = PasswordStrength::of(&self.password_row.text());
+                self.salt_strength = PasswordStrength::of(&self.salt_row.text());
+            }
+            Self::Input::PasswordGenerationRequested => {
+                self.password_row
+                    .set_text(&generate_password(GENERATED_PASSWORD_LENGTH));
+            }
+            Self::Input::SaltGenerationRequested => {
+                self.salt_row
+                    .set_text(&generate_password(GENERATED_PASSWORD_LENGTH));
+            }
+            Self::Input::CreateRequested => {
+                let Some(target) = self.target() else {
+                    return;
+                };
+                let (filename_encryption, _) =
+                    FILENAME_ENCRYPTION_MODES[self.filename_encryption_row.selected() as usize];
+                sender
+                    .output(Self::Output::Submitted(CryptSetup {
+                        name: self.name(),
+                        target,
+                        password: self.password_row.text().to_string(),
+                        salt: self.salt_row.text().to_string(),
+                        filename_encryption: String::from(filename_encryption),
+                        directory_name_encryption: self.directory_name_encryption_row.is_active(),
+                    }))
+                    .expect("Could not broadcast crypt setup");
+                root.close();
+            }
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::sync::Arc;
+
+use adw::glib::clone;
+use adw::prelude::{ActionRowExt, AdwDialogExt, AlertDialogExt, BoxExt, ButtonExt};
+use relm4::gtk::prelude::{OrientableExt, WidgetExt};
+use relm4::gtk::{self};
+use relm4::ComponentSender;
+use relm4::{adw, RelmWidgetExt};
+use relm4::{Component, ComponentParts};
+
+use crate::client::{ProcessHandle, RcloneError, StorageClient};
+use crate::disk_usage::{scan, UsageNode};
+use crate::icons::icon_names;
+use crate::path_tools::RclonePath;
+use crate::trash::is_in_trash;
+
+#[derive(Debug)]
+enum ScanStatus {
+    /// Holds how many items have been listed so far.
+    Scanning(usize),
+    Scanned,
+    Cancelled,
+    Failed(RcloneError),
+}
+
+#[derive(Debug)]
+pub struct DiskUsageDialog {
+    client: Arc<dyn StorageClient>,
+    root: RclonePath,
+    /// The folder being looked at, which is `root` or somewhere below it.
+    current: RclonePath,
+    trash_folder: Option<String>,
+    /// Everything below `root` as of the last scan, so moving around needs no listing.
+    tree: Option<UsageNode>,
+    item_list: gtk::ListBox,
+    status: ScanStatus,
+    process: ProcessHandle,
+    /// Tells results of the current scan apart from those of a scan that was replaced.
+    generation: u32,
+}
+
+#[derive(Debug)]
+pub struct DiskUsageDialogInit {
+    pub client: Arc<dyn StorageClient>,
+    pub path: RclonePath,
+    /// Where deleted items go, if they are moved to the trash rather than deleted for good.
+    pub trash_folder: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum DiskUsageDialogInMsg {
+    ScanRequested,
+    CancelRequested,
+    FolderOpened(RclonePath),
+    ParentRequested,
+    DeleteRequested(RclonePath),
+    DeleteConfirmed(RclonePath),
+}
+
+#[derive(Debug)]
+pub enum DiskUsageDialogOutMsg {
+    /// Delete the file or folder at this path, the same way as from the file list.
+    DeleteRequested(RclonePath, bool),
+}
+
+#[derive(Debug)]
+pub enum DiskUsageDialogCmdOut {
+    Progress(u32, usize),
+    Scanned(u32, Result<UsageNode, RcloneError>),
+}
+
+impl DiskUsageDialog {
+    fn current_node(&self) -> Option<&UsageNode> {
+        self.tree.as_ref()?.find(&self.current)
+    }
+
+    fn status_text(&self) -> String {
+        match &self.status {
+            ScanStatus::Scanning(0) => format!("Scanning {}…", self.root),
+            ScanStatus::Scanning(n) => format!("Scanning… {} items so far", n),
+            ScanStatus::Scanned => match self.current_node() {
+                Some(node) if node.file_count == 1 => {
+                    format!("{} in 1 file", node.formatted_bytes())
+                }
+                Some(node) => format!("{} in {} files", node.formatted_bytes(), node.file_count),
+                None => String::new(),
+            },
+            ScanStatus::Cancelled => String::from("Scan cancelled"),
+            ScanStatus::Failed(error) => format!("{}. {}", error.title(), error.description()),
+        }
+    }
+
+    /// Fills the list with what is in the current folder, largest first.
+    fn show_current(&self, sender: &ComponentSender<Self>) {
+        self.item_list.remove_all();
+        let Some(node) = self.current_node() else {
+            return;
+        };
+        for child in node.children() {
+            let fraction = child.fraction_of(node.bytes);
+            let subtitle = match (child.is_dir, child.file_count) {
+                (true, 1) => format!(
+                    "{} · {:.1}% · 1 file",
+                    child.formatted_bytes(),
+                    fraction * 100.0
+                ),
+                (true, count) => format!(
+                    "{} · {:.1}% · {} files",
+                    child.formatted_bytes(),
+                    fraction * 100.0,
+                    count
+                ),
+                (false, _) => format!("{} · {:.1}%", child.formatted_bytes(), fraction * 100.0),
+            };
+            let row = adw::ActionRow::builder()
+                .title(child.name())
+                .subtitle(subtitle)
+                .use_markup(false)
+                .activatable(child.is_dir)
+                .build();
+            row.add_prefix(&gtk::Image::from_icon_name(match child.is_dir {
+                true => icon_names::FOLDER_FILLED,
+                false => icon_names::PAPER_FILLED,
+            }));
+            row.add_suffix(
+                &gtk::LevelBar::builder()
+                    .value(fraction)
+                    .valign(gtk::Align::Center)
+                    .width_request(120)
+                    .build(),
+            );
+            let delete_button = gtk::Button::builder()
+                .label("Delete")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            let path = child.path.clone();
+            delete_button.connect_clicked(clone!(
+                #[strong]
+                sender,
+                #[strong]
+                path,
+                move |_| {
+                    sender.input(DiskUsageDialogInMsg::DeleteRequested(path.clone()));
+                }
+            ));
+            row.add_suffix(&delete_button);
+            if child.is_dir {
+                row.connect_activated(clone!(
+                    #[strong]
+                    sender,
+                    move |_| {
+                        sender.input(DiskUsageDialogInMsg::FolderOpened(path.clone()));
+                    }
+                ));
+            }
+            self.item_list.append(&row);
+        }
+    }
+}
+
+#[relm4::component(pub)]
+impl Component for DiskUsageDialog {
+    type Init = DiskUsageDialogInit;
+    type Input = DiskUsageDialogInMsg;
+    type Output = DiskUsageDialogOutMsg;
+    type CommandOutput = DiskUsageDialogCmdOut;
+
+    view! {
+        #[root]
+        adw::Dialog {
+            set_title: "Disk Usage",
+            set_content_width: 720,
+            set_content_height: 560,
+            connect_closed[sender] => move |_| {
+                sender.input(DiskUsageDialogInMsg::CancelRequested);
+            },
+
+            #[wrap(Some)]
+            set_child = &adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {
+                    pack_start = &gtk::Button {
+                        set_icon_name: icon_names::UP_LARGE,
+                        set_tooltip_text: Some("Parent folder"),
+                        #[watch]
+                        set_sensitive: model.current != model.root,
+                        connect_clicked => DiskUsageDialogInMsg::ParentRequested,
+                    },
+
+                    #[wrap(Some)]
+                    set_title_widget = &adw::WindowTitle {
+                        set_title: "Disk Usage",
+                        #[watch]
+                        set_subtitle: &model.current.to_string(),
+                    },
+
+                    pack_end = &gtk::Button {
+                        set_icon_name: icon_names::ARROW_CIRCULAR_TOP_RIGHT,
+                        set_tooltip_text: Some("Scan again"),
+                        #[watch]
+                        set_sensitive: !matches!(model.status, ScanStatus::Scanning(_)),
+                        connect_clicked => DiskUsageDialogInMsg::ScanRequested,
+                    },
+                },
+
+                #[wrap(Some)]
+                set_content = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_margin_all: 10,
+                    set_spacing: 10,
+
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 10,
+
+                        gtk::Spinner {
+                            #[watch]
+                            set_spinning: matches!(model.status, ScanStatus::Scanning(_)),
+                            #[watch]
+                            set_visible: matches!(model.status, ScanStatus::Scanning(_)),
+                        },
+
+                        gtk::Label {
+                            set_halign: gtk::Align::Start,
+                            set_hexpand: true,
+                            set_wrap: true,
+                            add_css_class: "dim-label",
+                            #[watch]
+                            set_text: &model.status_text(),
+                        },
+
+                        gtk::Button {
+                            set_label: "Cancel",
+                            #[watch]
+                            set_visible: matches!(model.status, ScanStatus::Scanning(_)),
+                            connect_clicked => DiskUsageDialogInMsg::CancelRequested,
+                        },
+                    },
+
+                    gtk::ScrolledWindow {
+                        set_vexpand: true,
+                        #[watch]
+                        set_visible: model.current_node().is_some_and(|node| !node.children().is_empty()),
+
+                        set_child: Some(&model.item_list),
+                    },
+                }
+            }
+        }
+    }
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let item_list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+
+        let model = Self {
+            client: init.client,
+            current: init.path.clone(),
+            root: init.path,
+            trash_folder: init.trash_folder,
+            tree: None,
+            item_list,
+            status: ScanStatus::Scanning(0),
+            process: ProcessHandle::default(),
+            generation: 0,
+        };
+        sender.input(DiskUsageDialogInMsg::ScanRequested);
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match message {
+            Self::Input::ScanRequested => {
+                self.process.cancel();
+                self.process = ProcessHandle::default();
+                self.generation += 1;
+                self.status = ScanStatus::Scanning(0);
+
+                let (client, path, process, generation) = (
+                    self.client.clone(),
+                    self.root.clone(),
+                    self.process.clone(),
+                    self.generation,
+                );
+                sender.spawn_command(move |out| {
+                    let result = scan(client.as_ref(), &path, &process, &|count| {
+                        out.emit(DiskUsageDialogCmdOut::Progress(generation, count))
+                    });
+                    out.emit(DiskUsageDialogCmdOut::Scanned(generation, result));
+                });
+            }
+            Self::Input::CancelRequested => {
+                if let ScanStatus::Scanning(_) = self.status {
+                    self.process.cancel();
+                    self.status = ScanStatus::Cancelled;
+                }
+            }
+            Self::Input::FolderOpened(path) => {
+                self.current = path;
+                self.show_current(&sender);
+            }
+            Self::Input::ParentRequested => {
+                if self.current != self.root {
+                    self.current = self.current.resolve_to_parent();
+                    self.show_current(&sender);
+                }
+            }
+            Self::Input::DeleteRequested(path) => {
+                let Some(node) = self.tree.as_ref().and_then(|tree| tree.find(&path)) else {
+                    return;
+                };
+                let body = match &self.trash_folder {
+                    Some(folder) if !is_in_trash(&path, folder) => format!(
+                        "It will be moved to the trash. The {} it takes up are only freed once the trash is emptied.",
+                        node.formatted_bytes()
+                    ),
+                    _ => format!(
+                        "This frees up {} and can't be undone.",
+                        node.formatted_bytes()
+                    ),
+                };
+                let alert = adw::AlertDialog::builder()
+                    .heading(format!("Delete \"{}\"?", node.name()))
+                    .body(body)
+                    .build();
+                alert.add_response("cancel", "Cancel");
+                alert.add_response("delete", "Delete");
+                alert.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+                alert.connect_response(Some("delete"), move |_, _| {
+                    sender.input(DiskUsageDialogInMsg::DeleteConfirmed(path.clone()));
+                });
+                alert.present(Some(root));
+            }
+            Self::Input::DeleteConfirmed(path) => {
+                let Some(removed) = self.tree.as_mut().and_then(|tree| tree.remove(&path)) else {
+                    return;
+                };
+                sender
+                    .output(Self::Output::DeleteRequested(path, removed.is_dir))
+                    .expect("Could not broadcast delete request");
+                self.show_current(&sender);
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            DiskUsageDialogCmdOut::Progress(generation, count) if generation == self.generation => {
+                if let ScanStatus::Scanning(_) = self.status {
+                    self.status = ScanStatus::Scanning(count);
+                }
+            }
+            DiskUsageDialogCmdOut::Scanned(generation, result) if generation == self.generation => {
+                match (result, &self.status) {
+                    (_, ScanStatus::Cancelled) => {}
+                    (Ok(tree), _) => {
+                        // The folder being looked at may be gone after scanning again
+                        if tree.find(&self.current).is_none() {
+                            self.current = self.root.clone();
+                        }
+                        self.tree = Some(tree);
+                        self.status = ScanStatus::Scanned;
+                        self.show_current(&sender);
+                    }
+                    (Err(error), _) => self.status = ScanStatus::Failed(error),
+                }
+            }
+            _ => {}
+        }
+    }
+}
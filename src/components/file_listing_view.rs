@@ -9,20 +9,39 @@ use relm4::gtk::{self};
 use relm4::typed_view::list::RelmListItem;
 use relm4::RelmWidgetExt;
 
+/// Widths of the detail columns, shared with the column headers above the list.
+pub const SIZE_COLUMN_WIDTH: i32 = 90;
+pub const MODIFIED_COLUMN_WIDTH: i32 = 140;
+pub const TYPE_COLUMN_WIDTH: i32 = 140;
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct FileListingView {
     pub(crate) model: RcloneFileListing,
+    compact: bool,
 }
 
 impl FileListingView {
-    pub fn new(model: RcloneFileListing) -> Self {
-        Self { model }
+    pub fn new(model: RcloneFileListing, compact: bool) -> Self {
+        Self { model, compact }
     }
 }
 
 pub struct FileListingViewWidgets {
     image: gtk::Image,
     label: gtk::Label,
+    size_label: gtk::Label,
+    modified_label: gtk::Label,
+    type_label: gtk::Label,
+}
+
+fn format_mod_time(listing: &RcloneFileListing) -> String {
+    chrono::DateTime::from_timestamp(listing.mod_time.unix_timestamp(), 0)
+        .map(|time| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
 }
 
 fn set_sensitivity(root: &gtk::Box, file_picker_mode: &FilePickerMode, is_dir: bool) -> bool {
@@ -49,12 +68,45 @@ impl RelmListItem for FileListingView {
                 #[name = "label"]
                 gtk::Label {
                     set_halign: gtk::Align::Start,
+                    set_hexpand: true,
+                    set_xalign: 0.0,
                     set_ellipsize: gtk::pango::EllipsizeMode::End,
                 },
+
+                #[name = "size_label"]
+                gtk::Label {
+                    set_width_request: SIZE_COLUMN_WIDTH,
+                    set_xalign: 1.0,
+                    add_css_class: "dim-label",
+                },
+
+                #[name = "modified_label"]
+                gtk::Label {
+                    set_width_request: MODIFIED_COLUMN_WIDTH,
+                    set_xalign: 0.0,
+                    add_css_class: "dim-label",
+                },
+
+                #[name = "type_label"]
+                gtk::Label {
+                    set_width_request: TYPE_COLUMN_WIDTH,
+                    set_xalign: 0.0,
+                    set_ellipsize: gtk::pango::EllipsizeMode::End,
+                    add_css_class: "dim-label",
+                },
             },
         }
 
-        (root, Self::Widgets { label, image })
+        (
+            root,
+            Self::Widgets {
+                label,
+                image,
+                size_label,
+                modified_label,
+                type_label,
+            },
+        )
     }
 
     fn bind(&mut self, widgets: &mut Self::Widgets, root: &mut Self::Root) {
@@ -84,5 +136,28 @@ impl RelmListItem for FileListingView {
             false => icon_names::PAPER_FILLED,
         }));
         widgets.label.set_text(&self.model.name);
+
+        root.set_margin_top(if self.compact { 3 } else { 10 });
+        root.set_margin_bottom(if self.compact { 3 } else { 10 });
+        for detail in [
+            &widgets.size_label,
+            &widgets.modified_label,
+            &widgets.type_label,
+        ] {
+            detail.set_visible(!self.compact);
+        }
+        widgets
+            .size_label
+            .set_text(&match self.model.formatted_size() {
+                Some(size) => format!("{}B", size),
+                None => String::from(""),
+            });
+        widgets
+            .modified_label
+            .set_text(&format_mod_time(&self.model));
+        widgets.type_label.set_text(match self.model.is_dir {
+            true => "Folder",
+            false => &self.model.mime_type,
+        });
     }
 }
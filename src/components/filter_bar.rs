@@ -0,0 +1,218 @@
+use adw::glib::clone;
+use adw::prelude::{BoxExt, CheckButtonExt, EditableExt, ToggleButtonExt};
+use relm4::gtk::prelude::{ListModelExt, OrientableExt, WidgetExt};
+use relm4::gtk::{self};
+use relm4::{adw, ComponentParts, ComponentSender, RelmWidgetExt, SimpleComponent};
+
+use crate::model::{ListingFilter, ListingKind};
+
+/// The first entry of the type dropdown, which lets every type through.
+const ANY_MIME_TYPE: &str = "Any type";
+
+#[derive(Debug)]
+pub struct FilterBar {
+    root: gtk::SearchBar,
+    filter: ListingFilter,
+    pattern_valid: bool,
+    entry: gtk::SearchEntry,
+    all_button: gtk::ToggleButton,
+    mime_types: gtk::StringList,
+    mime_type_dropdown: gtk::DropDown,
+}
+
+#[derive(Debug)]
+pub enum FilterBarInMsg {
+    Toggled,
+    Closed,
+    PatternChanged(String),
+    RegexToggled(bool),
+    KindChanged(ListingKind),
+    MimeTypeSelected(u32),
+    /// Offers the types of the files in a newly loaded folder.
+    MimeTypesChanged(Vec<String>),
+}
+
+#[derive(Debug)]
+pub enum FilterBarOutMsg {
+    FilterChanged(ListingFilter),
+}
+
+impl FilterBar {
+    fn kind_button(
+        label: &str,
+        kind: ListingKind,
+        group: Option<&gtk::ToggleButton>,
+        sender: &ComponentSender<Self>,
+    ) -> gtk::ToggleButton {
+        let button = gtk::ToggleButton::with_label(label);
+        button.set_group(group);
+        button.connect_toggled(clone!(
+            #[strong]
+            sender,
+            move |button| {
+                if button.is_active() {
+                    sender.input(FilterBarInMsg::KindChanged(kind));
+                }
+            }
+        ));
+        button
+    }
+
+    fn notify(&self, sender: &ComponentSender<Self>) {
+        sender
+            .output(FilterBarOutMsg::FilterChanged(self.filter.clone()))
+            .expect("Could not broadcast filter");
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for FilterBar {
+    type Init = ();
+    type Input = FilterBarInMsg;
+    type Output = FilterBarOutMsg;
+
+    view! {
+        #[root]
+        gtk::SearchBar {
+            set_show_close_button: true,
+            connect_entry: &model.entry,
+            connect_search_mode_enabled_notify[sender] => move |bar| {
+                if !bar.is_search_mode() {
+                    sender.input(FilterBarInMsg::Closed);
+                }
+            },
+
+            #[wrap(Some)]
+            set_child = &gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: 10,
+
+                append: &model.entry,
+
+                gtk::CheckButton {
+                    set_label: Some("Regex"),
+                    set_tooltip_text: Some("Treat the filter as a regular expression"),
+                    connect_toggled[sender] => move |button| {
+                        sender.input(FilterBarInMsg::RegexToggled(button.is_active()));
+                    },
+                },
+
+                #[name = "kind_buttons"]
+                gtk::Box {
+                    add_css_class: "linked",
+                },
+
+                append: &model.mime_type_dropdown,
+            }
+        }
+    }
+
+    fn init(
+        _init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let entry = gtk::SearchEntry::builder()
+            .placeholder_text("Filter by name, *.glob or regex")
+            .hexpand(true)
+            .build();
+        entry.connect_search_changed(clone!(
+            #[strong]
+            sender,
+            move |entry| {
+                sender.input(FilterBarInMsg::PatternChanged(entry.text().to_string()));
+            }
+        ));
+        let all_button = Self::kind_button("All", ListingKind::All, None, &sender);
+        all_button.set_active(true);
+        let mime_types = gtk::StringList::new(&[ANY_MIME_TYPE]);
+        let mime_type_dropdown = gtk::DropDown::builder()
+            .model(&mime_types)
+            .tooltip_text("Only show files of this type")
+            .build();
+        mime_type_dropdown.connect_selected_notify(clone!(
+            #[strong]
+            sender,
+            move |dropdown| {
+                sender.input(FilterBarInMsg::MimeTypeSelected(dropdown.selected()));
+            }
+        ));
+
+        let model = Self {
+            root: root.clone(),
+            filter: ListingFilter::default(),
+            pattern_valid: true,
+            entry,
+            all_button,
+            mime_types,
+            mime_type_dropdown,
+        };
+        let widgets = view_output!();
+        widgets.kind_buttons.append(&model.all_button);
+        for (label, kind) in [
+            ("Folders", ListingKind::Folders),
+            ("Files", ListingKind::Files),
+        ] {
+            widgets.kind_buttons.append(&Self::kind_button(
+                label,
+                kind,
+                Some(&model.all_button),
+                &sender,
+            ));
+        }
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
+        match message {
+            FilterBarInMsg::Toggled => {
+                self.root.set_search_mode(!self.root.is_search_mode());
+            }
+            FilterBarInMsg::Closed => {
+                // A hidden filter would leave files missing without a hint, so it goes away
+                self.entry.set_text("");
+                self.all_button.set_active(true);
+                self.mime_type_dropdown.set_selected(0);
+                self.filter.pattern.clear();
+                self.filter.kind = ListingKind::All;
+                self.filter.mime_type = None;
+                self.notify(&sender);
+            }
+            FilterBarInMsg::PatternChanged(pattern) => {
+                self.filter.pattern = pattern;
+                self.pattern_valid = self.filter.name_regex().is_ok();
+                self.notify(&sender);
+            }
+            FilterBarInMsg::RegexToggled(use_regex) => {
+                self.filter.use_regex = use_regex;
+                self.pattern_valid = self.filter.name_regex().is_ok();
+                self.notify(&sender);
+            }
+            FilterBarInMsg::KindChanged(kind) => {
+                self.filter.kind = kind;
+                self.mime_type_dropdown
+                    .set_sensitive(kind != ListingKind::Folders);
+                self.notify(&sender);
+            }
+            FilterBarInMsg::MimeTypeSelected(position) => {
+                self.filter.mime_type = match position {
+                    0 => None,
+                    _ => self.mime_types.string(position).map(|m| m.to_string()),
+                };
+                self.notify(&sender);
+            }
+            FilterBarInMsg::MimeTypesChanged(mime_types) => {
+                let mime_types: Vec<&str> = mime_types.iter().map(String::as_str).collect();
+                // The chosen type stays chosen when the folder reloads and still has such files
+                let selected_mime_type = self.filter.mime_type.clone();
+                self.mime_types
+                    .splice(1, self.mime_types.n_items() - 1, &mime_types);
+                let position = selected_mime_type
+                    .and_then(|selected| mime_types.iter().position(|m| *m == selected))
+                    .map_or(0, |index| index as u32 + 1);
+                self.mime_type_dropdown.set_selected(position);
+            }
+        }
+        self.entry.set_class_active("error", !self.pattern_valid);
+    }
+}
@@ -1,7 +1,15 @@
+pub(crate) mod crypt_wizard_dialog;
+pub(crate) mod disk_usage_dialog;
 pub(crate) mod file_listing_view;
+pub(crate) mod filter_bar;
+pub(crate) mod preview_pane;
+pub(crate) mod properties_dialog;
 pub(crate) mod queue_button;
 pub(crate) mod queue_detail_view;
 pub(crate) mod queue_dialog;
+pub(crate) mod remote_editor_dialog;
 pub(crate) mod remote_view;
+pub(crate) mod search_dialog;
 pub(crate) mod string_prompt_dialog;
+pub(crate) mod trash_dialog;
 pub(crate) mod unlock_view;
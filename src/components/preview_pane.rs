@@ -0,0 +1,336 @@
+use std::sync::Arc;
+
+use adw::prelude::{BoxExt, ButtonExt, TextBufferExt, TextViewExt};
+use relm4::gtk::prelude::{OrientableExt, WidgetExt};
+use relm4::gtk::{self, gdk, glib};
+use relm4::{adw, Component, ComponentParts, ComponentSender, RelmWidgetExt};
+
+use crate::client::{FileMetadata, RcloneError, RcloneFileListing, StorageClient};
+use crate::preview::{render_pdf_page, text_preview, PreviewKind, TEXT_PREVIEW_BYTES};
+
+#[derive(Debug)]
+enum PreviewContent {
+    Placeholder(String),
+    Loading,
+    Text,
+    Image,
+    /// Holds the size of a file that is only fetched once the user agrees.
+    TooLarge(u64),
+    Failed(String),
+}
+
+/// What was fetched for a preview, before it is put on screen.
+#[derive(Debug)]
+pub enum FetchedPreview {
+    Text(String),
+    Binary,
+    Image(Vec<u8>),
+}
+
+/// A file to preview, along with what is needed to fetch it.
+#[derive(Debug, Clone)]
+pub struct PreviewRequest {
+    pub client: Arc<dyn StorageClient>,
+    pub listing: RcloneFileListing,
+    /// Files above this many bytes that have to be fetched whole are not fetched unasked.
+    pub size_limit: u64,
+}
+
+#[derive(Debug)]
+pub struct PreviewPane {
+    request: Option<PreviewRequest>,
+    content: PreviewContent,
+    picture: gtk::Picture,
+    text_buffer: gtk::TextBuffer,
+    metadata_list: gtk::ListBox,
+    /// Tells results for the current file apart from those of a file that was selected before.
+    generation: u32,
+}
+
+#[derive(Debug)]
+pub enum PreviewPaneInMsg {
+    Show(PreviewRequest),
+    /// Shows a hint instead of a file, for when none or several are selected.
+    Clear(String),
+    FetchAnywayRequested,
+}
+
+#[derive(Debug)]
+pub enum PreviewPaneCmdOut {
+    Metadata(u32, Result<FileMetadata, RcloneError>),
+    Content(u32, Result<FetchedPreview, String>),
+}
+
+impl PreviewPane {
+    fn status_text(&self) -> String {
+        match &self.content {
+            PreviewContent::Placeholder(text) | PreviewContent::Failed(text) => text.clone(),
+            PreviewContent::TooLarge(size) => format!(
+                "This file is {}. Previewing it means downloading all of it.",
+                size_format::SizeFormatterSI::new(*size)
+            ),
+            _ => String::new(),
+        }
+    }
+
+    fn fetch(&mut self, sender: &ComponentSender<Self>) {
+        let Some(request) = self.request.clone() else {
+            return;
+        };
+        self.content = PreviewContent::Loading;
+        let generation = self.generation;
+        sender.spawn_oneshot_command(move || {
+            let PreviewRequest {
+                client,
+                listing,
+                size_limit,
+            } = request;
+            let whole_file = match listing.size {
+                size if size >= 0 => size as u64,
+                _ => size_limit,
+            };
+            let describe =
+                |error: RcloneError| format!("{}. {}", error.title(), error.description());
+            let result = match PreviewKind::of(&listing) {
+                PreviewKind::Image => client
+                    .cat(&listing.path, whole_file)
+                    .map(FetchedPreview::Image)
+                    .map_err(describe),
+                PreviewKind::Pdf => client
+                    .cat(&listing.path, whole_file)
+                    .map_err(describe)
+                    .and_then(|pdf| render_pdf_page(&pdf))
+                    .map(FetchedPreview::Image),
+                _ => client
+                    .cat(&listing.path, TEXT_PREVIEW_BYTES)
+                    .map(|bytes| match text_preview(&bytes) {
+                        Some(text) => FetchedPreview::Text(text),
+                        None => FetchedPreview::Binary,
+                    })
+                    .map_err(describe),
+            };
+            PreviewPaneCmdOut::Content(generation, result)
+        });
+    }
+
+    fn show_metadata(&self, metadata: FileMetadata) {
+        self.metadata_list.remove_all();
+        for (key, value) in metadata {
+            let row = adw::ActionRow::builder()
+                .title(&key)
+                .subtitle(&value)
+                .use_markup(false)
+                .subtitle_selectable(true)
+                .css_classes(["property"])
+                .build();
+            self.metadata_list.append(&row);
+        }
+    }
+}
+
+#[relm4::component(pub)]
+impl Component for PreviewPane {
+    type Init = ();
+    type Input = PreviewPaneInMsg;
+    type Output = ();
+    type CommandOutput = PreviewPaneCmdOut;
+
+    view! {
+        #[root]
+        gtk::ScrolledWindow {
+            set_width_request: 320,
+            set_hscrollbar_policy: gtk::PolicyType::Never,
+
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_margin_all: 10,
+                set_spacing: 10,
+
+                gtk::Label {
+                    add_css_class: "title-4",
+                    set_wrap: true,
+                    set_wrap_mode: gtk::pango::WrapMode::WordChar,
+                    set_selectable: true,
+                    #[watch]
+                    set_visible: model.request.is_some(),
+                    #[watch]
+                    set_text: model.request.as_ref().map_or("", |r| r.listing.name.as_str()),
+                },
+
+                gtk::Spinner {
+                    set_height_request: 30,
+                    #[watch]
+                    set_spinning: matches!(model.content, PreviewContent::Loading),
+                    #[watch]
+                    set_visible: matches!(model.content, PreviewContent::Loading),
+                },
+
+                gtk::Label {
+                    add_css_class: "dim-label",
+                    set_wrap: true,
+                    set_justify: gtk::Justification::Center,
+                    #[watch]
+                    set_text: &model.status_text(),
+                    #[watch]
+                    set_visible: !model.status_text().is_empty(),
+                },
+
+                gtk::Button {
+                    set_label: "Preview anyway",
+                    set_halign: gtk::Align::Center,
+                    #[watch]
+                    set_visible: matches!(model.content, PreviewContent::TooLarge(_)),
+                    connect_clicked => PreviewPaneInMsg::FetchAnywayRequested,
+                },
+
+                append: &model.picture,
+
+                gtk::TextView {
+                    set_buffer: Some(&model.text_buffer),
+                    set_editable: false,
+                    set_cursor_visible: false,
+                    set_monospace: true,
+                    set_wrap_mode: gtk::WrapMode::WordChar,
+                    add_css_class: "card",
+                    #[watch]
+                    set_visible: matches!(model.content, PreviewContent::Text),
+                },
+
+                append: &model.metadata_list,
+            }
+        }
+    }
+
+    fn init(
+        _init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let picture = gtk::Picture::builder()
+            .content_fit(gtk::ContentFit::Contain)
+            .height_request(240)
+            .visible(false)
+            .build();
+        let metadata_list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+
+        let model = Self {
+            request: None,
+            content: PreviewContent::Placeholder(String::from("Select a file to preview")),
+            picture,
+            text_buffer: gtk::TextBuffer::new(None),
+            metadata_list,
+            generation: 0,
+        };
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        match message {
+            PreviewPaneInMsg::Show(request) => {
+                // Selection changes come in bursts, which shouldn't fetch the same file again
+                if self.request.as_ref().is_some_and(|current| {
+                    current.listing.path == request.listing.path
+                        && current.listing.mod_time == request.listing.mod_time
+                }) {
+                    return;
+                }
+                self.generation += 1;
+                self.metadata_list.remove_all();
+                self.text_buffer.set_text("");
+                self.picture.set_paintable(None::<&gdk::Paintable>);
+
+                let (client, path, generation) = (
+                    request.client.clone(),
+                    request.listing.path.clone(),
+                    self.generation,
+                );
+                sender.spawn_oneshot_command(move || {
+                    PreviewPaneCmdOut::Metadata(generation, client.stat(&path))
+                });
+
+                let kind = PreviewKind::of(&request.listing);
+                let size = request.listing.size;
+                let too_large = size >= 0 && size as u64 > request.size_limit;
+                self.request = Some(request);
+                match kind {
+                    PreviewKind::Folder => {
+                        self.content = PreviewContent::Placeholder(String::from("Folder"));
+                    }
+                    PreviewKind::Unsupported => {
+                        self.content = PreviewContent::Placeholder(String::from(
+                            "No preview for this type of file",
+                        ));
+                    }
+                    _ if kind.needs_whole_file() && too_large => {
+                        self.content = PreviewContent::TooLarge(size as u64);
+                    }
+                    _ => self.fetch(&sender),
+                }
+            }
+            PreviewPaneInMsg::Clear(hint) => {
+                self.generation += 1;
+                self.request = None;
+                self.content = PreviewContent::Placeholder(hint);
+                self.metadata_list.remove_all();
+                self.text_buffer.set_text("");
+                self.picture.set_paintable(None::<&gdk::Paintable>);
+            }
+            PreviewPaneInMsg::FetchAnywayRequested => {
+                if let PreviewContent::TooLarge(_) = self.content {
+                    self.fetch(&sender);
+                }
+            }
+        }
+        self.picture
+            .set_visible(matches!(self.content, PreviewContent::Image));
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        _sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            PreviewPaneCmdOut::Metadata(generation, result) if generation == self.generation => {
+                match result {
+                    Ok(metadata) => self.show_metadata(metadata),
+                    Err(error) => self.show_metadata(FileMetadata::from([(
+                        String::from("Error"),
+                        format!("{}. {}", error.title(), error.description()),
+                    )])),
+                }
+            }
+            PreviewPaneCmdOut::Content(generation, result) if generation == self.generation => {
+                self.content = match result {
+                    Ok(FetchedPreview::Text(text)) => {
+                        self.text_buffer.set_text(&text);
+                        PreviewContent::Text
+                    }
+                    Ok(FetchedPreview::Binary) => PreviewContent::Placeholder(String::from(
+                        "No preview for this type of file",
+                    )),
+                    Ok(FetchedPreview::Image(bytes)) => {
+                        match gdk::Texture::from_bytes(&glib::Bytes::from_owned(bytes)) {
+                            Ok(texture) => {
+                                self.picture.set_paintable(Some(&texture));
+                                PreviewContent::Image
+                            }
+                            Err(_) => {
+                                PreviewContent::Failed(String::from("Could not read this image"))
+                            }
+                        }
+                    }
+                    Err(message) => PreviewContent::Failed(message),
+                };
+                self.picture
+                    .set_visible(matches!(self.content, PreviewContent::Image));
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use adw::glib::clone;
+use adw::prelude::{ActionRowExt, ButtonExt, PreferencesGroupExt};
+use relm4::gtk::prelude::{OrientableExt, WidgetExt};
+use relm4::gtk::{self};
+use relm4::ComponentSender;
+use relm4::{adw, RelmWidgetExt};
+use relm4::{Component, ComponentParts};
+use time::OffsetDateTime;
+
+use crate::client::{FileMetadata, FolderSize, RcloneError, RcloneFileListing, StorageClient};
+
+#[derive(Debug)]
+pub struct PropertiesDialog {
+    client: Arc<dyn StorageClient>,
+    listing: RcloneFileListing,
+    general_group: adw::PreferencesGroup,
+    contents_group: adw::PreferencesGroup,
+    file_count_row: adw::ActionRow,
+    total_size_row: adw::ActionRow,
+    checksums_group: adw::PreferencesGroup,
+    /// Stands in for the checksum rows until the remote has said which ones it supports.
+    checksums_placeholder: adw::ActionRow,
+    checksum_rows: BTreeMap<String, (adw::ActionRow, gtk::Button)>,
+    metadata_group: adw::PreferencesGroup,
+    metadata_placeholder: adw::ActionRow,
+}
+
+#[derive(Debug)]
+pub struct PropertiesDialogInit {
+    pub client: Arc<dyn StorageClient>,
+    pub listing: RcloneFileListing,
+}
+
+#[derive(Debug)]
+pub enum PropertiesDialogInMsg {
+    ChecksumRequested(String),
+}
+
+#[derive(Debug)]
+pub enum PropertiesDialogCmdOut {
+    Metadata(Result<FileMetadata, RcloneError>),
+    HashTypes(Result<Vec<String>, RcloneError>),
+    Checksum(String, Result<String, RcloneError>),
+    Size(Result<FolderSize, RcloneError>),
+}
+
+/// A row showing one value that can be selected and copied.
+fn property_row(title: &str, value: &str) -> adw::ActionRow {
+    adw::ActionRow::builder()
+        .title(title)
+        .subtitle(value)
+        .use_markup(false)
+        .subtitle_selectable(true)
+        .css_classes(["property"])
+        .build()
+}
+
+fn describe_error(error: &RcloneError) -> String {
+    format!("{}. {}", error.title(), error.description())
+}
+
+/// The size in bytes with thousands separated, followed by a rounded size when it is large.
+fn exact_size(bytes: i64) -> String {
+    if bytes < 0 {
+        return String::from("Unknown");
+    }
+    let digits = bytes.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    match bytes {
+        1 => String::from("1 byte"),
+        bytes if bytes < 1000 => format!("{} bytes", grouped),
+        bytes => format!(
+            "{} bytes ({}B)",
+            grouped,
+            size_format::SizeFormatterSI::new(bytes as u64)
+        ),
+    }
+}
+
+/// Down to the second and in the local time zone, unlike the file list.
+fn exact_time(time: OffsetDateTime) -> String {
+    chrono::DateTime::from_timestamp(time.unix_timestamp(), 0)
+        .map(|time| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S %Z")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+impl PropertiesDialog {
+    fn show_hash_types(&mut self, sender: &ComponentSender<Self>, hash_types: Vec<String>) {
+        if hash_types.is_empty() {
+            self.checksums_placeholder
+                .set_subtitle("This remote doesn't compute checksums");
+            return;
+        }
+        self.checksums_group.remove(&self.checksums_placeholder);
+        for hash_type in hash_types {
+            let row = adw::ActionRow::builder()
+                .title(hash_type.to_uppercase())
+                .use_markup(false)
+                .subtitle_selectable(true)
+                .css_classes(["property"])
+                .build();
+            let button = gtk::Button::builder()
+                .label("Compute")
+                .valign(gtk::Align::Center)
+                .build();
+            button.connect_clicked(clone!(
+                #[strong]
+                sender,
+                #[strong]
+                hash_type,
+                move |_| {
+                    sender.input(PropertiesDialogInMsg::ChecksumRequested(hash_type.clone()));
+                }
+            ));
+            row.add_suffix(&button);
+            self.checksums_group.add(&row);
+            self.checksum_rows.insert(hash_type, (row, button));
+        }
+    }
+
+    fn show_metadata(&self, metadata: FileMetadata) {
+        self.metadata_group.remove(&self.metadata_placeholder);
+        for (key, value) in metadata {
+            self.metadata_group.add(&property_row(&key, &value));
+        }
+    }
+}
+
+#[relm4::component(pub)]
+impl Component for PropertiesDialog {
+    type Init = PropertiesDialogInit;
+    type Input = PropertiesDialogInMsg;
+    type Output = ();
+    type CommandOutput = PropertiesDialogCmdOut;
+
+    view! {
+        #[root]
+        adw::Dialog {
+            set_title: &format!("Properties of '{}'", model.listing.name),
+            set_content_width: 560,
+            set_content_height: 600,
+
+            #[wrap(Some)]
+            set_child = &adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {},
+
+                #[wrap(Some)]
+                set_content = &gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_margin_all: 20,
+                        set_margin_top: 0,
+                        set_spacing: 20,
+
+                        append: &model.general_group,
+                        append: &model.contents_group,
+                        append: &model.checksums_group,
+                        append: &model.metadata_group,
+                    }
+                }
+            }
+        }
+    }
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let PropertiesDialogInit { client, listing } = init;
+
+        let general_group = adw::PreferencesGroup::new();
+        general_group.add(&property_row("Location", &listing.path.to_string()));
+        if !listing.is_dir {
+            general_group.add(&property_row("Size", &exact_size(listing.size)));
+        }
+        general_group.add(&property_row("Modified", &exact_time(listing.mod_time)));
+        general_group.add(&property_row("Type", &listing.mime_type));
+
+        let contents_group = adw::PreferencesGroup::builder()
+            .title("Contents")
+            .description("Everything inside this folder, including subfolders.")
+            .visible(listing.is_dir)
+            .build();
+        let file_count_row = property_row("Files", "Counting…");
+        let total_size_row = property_row("Total size", "Counting…");
+        contents_group.add(&file_count_row);
+        contents_group.add(&total_size_row);
+
+        let checksums_group = adw::PreferencesGroup::builder()
+            .title("Checksums")
+            .description("Computing a checksum may download the whole file.")
+            .visible(!listing.is_dir)
+            .build();
+        let checksums_placeholder = property_row("Checksums", "Loading…");
+        checksums_group.add(&checksums_placeholder);
+
+        let metadata_group = adw::PreferencesGroup::builder().title("Metadata").build();
+        let metadata_placeholder = property_row("Metadata", "Loading…");
+        metadata_group.add(&metadata_placeholder);
+
+        let (stat_client, path) = (client.clone(), listing.path.clone());
+        sender.spawn_oneshot_command(move || {
+            PropertiesDialogCmdOut::Metadata(stat_client.stat(&path))
+        });
+        if listing.is_dir {
+            let (size_client, path) = (client.clone(), listing.path.clone());
+            sender.spawn_oneshot_command(move || {
+                PropertiesDialogCmdOut::Size(size_client.size(&path))
+            });
+        } else {
+            let (hash_client, path) = (client.clone(), listing.path.clone());
+            sender.spawn_oneshot_command(move || {
+                PropertiesDialogCmdOut::HashTypes(hash_client.hash_types(&path))
+            });
+        }
+
+        let model = Self {
+            client,
+            listing,
+            general_group,
+            contents_group,
+            file_count_row,
+            total_size_row,
+            checksums_group,
+            checksums_placeholder,
+            checksum_rows: BTreeMap::new(),
+            metadata_group,
+            metadata_placeholder,
+        };
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        match message {
+            PropertiesDialogInMsg::ChecksumRequested(hash_type) => {
+                let Some((row, button)) = self.checksum_rows.get(&hash_type) else {
+                    return;
+                };
+                row.set_subtitle("Computing…");
+                button.set_sensitive(false);
+                let (client, path) = (self.client.clone(), self.listing.path.clone());
+                sender.spawn_oneshot_command(move || {
+                    let result = client.hashsum(&path, &hash_type);
+                    PropertiesDialogCmdOut::Checksum(hash_type, result)
+                });
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            PropertiesDialogCmdOut::Metadata(Ok(metadata)) => self.show_metadata(metadata),
+            PropertiesDialogCmdOut::Metadata(Err(error)) => {
+                self.metadata_placeholder
+                    .set_subtitle(&describe_error(&error));
+            }
+            PropertiesDialogCmdOut::HashTypes(Ok(hash_types)) => {
+                self.show_hash_types(&sender, hash_types);
+            }
+            PropertiesDialogCmdOut::HashTypes(Err(error)) => {
+                self.checksums_placeholder
+                    .set_subtitle(&describe_error(&error));
+            }
+            PropertiesDialogCmdOut::Checksum(hash_type, result) => {
+                let Some((row, button)) = self.checksum_rows.get(&hash_type) else {
+                    return;
+                };
+                match result {
+                    Ok(checksum) => {
+                        row.set_subtitle(&checksum);
+                        button.set_visible(false);
+                    }
+                    Err(error) => {
+                        row.set_subtitle(&describe_error(&error));
+                        button.set_label("Retry");
+                        button.set_sensitive(true);
+                    }
+                }
+            }
+            PropertiesDialogCmdOut::Size(Ok(size)) => {
+                self.file_count_row.set_subtitle(&match size.count {
+                    1 => String::from("1 file"),
+                    count => format!("{} files", count),
+                });
+                self.total_size_row.set_subtitle(&exact_size(size.bytes));
+            }
+            PropertiesDialogCmdOut::Size(Err(error)) => {
+                self.file_count_row.set_subtitle(&describe_error(&error));
+                self.total_size_row.set_subtitle("Unknown");
+            }
+        }
+    }
+}
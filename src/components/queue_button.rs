@@ -1,15 +1,19 @@
-use crate::globals::JOBS;
+use crate::globals::{JOBS, JOB_PROGRESS, QUEUE_PAUSED};
 use crate::icons::icon_names;
-use crate::model::{get_ongoing_jobs, has_failed_jobs};
+use crate::model::{
+    get_ongoing_jobs, get_overall_progress, get_queued_jobs, has_failed_jobs, is_queue_paused,
+    toggle_queue_paused,
+};
 use relm4::adw::prelude::AdwDialogExt;
-use relm4::gtk::prelude::{BoxExt, ButtonExt, WidgetExt};
+use relm4::gtk::prelude::{BoxExt, ButtonExt, OrientableExt, WidgetExt};
 use relm4::gtk::{self};
 use relm4::SimpleComponent;
 use relm4::{Component, ComponentSender};
 use relm4::{ComponentController, Controller};
 use relm4::{ComponentParts, RelmWidgetExt};
+use uuid::Uuid;
 
-use super::queue_dialog::QueueDialog;
+use super::queue_dialog::{QueueDialog, QueueDialogOutput};
 
 #[derive(Debug)]
 pub struct QueueButton {
@@ -21,13 +25,39 @@ pub struct QueueButton {
 pub enum QueueViewInMsg {
     DialogRequested,
     JobsUpdated,
+    RetryRequested(Uuid),
+    PauseToggled,
+}
+
+#[derive(Debug)]
+pub enum QueueViewOutMsg {
+    RetryRequested(Uuid),
+}
+
+impl QueueButton {
+    fn status_text() -> String {
+        let queued_job_count = get_queued_jobs().len();
+        if is_queue_paused() {
+            format!("Paused ({} queued)", queued_job_count)
+        } else if !get_ongoing_jobs().is_empty() && queued_job_count > 0 {
+            format!("Working ({} queued)", queued_job_count)
+        } else if !get_ongoing_jobs().is_empty() {
+            String::from("Working")
+        } else if queued_job_count > 0 {
+            format!("{} queued", queued_job_count)
+        } else if has_failed_jobs() {
+            String::from("Error")
+        } else {
+            String::from("Ready")
+        }
+    }
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for QueueButton {
     type Init = ();
     type Input = QueueViewInMsg;
-    type Output = ();
+    type Output = QueueViewOutMsg;
 
     view! {
         #[root]
@@ -41,36 +71,60 @@ impl SimpleComponent for QueueButton {
                 connect_clicked => QueueViewInMsg::DialogRequested,
 
                 gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
                     set_valign: gtk::Align::Center,
-                    set_spacing: 10,
+                    set_spacing: 5,
 
-                    if !get_ongoing_jobs().is_empty() {
-                        gtk::Spinner {
-                            set_spinning: true,
-                        }
-                    } else {
-                        gtk::Image {
-                            #[watch]
-                            set_icon_name: if has_failed_jobs() {
-                                Some(icon_names::ERROR_OUTLINE)
-                            } else {
-                                Some(icon_names::CHECK_ROUND_OUTLINE)
+                    gtk::Box {
+                        set_spacing: 10,
+
+                        if !get_ongoing_jobs().is_empty() {
+                            gtk::Spinner {
+                                set_spinning: true,
+                            }
+                        } else {
+                            gtk::Image {
+                                #[watch]
+                                set_icon_name: if has_failed_jobs() {
+                                    Some(icon_names::ERROR_OUTLINE)
+                                } else {
+                                    Some(icon_names::CHECK_ROUND_OUTLINE)
+                                }
                             }
+                        },
+
+                        gtk::Label {
+                            #[watch]
+                            set_text: &Self::status_text(),
+                            set_halign: gtk::Align::Start,
                         }
                     },
 
-                    gtk::Label {
+                    gtk::ProgressBar {
                         #[watch]
-                        set_text: if !get_ongoing_jobs().is_empty() {
-                            "Working"
-                        } else if has_failed_jobs() {
-                            "Error"
-                        } else {
-                            "Ready"
-                        },
-                        set_halign: gtk::Align::Start,
-                    }
+                        set_visible: get_overall_progress().is_some(),
+                        #[watch]
+                        set_fraction: get_overall_progress().unwrap_or(0.0),
+                    },
+                },
+            },
+
+            gtk::Button {
+                #[watch]
+                set_icon_name: if is_queue_paused() {
+                    "media-playback-start-symbolic"
+                } else {
+                    "media-playback-pause-symbolic"
+                },
+                #[watch]
+                set_tooltip_text: if is_queue_paused() {
+                    Some("Resume queue")
+                } else {
+                    Some("Pause queue")
                 },
+                set_width_request: 50,
+                inline_css: "border-radius: 0",
+                connect_clicked => QueueViewInMsg::PauseToggled,
             },
         }
     }
@@ -81,16 +135,30 @@ impl SimpleComponent for QueueButton {
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         JOBS.subscribe(sender.input_sender(), |_| Self::Input::JobsUpdated);
+        JOB_PROGRESS.subscribe(sender.input_sender(), |_| Self::Input::JobsUpdated);
+        QUEUE_PAUSED.subscribe(sender.input_sender(), |_| Self::Input::JobsUpdated);
         let widgets = view_output!();
-        let dialog = QueueDialog::builder().launch(()).detach();
+        let dialog = QueueDialog::builder()
+            .launch(())
+            .forward(sender.input_sender(), |msg| match msg {
+                QueueDialogOutput::RetryRequested(uuid) => QueueViewInMsg::RetryRequested(uuid),
+            });
         let model = Self { root, dialog };
 
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
             Self::Input::JobsUpdated => {}
+            Self::Input::PauseToggled => {
+                toggle_queue_paused();
+            }
+            Self::Input::RetryRequested(uuid) => {
+                sender
+                    .output(Self::Output::RetryRequested(uuid))
+                    .expect("Could not broadcast retry request");
+            }
             Self::Input::DialogRequested => {
                 self.dialog.widget().present(Some(&self.root));
             }
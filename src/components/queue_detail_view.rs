@@ -1,4 +1,5 @@
-use crate::globals::JOBS;
+use crate::client::TransferStats;
+use crate::globals::{JOBS, JOB_PROGRESS};
 use crate::icons::icon_names;
 use crate::model::{RcloneJob, RcloneJobStatus, RcloneJobType};
 use relm4::adw;
@@ -12,6 +13,7 @@ pub struct QueueDetailView {
     uuid: Uuid,
     job_description: String,
     job_copy: RcloneJob,
+    progress: Option<TransferStats>,
 }
 
 #[derive(Debug)]
@@ -20,68 +22,91 @@ pub enum QueueDetailInMsg {
     JobUpdated,
     #[doc(hidden)]
     JobDeletionRequested,
+    #[doc(hidden)]
+    JobCancellationRequested,
+    #[doc(hidden)]
+    JobRetryRequested,
+}
+
+#[derive(Debug)]
+pub enum QueueDetailOutMsg {
+    RetryRequested(Uuid),
 }
 
 impl QueueDetailView {
-    fn make_job_description(job: &RcloneJob) -> String {
-        match job.r#type.clone() {
-            RcloneJobType::Upload {
-                local_path: source,
-                remote_path: dest,
-            } => {
-                format!(
-                    "Upload {} to {}",
-                    source.filename(),
-                    dest.resolve_to_parent()
-                )
-            }
-            RcloneJobType::Move {
-                source_path,
-                target_path,
-            } => {
-                format!(
-                    "Move {} to {}",
-                    source_path.filename(),
-                    target_path.resolve_to_parent()
-                )
-            }
-            RcloneJobType::Copy {
-                source_path,
-                target_path,
-            } => {
-                format!(
-                    "Copy {} to {}",
-                    source_path.filename(),
-                    target_path.resolve_to_parent()
-                )
-            }
-            RcloneJobType::Rename(path) => {
-                format!("Rename {} in {}", path.filename(), path.resolve_to_parent())
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn make_subtitle(job: &RcloneJob, progress: &Option<TransferStats>) -> String {
+        let attempt = match job.history.len() {
+            0 => None,
+            n => Some(format!("Attempt {}", n + 1)),
+        };
+        let batch_progress = match &job.r#type {
+            RcloneJobType::Batch { items } if !job.finished_items.is_empty() => Some(format!(
+                "{} of {} done",
+                job.finished_items.len(),
+                items.len()
+            )),
+            _ => None,
+        };
+        let details = match (&job.status, progress) {
+            (RcloneJobStatus::Ongoing, Some(progress)) => {
+                let mut parts = vec![progress.formatted_progress(), progress.formatted_speed()];
+                if let Some(eta) = progress.formatted_eta() {
+                    parts.push(format!("{} left", eta));
+                }
+                Some(parts.join(" · "))
             }
-            RcloneJobType::Download {
-                local_path,
-                remote_path,
-            } => {
-                format!(
-                    "Download {} to {}",
-                    remote_path.filename(),
-                    local_path.resolve_to_parent()
-                )
+            (RcloneJobStatus::Failed(_), _) if job.retry_scheduled => {
+                Some(String::from("Retrying automatically"))
             }
-            RcloneJobType::Delete(path) => {
-                format!(
-                    "Delete {} from {}",
-                    path.filename(),
-                    path.resolve_to_parent()
-                )
+            (RcloneJobStatus::Failed(error), _) => Self::summarize_error(error),
+            (RcloneJobStatus::Interrupted, _) => {
+                Some(String::from("Interrupted when the app closed"))
             }
-            RcloneJobType::Open { remote_path, .. } => {
+            _ => None,
+        };
+        attempt
+            .into_iter()
+            .chain(batch_progress)
+            .chain(details)
+            .collect::<Vec<String>>()
+            .join(" · ")
+    }
+
+    fn summarize_error(error: &str) -> Option<String> {
+        error
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .last()
+            .map(String::from)
+    }
+
+    fn make_history_tooltip(job: &RcloneJob) -> Option<String> {
+        if job.history.is_empty() {
+            return None;
+        }
+        let attempts = job
+            .history
+            .iter()
+            .enumerate()
+            .map(|(i, attempt)| {
                 format!(
-                    "Open {} from {}",
-                    remote_path.filename(),
-                    remote_path.resolve_to_parent()
+                    "Attempt {}: {}",
+                    i + 1,
+                    Self::summarize_error(&attempt.error).unwrap_or_default()
                 )
-            }
+            })
+            .collect::<Vec<String>>();
+        Some(attempts.join("\n"))
+    }
+
+    fn make_job_description(job: &RcloneJob) -> String {
+        match &job.undoes {
+            Some(undone) => format!("Undo: {}", undone),
+            None => job.r#type.describe(),
         }
     }
 }
@@ -90,7 +115,7 @@ impl QueueDetailView {
 impl FactoryComponent for QueueDetailView {
     type Init = Uuid;
     type Input = QueueDetailInMsg;
-    type Output = ();
+    type Output = QueueDetailOutMsg;
     type CommandOutput = ();
     type Widgets = CounterWidgets;
     type ParentWidget = adw::PreferencesGroup;
@@ -100,7 +125,12 @@ impl FactoryComponent for QueueDetailView {
         adw::PreferencesRow {
             #[wrap(Some)]
             set_child = &adw::ActionRow {
+                #[watch]
                 set_title: self.job_description.as_str(),
+                #[watch]
+                set_subtitle: &Self::make_subtitle(&self.job_copy, &self.progress),
+                #[watch]
+                set_tooltip_text: Self::make_history_tooltip(&self.job_copy).as_deref(),
                 set_activatable: false,
                 set_selectable: false,
                 add_prefix = if matches!(self.job_copy.status, RcloneJobStatus::Ongoing) {
@@ -112,25 +142,66 @@ impl FactoryComponent for QueueDetailView {
                 } else {
                     gtk::Image {
                         set_pixel_size: 20,
+                        #[watch]
                         set_icon_name: match self.job_copy.status {
+                            RcloneJobStatus::Queued => Some("content-loading-symbolic"),
                             RcloneJobStatus::Ongoing => None,
                             RcloneJobStatus::Finished => Some(icon_names::CHECK_ROUND_OUTLINE),
-                            RcloneJobStatus::Failed(_) => Some(icon_names::ERROR_OUTLINE)
+                            RcloneJobStatus::Failed(_) => Some(icon_names::ERROR_OUTLINE),
+                            RcloneJobStatus::Cancelled => Some("action-unavailable-symbolic"),
+                            RcloneJobStatus::Interrupted => Some(icon_names::WARNING_OUTLINE),
                         },
+                        #[watch]
                         set_tooltip_text: match self.job_copy.status {
+                            RcloneJobStatus::Queued => Some("Queued"),
                             RcloneJobStatus::Ongoing => Some("Ongoing"),
                             RcloneJobStatus::Finished => Some("Finished"),
-                            RcloneJobStatus::Failed(_) => Some("Failed")
+                            RcloneJobStatus::Failed(_) => Some("Failed"),
+                            RcloneJobStatus::Cancelled => Some("Cancelled"),
+                            RcloneJobStatus::Interrupted => Some("Interrupted"),
                         },
                     }
                 },
+                add_suffix = &gtk::ProgressBar {
+                    set_valign: gtk::Align::Center,
+                    set_width_request: 80,
+                    #[watch]
+                    set_visible: self.job_copy.status == RcloneJobStatus::Ongoing && self.progress.is_some(),
+                    #[watch]
+                    set_fraction: self.progress.as_ref().map(|p| p.fraction()).unwrap_or(0.0),
+                },
+                add_suffix = &gtk::Button {
+                    set_halign: gtk::Align::End,
+                    set_valign: gtk::Align::Center,
+                    set_has_frame: false,
+                    set_icon_name: "process-stop-symbolic",
+                    set_tooltip_text: Some("Cancel job"),
+                    #[watch]
+                    set_visible: self.job_copy.is_active(),
+                    connect_clicked => Self::Input::JobCancellationRequested,
+                },
+                add_suffix = &gtk::Button {
+                    set_halign: gtk::Align::End,
+                    set_valign: gtk::Align::Center,
+                    set_has_frame: false,
+                    set_icon_name: icon_names::ARROW_CIRCULAR_TOP_RIGHT,
+                    #[watch]
+                    set_tooltip_text: match self.job_copy.status {
+                        RcloneJobStatus::Interrupted => Some("Resume"),
+                        _ => Some("Retry"),
+                    },
+                    #[watch]
+                    set_visible: self.job_copy.is_retryable(),
+                    connect_clicked => Self::Input::JobRetryRequested,
+                },
                 add_suffix = &gtk::Button {
                     set_halign: gtk::Align::End,
                     set_valign: gtk::Align::Center,
                     set_has_frame: false,
                     set_icon_name: icon_names::MINUS_CIRCLE_FILLED,
                     set_tooltip_text: Some("Remove from queue"),
-                    set_visible: self.job_copy.status != RcloneJobStatus::Ongoing,
+                    #[watch]
+                    set_visible: !self.job_copy.is_active(),
                     connect_clicked => Self::Input::JobDeletionRequested,
                 }
             }
@@ -139,25 +210,41 @@ impl FactoryComponent for QueueDetailView {
 
     fn init_model(value: Self::Init, _index: &DynamicIndex, sender: FactorySender<Self>) -> Self {
         JOBS.subscribe(sender.input_sender(), |_| Self::Input::JobUpdated);
+        JOB_PROGRESS.subscribe(sender.input_sender(), |_| Self::Input::JobUpdated);
         let job_copy = JOBS.read().get(&value).unwrap().clone();
         Self {
             uuid: value,
             job_copy: job_copy.clone(),
             job_description: Self::make_job_description(&job_copy),
+            progress: JOB_PROGRESS.read().get(&value).cloned(),
         }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: FactorySender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: FactorySender<Self>) {
         match message {
             Self::Input::JobUpdated => {
                 if let Some(job) = JOBS.read().get(&self.uuid) {
                     self.job_copy = job.clone();
                     self.job_description = Self::make_job_description(&self.job_copy);
                 }
+                self.progress = JOB_PROGRESS.read().get(&self.uuid).cloned();
             }
             Self::Input::JobDeletionRequested => {
                 JOBS.write().remove(&self.uuid);
             }
+            Self::Input::JobRetryRequested => {
+                sender
+                    .output(Self::Output::RetryRequested(self.uuid))
+                    .expect("Could not broadcast retry request");
+            }
+            Self::Input::JobCancellationRequested => {
+                if let Some(job) = JOBS.write().get_mut(&self.uuid) {
+                    match job.status {
+                        RcloneJobStatus::Queued => job.set_status(RcloneJobStatus::Cancelled),
+                        _ => job.cancel(),
+                    }
+                }
+            }
         }
     }
 }
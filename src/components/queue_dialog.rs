@@ -9,15 +9,17 @@ use relm4::gtk::{self};
 use relm4::ComponentSender;
 use relm4::RelmWidgetExt;
 use relm4::{Component, ComponentParts};
+use uuid::Uuid;
 
-use crate::globals::JOBS;
-use crate::model::{RcloneJob, RcloneJobStatus};
+use crate::globals::{JOBS, QUEUE_PAUSED};
+use crate::model::{is_queue_paused, toggle_queue_paused, RcloneJob, RcloneJobStatus};
 
-use super::queue_detail_view::QueueDetailView;
+use super::queue_detail_view::{QueueDetailOutMsg, QueueDetailView};
 
 #[derive(Debug)]
 pub struct QueueDialog {
     ongoing_queue_wrapper: FactoryVecDeque<QueueDetailView>,
+    queued_queue_wrapper: FactoryVecDeque<QueueDetailView>,
     terminated_queue_wrapper: FactoryVecDeque<QueueDetailView>,
 }
 
@@ -25,26 +27,64 @@ pub struct QueueDialog {
 pub enum QueueDialogInput {
     JobsUpdated,
     CleanNonOngoingJobs,
+    RetryRequested(Uuid),
+    PauseToggled,
+}
+
+#[derive(Debug)]
+pub enum QueueDialogOutput {
+    RetryRequested(Uuid),
 }
 
 impl QueueDialog {
     fn propagate_jobs_update(
         ongoing_queue_wrapper: &mut FactoryVecDeque<QueueDetailView>,
+        queued_queue_wrapper: &mut FactoryVecDeque<QueueDetailView>,
         terminated_queue_wrapper: &mut FactoryVecDeque<QueueDetailView>,
     ) {
-        ongoing_queue_wrapper.guard().clear();
-        terminated_queue_wrapper.guard().clear();
-
         let mut ordered_jobs = JOBS.read().values().cloned().collect::<Vec<RcloneJob>>();
         ordered_jobs.sort_by(|a, b| b.started_at.cmp(&a.started_at));
 
-        for job in ordered_jobs {
-            if job.status == RcloneJobStatus::Ongoing {
-                ongoing_queue_wrapper.guard().push_back(job.uuid);
-            } else {
-                terminated_queue_wrapper.guard().push_back(job.uuid);
+        let mut ongoing_jobs = vec![];
+        let mut terminated_jobs = vec![];
+        for job in ordered_jobs.iter() {
+            match job.status {
+                RcloneJobStatus::Ongoing => ongoing_jobs.push(job.uuid),
+                RcloneJobStatus::Queued => continue,
+                _ => terminated_jobs.push(job.uuid),
+            };
+        }
+
+        // Waiting jobs are listed in the order in which they will start
+        ordered_jobs.retain(|j| j.status == RcloneJobStatus::Queued);
+        ordered_jobs.sort_by_key(|j| j.queued_at);
+        let queued_jobs = ordered_jobs.iter().map(|j| j.uuid).collect::<Vec<Uuid>>();
+
+        Self::sync_rows(ongoing_queue_wrapper, &ongoing_jobs);
+        Self::sync_rows(queued_queue_wrapper, &queued_jobs);
+        Self::sync_rows(terminated_queue_wrapper, &terminated_jobs);
+    }
+
+    /// Brings a list in line with the given jobs. Rows that stay keep their widgets and update
+    /// themselves, so that a change to one job doesn't rebuild the whole list.
+    fn sync_rows(wrapper: &mut FactoryVecDeque<QueueDetailView>, uuids: &[Uuid]) {
+        let mut guard = wrapper.guard();
+        for index in (0..guard.len()).rev() {
+            let is_listed = guard
+                .get(index)
+                .is_some_and(|row| uuids.contains(&row.uuid()));
+            if !is_listed {
+                guard.remove(index);
             }
         }
+        let kept = guard.iter().map(|row| row.uuid()).collect::<Vec<Uuid>>();
+        // Preference groups can only append rows, so a new order means starting over
+        if !uuids.starts_with(&kept) {
+            guard.clear();
+        }
+        for uuid in &uuids[guard.len()..] {
+            guard.push_back(*uuid);
+        }
     }
 }
 
@@ -52,7 +92,7 @@ impl QueueDialog {
 impl Component for QueueDialog {
     type Init = ();
     type Input = QueueDialogInput;
-    type Output = ();
+    type Output = QueueDialogOutput;
     type CommandOutput = ();
 
     view! {
@@ -62,68 +102,116 @@ impl Component for QueueDialog {
             set_can_close: true,
             #[wrap(Some)]
             set_child = &adw::ToolbarView {
-                add_top_bar = &adw::HeaderBar {},
+                add_top_bar = &adw::HeaderBar {
+                    pack_start = &gtk::Button {
+                        #[watch]
+                        set_icon_name: if is_queue_paused() {
+                            "media-playback-start-symbolic"
+                        } else {
+                            "media-playback-pause-symbolic"
+                        },
+                        #[watch]
+                        set_tooltip_text: if is_queue_paused() {
+                            Some("Resume queue")
+                        } else {
+                            Some("Pause queue")
+                        },
+                        connect_clicked => Self::Input::PauseToggled,
+                    },
+                },
 
                 #[wrap(Some)]
-                set_content = &gtk::Box {
-                    set_orientation: gtk::Orientation::Vertical,
-                    set_hexpand: true,
-                    set_halign: gtk::Align::Center,
-                    set_valign: gtk::Align::Start,
-                    set_margin_all: 20,
-                    set_margin_top: 0,
-                    set_spacing: 20,
-
-                    #[local_ref]
-                    ongoing_queue_view -> adw::PreferencesGroup {
-                        set_title: "Ongoing jobs",
-
-                        adw::PreferencesRow {
-                            #[watch]
-                            set_visible: model.ongoing_queue_wrapper.is_empty(),
+                set_content = &gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+                    set_propagate_natural_height: true,
 
-                            #[wrap(Some)]
-                            set_child = &gtk::Box {
-                                set_orientation: gtk::Orientation::Horizontal,
-                                set_margin_all: 10,
-
-                                gtk::Label {
-                                    set_text: "Empty",
-                                    set_opacity: 0.4,
-                                },
-                            }
-                        }
-                    },
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_hexpand: true,
+                        set_halign: gtk::Align::Center,
+                        set_valign: gtk::Align::Start,
+                        set_margin_all: 20,
+                        set_margin_top: 0,
+                        set_spacing: 20,
 
-                    #[local_ref]
-                    terminated_queue_view -> adw::PreferencesGroup {
-                        set_title: "Terminated jobs",
-                        #[wrap(Some)]
-                        set_header_suffix = &gtk::Button {
-                            set_label: "Clear",
-                            #[watch]
-                            set_sensitive: !model.terminated_queue_wrapper.is_empty(),
-                            set_tooltip_text: Some("Clear terminated jobs"),
-                            connect_clicked => Self::Input::CleanNonOngoingJobs,
-                            set_margin_start: 10,
+                        #[local_ref]
+                        ongoing_queue_view -> adw::PreferencesGroup {
+                            set_title: "Ongoing jobs",
+
+                            adw::PreferencesRow {
+                                #[watch]
+                                set_visible: model.ongoing_queue_wrapper.is_empty(),
+
+                                #[wrap(Some)]
+                                set_child = &gtk::Box {
+                                    set_orientation: gtk::Orientation::Horizontal,
+                                    set_margin_all: 10,
+
+                                    gtk::Label {
+                                        set_text: "Empty",
+                                        set_opacity: 0.4,
+                                    },
+                                }
+                            }
                         },
 
-                        add = &adw::PreferencesRow {
+                        #[local_ref]
+                        queued_queue_view -> adw::PreferencesGroup {
+                            set_title: "Queued jobs",
                             #[watch]
-                            set_visible: model.terminated_queue_wrapper.is_empty(),
+                            set_description: Some(&if is_queue_paused() {
+                                format!("{} waiting, queue paused", model.queued_queue_wrapper.len())
+                            } else {
+                                format!("{} waiting", model.queued_queue_wrapper.len())
+                            }),
+
+                            adw::PreferencesRow {
+                                #[watch]
+                                set_visible: model.queued_queue_wrapper.is_empty(),
+
+                                #[wrap(Some)]
+                                set_child = &gtk::Box {
+                                    set_orientation: gtk::Orientation::Horizontal,
+                                    set_margin_all: 10,
+
+                                    gtk::Label {
+                                        set_text: "Empty",
+                                        set_opacity: 0.4,
+                                    },
+                                }
+                            }
+                        },
 
+                        #[local_ref]
+                        terminated_queue_view -> adw::PreferencesGroup {
+                            set_title: "Terminated jobs",
                             #[wrap(Some)]
-                            set_child = &gtk::Box {
-                                set_orientation: gtk::Orientation::Horizontal,
-                                set_margin_all: 10,
-
-                                gtk::Label {
-                                    set_text: "Empty",
-                                    set_opacity: 0.4,
-                                },
+                            set_header_suffix = &gtk::Button {
+                                set_label: "Clear",
+                                #[watch]
+                                set_sensitive: !model.terminated_queue_wrapper.is_empty(),
+                                set_tooltip_text: Some("Clear terminated jobs"),
+                                connect_clicked => Self::Input::CleanNonOngoingJobs,
+                                set_margin_start: 10,
+                            },
+
+                            add = &adw::PreferencesRow {
+                                #[watch]
+                                set_visible: model.terminated_queue_wrapper.is_empty(),
+
+                                #[wrap(Some)]
+                                set_child = &gtk::Box {
+                                    set_orientation: gtk::Orientation::Horizontal,
+                                    set_margin_all: 10,
+
+                                    gtk::Label {
+                                        set_text: "Empty",
+                                        set_opacity: 0.4,
+                                    },
+                                }
                             }
-                        }
-                    },
+                        },
+                    }
                 }
             }
         }
@@ -135,42 +223,68 @@ impl Component for QueueDialog {
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         JOBS.subscribe(sender.input_sender(), |_| Self::Input::JobsUpdated);
+        QUEUE_PAUSED.subscribe(sender.input_sender(), |_| Self::Input::JobsUpdated);
         let mut ongoing_queue_wrapper = FactoryVecDeque::builder()
             .launch(adw::PreferencesGroup::default())
-            .detach();
+            .forward(sender.input_sender(), |msg| match msg {
+                QueueDetailOutMsg::RetryRequested(uuid) => Self::Input::RetryRequested(uuid),
+            });
+        let mut queued_queue_wrapper = FactoryVecDeque::builder()
+            .launch(adw::PreferencesGroup::default())
+            .forward(sender.input_sender(), |msg| match msg {
+                QueueDetailOutMsg::RetryRequested(uuid) => Self::Input::RetryRequested(uuid),
+            });
         let mut terminated_queue_wrapper = FactoryVecDeque::builder()
             .launch(adw::PreferencesGroup::default())
-            .detach();
-        Self::propagate_jobs_update(&mut ongoing_queue_wrapper, &mut terminated_queue_wrapper);
+            .forward(sender.input_sender(), |msg| match msg {
+                QueueDetailOutMsg::RetryRequested(uuid) => Self::Input::RetryRequested(uuid),
+            });
+        Self::propagate_jobs_update(
+            &mut ongoing_queue_wrapper,
+            &mut queued_queue_wrapper,
+            &mut terminated_queue_wrapper,
+        );
         let model = Self {
             ongoing_queue_wrapper,
+            queued_queue_wrapper,
             terminated_queue_wrapper,
         };
         let ongoing_queue_view = model.ongoing_queue_wrapper.widget();
+        let queued_queue_view = model.queued_queue_wrapper.widget();
         let terminated_queue_view = model.terminated_queue_wrapper.widget();
         let widgets = view_output!();
 
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>, _root: &Self::Root) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
         match message {
             Self::Input::CleanNonOngoingJobs => {
                 let mut new_jobs = BTreeMap::new();
                 for job in JOBS.read().iter() {
-                    if job.1.status == RcloneJobStatus::Ongoing {
+                    if job.1.is_active() {
                         new_jobs.insert(*job.0, job.1.clone());
                     }
                 }
                 *JOBS.write() = new_jobs;
                 Self::propagate_jobs_update(
                     &mut self.ongoing_queue_wrapper,
+                    &mut self.queued_queue_wrapper,
                     &mut self.terminated_queue_wrapper,
                 );
             }
+            Self::Input::PauseToggled => {
+                toggle_queue_paused();
+            }
+            Self::Input::RetryRequested(uuid) => {
+                sender
+                    .output(Self::Output::RetryRequested(uuid))
+                    .expect("Could not broadcast retry request");
+            }
             Self::Input::JobsUpdated => {
                 Self::propagate_jobs_update(
                     &mut self.ongoing_queue_wrapper,
+                    &mut self.queued_queue_wrapper,
                     &mut self.terminated_queue_wrapper,
                 );
             }
@@ -0,0 +1,428 @@
+use adw::glib::clone;
+use adw::prelude::{AdwDialogExt, ComboRowExt, EditableExt, ExpanderRowExt, PreferencesGroupExt};
+use relm4::gtk::prelude::{BoxExt, ButtonExt, Cast, OrientableExt, WidgetExt};
+use relm4::gtk::{self};
+use relm4::ComponentSender;
+use relm4::{adw, RelmWidgetExt};
+use relm4::{Component, ComponentParts};
+
+use crate::client::{is_valid_remote_name, ProviderOption, RemoteConfig, RemoteProvider};
+
+/// The option that picks a variant of a backend, like the vendor behind an S3 remote. Which other
+/// options apply depends on its value.
+const VARIANT_OPTION: &str = "provider";
+
+#[derive(Debug)]
+enum OptionInput {
+    Text(adw::EntryRow),
+    Password(adw::PasswordEntryRow),
+    Switch(adw::SwitchRow),
+    Choice(adw::ComboRow, Vec<String>),
+}
+
+impl OptionInput {
+    fn build(option: &ProviderOption, variant: &str, value: &str) -> Self {
+        let examples = option.examples_for(variant);
+        if option.is_secret() {
+            let row = adw::PasswordEntryRow::builder()
+                .title(option.summary())
+                .tooltip_text(&option.help)
+                .build();
+            row.set_text(value);
+            Self::Password(row)
+        } else if option.value_type == "bool" {
+            Self::Switch(
+                adw::SwitchRow::builder()
+                    .title(option.summary())
+                    .tooltip_text(&option.help)
+                    .active(value == "true")
+                    .build(),
+            )
+        } else if !examples.is_empty() {
+            let mut choices = examples
+                .iter()
+                .map(|e| {
+                    let label = match e.help.lines().next() {
+                        Some(help) if !help.is_empty() => help.to_owned(),
+                        _ => e.value.clone(),
+                    };
+                    (e.value.clone(), label)
+                })
+                .collect::<Vec<(String, String)>>();
+            if !choices.iter().any(|(v, _)| *v == option.default) {
+                let label = match option.default.is_empty() {
+                    true => String::from("Not set"),
+                    false => format!("Default ({})", option.default),
+                };
+                choices.insert(0, (option.default.clone(), label));
+            }
+            if !choices.iter().any(|(v, _)| v == value) {
+                choices.insert(0, (value.to_owned(), value.to_owned()));
+            }
+            let labels = choices
+                .iter()
+                .map(|(_, label)| label.as_str())
+                .collect::<Vec<&str>>();
+            let row = adw::ComboRow::builder()
+                .title(option.summary())
+                .tooltip_text(&option.help)
+                .model(&gtk::StringList::new(&labels))
+                .selected(choices.iter().position(|(v, _)| v == value).unwrap_or(0) as u32)
+                .build();
+            Self::Choice(row, choices.into_iter().map(|(v, _)| v).collect())
+        } else {
+            let row = adw::EntryRow::builder()
+                .title(option.summary())
+                .tooltip_text(&option.help)
+                .build();
+            row.set_text(value);
+            Self::Text(row)
+        }
+    }
+
+    fn value(&self) -> String {
+        match self {
+            Self::Text(row) => row.text().to_string(),
+            Self::Password(row) => row.text().to_string(),
+            Self::Switch(row) => row.is_active().to_string(),
+            Self::Choice(row, values) => values
+                .get(row.selected() as usize)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    fn widget(&self) -> gtk::Widget {
+        match self {
+            Self::Text(row) => row.clone().upcast(),
+            Self::Password(row) => row.clone().upcast(),
+            Self::Switch(row) => row.clone().upcast(),
+            Self::Choice(row, _) => row.clone().upcast(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OptionRow {
+    option: ProviderOption,
+    input: OptionInput,
+}
+
+/// A remote as submitted from the editor.
+#[derive(Debug, Clone)]
+pub struct RemoteEdit {
+    /// The name the remote had before, when an existing remote was edited.
+    pub original_name: Option<String>,
+    pub name: String,
+    pub provider: String,
+    /// The options that differ from the stored config, or from the defaults for a new remote.
+    pub values: RemoteConfig,
+}
+
+#[derive(Debug)]
+pub struct RemoteEditorDialog {
+    providers: Vec<RemoteProvider>,
+    taken_names: Vec<String>,
+    original_name: Option<String>,
+    original_config: RemoteConfig,
+    name_row: adw::EntryRow,
+    type_row: adw::ComboRow,
+    options_box: gtk::Box,
+    option_rows: Vec<OptionRow>,
+}
+
+#[derive(Debug)]
+pub struct RemoteEditorDialogInit {
+    pub providers: Vec<RemoteProvider>,
+    /// All configured remotes, named without the colon.
+    pub remotes: Vec<String>,
+    /// The name and stored config of the remote to edit, or `None` to add a new one.
+    pub existing: Option<(String, RemoteConfig)>,
+}
+
+#[derive(Debug)]
+pub enum RemoteEditorDialogInMsg {
+    ProviderChanged,
+    VariantChanged,
+    SaveRequested,
+    DeleteRequested,
+}
+
+#[derive(Debug)]
+pub enum RemoteEditorDialogOutMsg {
+    Saved(RemoteEdit),
+    DeleteRequested(String),
+}
+
+impl RemoteEditorDialog {
+    fn selected_provider(&self) -> Option<&RemoteProvider> {
+        self.providers.get(self.type_row.selected() as usize)
+    }
+
+    fn entered_values(&self) -> RemoteConfig {
+        self.option_rows
+            .iter()
+            .map(|row| (row.option.name.clone(), row.input.value()))
+            .collect()
+    }
+
+    /// Lays out the options of the selected backend, keeping whatever was already filled in.
+    fn rebuild_options(&mut self, sender: &ComponentSender<Self>) {
+        let entered_values = self.entered_values();
+        while let Some(child) = self.options_box.first_child() {
+            self.options_box.remove(&child);
+        }
+        self.option_rows.clear();
+        let Some(provider) = self.selected_provider().cloned() else {
+            return;
+        };
+
+        // Secrets start out empty and keep what is stored unless something is typed in, so
+        // they never show up on screen
+        let current_value = |option: &ProviderOption| {
+            entered_values
+                .get(&option.name)
+                .or(match option.is_secret() {
+                    true => None,
+                    false => self.original_config.get(&option.name),
+                })
+                .cloned()
+                .unwrap_or(match option.is_secret() {
+                    true => String::from(""),
+                    false => option.default.clone(),
+                })
+        };
+        let variant = provider
+            .options
+            .iter()
+            .find(|o| o.name == VARIANT_OPTION)
+            .map(&current_value)
+            .unwrap_or_default();
+
+        let options_group = adw::PreferencesGroup::builder().title("Options").build();
+        let advanced_row = adw::ExpanderRow::builder()
+            .title("Advanced options")
+            .build();
+        let advanced_group = adw::PreferencesGroup::new();
+        advanced_group.add(&advanced_row);
+        for option in provider.options.iter().filter(|o| o.is_shown_for(&variant)) {
+            let input = OptionInput::build(option, &variant, &current_value(option));
+            if let (VARIANT_OPTION, OptionInput::Choice(row, _)) = (option.name.as_str(), &input) {
+                row.connect_selected_notify(clone!(
+                    #[strong]
+                    sender,
+                    move |_| {
+                        sender.input(RemoteEditorDialogInMsg::VariantChanged);
+                    }
+                ));
+            }
+            match option.advanced {
+                true => advanced_row.add_row(&input.widget()),
+                false => options_group.add(&input.widget()),
+            }
+            self.option_rows.push(OptionRow {
+                option: option.clone(),
+                input,
+            });
+        }
+        options_group.set_visible(self.option_rows.iter().any(|r| !r.option.advanced));
+        advanced_group.set_visible(self.option_rows.iter().any(|r| r.option.advanced));
+        self.options_box.append(&options_group);
+        self.options_box.append(&advanced_group);
+    }
+
+    /// Marks the fields that need attention, returning whether the form can be saved.
+    fn validate(&self) -> bool {
+        let name = self.name_row.text().to_string();
+        let name_taken =
+            self.taken_names.contains(&name) && self.original_name.as_ref() != Some(&name);
+        let name_valid = is_valid_remote_name(&name) && !name_taken;
+        set_error(&self.name_row.clone().upcast(), !name_valid);
+
+        let mut options_valid = true;
+        for row in self.option_rows.iter() {
+            let already_stored = self.original_config.contains_key(&row.option.name);
+            let missing = row.option.required && row.input.value().is_empty() && !already_stored;
+            set_error(&row.input.widget(), missing);
+            options_valid &= !missing;
+        }
+        name_valid && options_valid
+    }
+
+    fn changed_values(&self) -> RemoteConfig {
+        self.option_rows
+            .iter()
+            .filter_map(|row| {
+                let value = row.input.value();
+                let stored_value = match row.option.is_secret() {
+                    true => "",
+                    false => self
+                        .original_config
+                        .get(&row.option.name)
+                        .unwrap_or(&row.option.default),
+                };
+                (value != stored_value).then(|| (row.option.name.clone(), value))
+            })
+            .collect()
+    }
+}
+
+fn set_error(widget: &gtk::Widget, has_error: bool) {
+    match has_error {
+        true => widget.add_css_class("error"),
+        false => widget.remove_css_class("error"),
+    }
+}
+
+#[relm4::component(pub)]
+impl Component for RemoteEditorDialog {
+    type Init = RemoteEditorDialogInit;
+    type Input = RemoteEditorDialogInMsg;
+    type Output = RemoteEditorDialogOutMsg;
+    type CommandOutput = ();
+
+    view! {
+        #[root]
+        adw::Dialog {
+            set_title: if model.original_name.is_some() { "Edit remote" } else { "New remote" },
+            set_can_close: true,
+            set_content_width: 500,
+            set_content_height: 600,
+            #[wrap(Some)]
+            set_child = &adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {
+                    pack_end = &gtk::Button {
+                        set_label: "Save",
+                        add_css_class: "suggested-action",
+                        connect_clicked => Self::Input::SaveRequested,
+                    },
+                },
+
+                #[wrap(Some)]
+                set_content = &gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_margin_all: 20,
+                        set_margin_top: 0,
+                        set_spacing: 20,
+
+                        adw::PreferencesGroup {
+                            add: &model.name_row,
+                            add: &model.type_row,
+                        },
+
+                        append: &model.options_box,
+
+                        gtk::Button {
+                            set_label: "Delete remote",
+                            set_halign: gtk::Align::Center,
+                            set_visible: model.original_name.is_some(),
+                            add_css_class: "destructive-action",
+                            connect_clicked => Self::Input::DeleteRequested,
+                        },
+                    }
+                }
+            }
+        }
+    }
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let (original_name, original_config) = match init.existing {
+            Some((name, config)) => (Some(name), config),
+            None => (None, RemoteConfig::new()),
+        };
+        let mut providers = init
+            .providers
+            .into_iter()
+            .filter(|p| !p.hide)
+            .collect::<Vec<RemoteProvider>>();
+        providers.sort_by(|a, b| a.description.cmp(&b.description));
+
+        let name_row = adw::EntryRow::builder().title("Name").build();
+        name_row.set_text(original_name.as_deref().unwrap_or(""));
+        let descriptions = providers
+            .iter()
+            .map(|p| p.description.as_str())
+            .collect::<Vec<&str>>();
+        let type_row = adw::ComboRow::builder()
+            .title("Type")
+            .model(&gtk::StringList::new(&descriptions))
+            .enable_search(true)
+            .sensitive(original_name.is_none())
+            .build();
+        if let Some(index) = original_config
+            .get("type")
+            .and_then(|t| providers.iter().position(|p| p.name == *t))
+        {
+            type_row.set_selected(index as u32);
+        }
+        type_row.connect_selected_notify(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(Self::Input::ProviderChanged);
+            }
+        ));
+
+        let mut model = Self {
+            providers,
+            taken_names: init.remotes,
+            original_name,
+            original_config,
+            name_row,
+            type_row,
+            options_box: gtk::Box::builder()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(20)
+                .build(),
+            option_rows: vec![],
+        };
+        model.rebuild_options(&sender);
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match message {
+            Self::Input::ProviderChanged => {
+                // Options of the previous backend don't carry over
+                self.option_rows.clear();
+                self.rebuild_options(&sender);
+            }
+            Self::Input::VariantChanged => {
+                self.rebuild_options(&sender);
+            }
+            Self::Input::SaveRequested => {
+                let Some(provider) = self.selected_provider() else {
+                    return;
+                };
+                if !self.validate() {
+                    return;
+                }
+                sender
+                    .output(Self::Output::Saved(RemoteEdit {
+                        original_name: self.original_name.clone(),
+                        name: self.name_row.text().to_string(),
+                        provider: provider.name.clone(),
+                        values: self.changed_values(),
+                    }))
+                    .expect("Could not broadcast remote edit");
+                root.close();
+            }
+            Self::Input::DeleteRequested => {
+                if let Some(name) = self.original_name.clone() {
+                    sender
+                        .output(Self::Output::DeleteRequested(name))
+                        .expect("Could not broadcast remote deletion");
+                    root.close();
+                }
+            }
+        }
+    }
+}
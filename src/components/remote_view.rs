@@ -1,35 +1,113 @@
+use std::sync::Arc;
+
 use relm4::factory::FactoryComponent;
-use relm4::gtk::prelude::WidgetExt;
+use relm4::gtk::prelude::{OrientableExt, WidgetExt};
 use relm4::gtk::{self};
+use relm4::FactorySender;
 
 use relm4::RelmWidgetExt;
 
+use crate::client::{RemoteQuota, StorageClient};
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RemoteView {
     pub name: String,
+    /// What the remote reported about its space, if it supports `about`.
+    pub quota: Option<RemoteQuota>,
+}
+
+#[derive(Debug)]
+pub struct RemoteViewInit {
+    pub name: String,
+    pub client: Arc<dyn StorageClient>,
+}
+
+#[derive(Debug)]
+pub enum RemoteViewInMsg {
+    QuotaRefreshRequested(Arc<dyn StorageClient>),
+}
+
+#[derive(Debug)]
+pub enum RemoteViewCmdOut {
+    QuotaLoaded(Option<RemoteQuota>),
+}
+
+impl RemoteView {
+    fn refresh_quota(&self, sender: &FactorySender<Self>, client: Arc<dyn StorageClient>) {
+        let name = self.name.clone();
+        sender.spawn_oneshot_command(move || {
+            // Remotes that can't tell just show their name
+            RemoteViewCmdOut::QuotaLoaded(client.about(&name).ok())
+        });
+    }
+
+    fn summary(&self) -> Option<String> {
+        self.quota.as_ref().and_then(RemoteQuota::summary)
+    }
 }
 
 #[relm4::factory(pub)]
 impl FactoryComponent for RemoteView {
-    type Init = String;
-    type Input = ();
+    type Init = RemoteViewInit;
+    type Input = RemoteViewInMsg;
     type Output = ();
-    type CommandOutput = ();
+    type CommandOutput = RemoteViewCmdOut;
     type ParentWidget = gtk::ListBox;
 
     view! {
-        gtk::Label {
-            set_halign: gtk::Align::Start,
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
             set_margin_all: 10,
-            set_text: &self.name,
+            set_spacing: 4,
+
+            gtk::Label {
+                set_halign: gtk::Align::Start,
+                set_text: &self.name,
+            },
+
+            // Fills up in the accent color, and only turns to a warning once the remote is
+            // nearly full
+            gtk::LevelBar {
+                remove_offset_value: Some(gtk::LEVEL_BAR_OFFSET_LOW),
+                remove_offset_value: Some(gtk::LEVEL_BAR_OFFSET_FULL),
+                add_offset_value: (gtk::LEVEL_BAR_OFFSET_HIGH, 0.9),
+                add_offset_value: (gtk::LEVEL_BAR_OFFSET_LOW, 1.0),
+                #[watch]
+                set_visible: self.quota.and_then(|quota| quota.fraction_used()).is_some(),
+                #[watch]
+                set_value: self.quota.and_then(|quota| quota.fraction_used()).unwrap_or_default(),
+            },
+
+            gtk::Label {
+                set_halign: gtk::Align::Start,
+                add_css_class: "caption",
+                add_css_class: "dim-label",
+                #[watch]
+                set_visible: self.summary().is_some(),
+                #[watch]
+                set_text: &self.summary().unwrap_or_default(),
+            },
         },
     }
 
-    fn init_model(
-        init: Self::Init,
-        _index: &Self::Index,
-        __sender: relm4::prelude::FactorySender<Self>,
-    ) -> Self {
-        Self { name: init }
+    fn init_model(init: Self::Init, _index: &Self::Index, sender: FactorySender<Self>) -> Self {
+        let model = Self {
+            name: init.name,
+            quota: None,
+        };
+        model.refresh_quota(&sender, init.client);
+        model
+    }
+
+    fn update(&mut self, message: Self::Input, sender: FactorySender<Self>) {
+        match message {
+            RemoteViewInMsg::QuotaRefreshRequested(client) => self.refresh_quota(&sender, client),
+        }
+    }
+
+    fn update_cmd(&mut self, message: Self::CommandOutput, _sender: FactorySender<Self>) {
+        match message {
+            RemoteViewCmdOut::QuotaLoaded(quota) => self.quota = quota,
+        }
     }
 }
@@ -0,0 +1,338 @@
+use std::sync::Arc;
+
+use adw::glib::clone;
+use adw::prelude::{ActionRowExt, AdwDialogExt, BoxExt, ButtonExt, EditableExt};
+use relm4::gtk::prelude::{OrientableExt, WidgetExt};
+use relm4::gtk::{self};
+use relm4::ComponentSender;
+use relm4::{adw, RelmWidgetExt};
+use relm4::{Component, ComponentParts};
+
+use crate::client::{ProcessHandle, RcloneError, RcloneFileListing, SearchQuery, StorageClient};
+use crate::icons::icon_names;
+use crate::path_tools::RclonePath;
+
+/// Stops listing hits past this point, so a vague search can't flood the dialog.
+const MAX_HITS: usize = 1000;
+
+#[derive(Debug)]
+enum SearchStatus {
+    Idle,
+    Running,
+    Finished,
+    Cancelled,
+    Failed(RcloneError),
+}
+
+#[derive(Debug)]
+pub struct SearchDialog {
+    client: Arc<dyn StorageClient>,
+    path: RclonePath,
+    pattern_entry: gtk::SearchEntry,
+    depth_button: gtk::SpinButton,
+    hit_list: gtk::ListBox,
+    hit_count: usize,
+    status: SearchStatus,
+    process: ProcessHandle,
+    /// Tells hits of the current search apart from those of a search that was replaced.
+    generation: u32,
+}
+
+#[derive(Debug)]
+pub struct SearchDialogInit {
+    pub client: Arc<dyn StorageClient>,
+    pub path: RclonePath,
+}
+
+#[derive(Debug)]
+pub enum SearchDialogInMsg {
+    SearchRequested,
+    CancelRequested,
+    OpenRequested(RcloneFileListing),
+    RevealRequested(RclonePath),
+}
+
+#[derive(Debug)]
+pub enum SearchDialogOutMsg {
+    OpenRequested(RcloneFileListing),
+    /// Show the folder a hit is in, with the hit selected.
+    RevealRequested(RclonePath),
+}
+
+#[derive(Debug)]
+pub enum SearchDialogCmdOut {
+    Hit(u32, RcloneFileListing),
+    Finished(u32, Result<(), RcloneError>),
+}
+
+impl SearchDialog {
+    fn status_text(&self) -> String {
+        match (&self.status, self.hit_count) {
+            (SearchStatus::Idle, _) => {
+                String::from("Matches part of the name, or a pattern like *.pdf")
+            }
+            (SearchStatus::Running, 0) => String::from("Searching…"),
+            (SearchStatus::Running, n) => format!("Searching… {} found so far", n),
+            (SearchStatus::Finished, 0) => String::from("Nothing found"),
+            (SearchStatus::Finished, n) if n >= MAX_HITS => format!(
+                "Showing the first {} results. Narrow down the search to see the rest.",
+                MAX_HITS
+            ),
+            (SearchStatus::Finished, 1) => String::from("1 result"),
+            (SearchStatus::Finished, n) => format!("{} results", n),
+            (SearchStatus::Cancelled, n) => format!("Search cancelled after {} results", n),
+            (SearchStatus::Failed(error), _) => {
+                format!("{}. {}", error.title(), error.description())
+            }
+        }
+    }
+
+    fn append_hit(&self, sender: &ComponentSender<Self>, hit: RcloneFileListing) {
+        let row = adw::ActionRow::builder()
+            .title(&hit.name)
+            .subtitle(hit.path.resolve_to_parent().to_string())
+            .use_markup(false)
+            .activatable(true)
+            .build();
+        row.add_prefix(&gtk::Image::from_icon_name(match hit.is_dir {
+            true => icon_names::FOLDER_FILLED,
+            false => icon_names::PAPER_FILLED,
+        }));
+        let path = hit.path.clone();
+        let reveal_button = gtk::Button::builder()
+            .label("Show in folder")
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        reveal_button.connect_clicked(clone!(
+            #[strong]
+            sender,
+            #[strong]
+            path,
+            move |_| {
+                sender.input(SearchDialogInMsg::RevealRequested(path.clone()));
+            }
+        ));
+        row.add_suffix(&reveal_button);
+        row.connect_activated(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(SearchDialogInMsg::OpenRequested(hit.clone()));
+            }
+        ));
+        self.hit_list.append(&row);
+    }
+}
+
+#[relm4::component(pub)]
+impl Component for SearchDialog {
+    type Init = SearchDialogInit;
+    type Input = SearchDialogInMsg;
+    type Output = SearchDialogOutMsg;
+    type CommandOutput = SearchDialogCmdOut;
+
+    view! {
+        #[root]
+        adw::Dialog {
+            set_title: &format!("Search in {}", model.path),
+            set_content_width: 640,
+            set_content_height: 520,
+            connect_closed[sender] => move |_| {
+                sender.input(SearchDialogInMsg::CancelRequested);
+            },
+
+            #[wrap(Some)]
+            set_child = &adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {},
+
+                #[wrap(Some)]
+                set_content = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_margin_all: 10,
+                    set_spacing: 10,
+
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 10,
+
+                        append: &model.pattern_entry,
+
+                        gtk::Label {
+                            set_text: "Depth",
+                        },
+
+                        append: &model.depth_button,
+
+                        gtk::Button {
+                            set_label: "Search",
+                            add_css_class: "suggested-action",
+                            #[watch]
+                            set_visible: !matches!(model.status, SearchStatus::Running),
+                            connect_clicked => SearchDialogInMsg::SearchRequested,
+                        },
+
+                        gtk::Button {
+                            set_label: "Cancel",
+                            #[watch]
+                            set_visible: matches!(model.status, SearchStatus::Running),
+                            connect_clicked => SearchDialogInMsg::CancelRequested,
+                        },
+                    },
+
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 10,
+
+                        gtk::Spinner {
+                            #[watch]
+                            set_spinning: matches!(model.status, SearchStatus::Running),
+                            #[watch]
+                            set_visible: matches!(model.status, SearchStatus::Running),
+                        },
+
+                        gtk::Label {
+                            set_halign: gtk::Align::Start,
+                            set_wrap: true,
+                            add_css_class: "dim-label",
+                            #[watch]
+                            set_text: &model.status_text(),
+                        },
+                    },
+
+                    gtk::ScrolledWindow {
+                        set_vexpand: true,
+                        #[watch]
+                        set_visible: model.hit_count > 0,
+
+                        set_child: Some(&model.hit_list),
+                    },
+                }
+            }
+        }
+    }
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let pattern_entry = gtk::SearchEntry::builder()
+            .placeholder_text("File or folder name")
+            .hexpand(true)
+            .build();
+        pattern_entry.connect_activate(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(SearchDialogInMsg::SearchRequested);
+            }
+        ));
+        let depth_button = gtk::SpinButton::with_range(0.0, 100.0, 1.0);
+        depth_button.set_tooltip_text(Some(
+            "How many folder levels to search. 0 searches all of them, which can take long on slow remotes.",
+        ));
+        let hit_list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+
+        let model = Self {
+            client: init.client,
+            path: init.path,
+            pattern_entry,
+            depth_button,
+            hit_list,
+            hit_count: 0,
+            status: SearchStatus::Idle,
+            process: ProcessHandle::default(),
+            generation: 0,
+        };
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match message {
+            Self::Input::SearchRequested => {
+                let pattern = self.pattern_entry.text().trim().to_owned();
+                if pattern.is_empty() {
+                    return;
+                }
+                self.process.cancel();
+                self.process = ProcessHandle::default();
+                self.generation += 1;
+                self.hit_list.remove_all();
+                self.hit_count = 0;
+                self.status = SearchStatus::Running;
+
+                let query = SearchQuery {
+                    pattern,
+                    max_depth: match self.depth_button.value_as_int() {
+                        depth if depth > 0 => Some(depth as u32),
+                        _ => None,
+                    },
+                };
+                let (client, path, process, generation) = (
+                    self.client.clone(),
+                    self.path.clone(),
+                    self.process.clone(),
+                    self.generation,
+                );
+                sender.spawn_command(move |out| {
+                    let result = client.search(&path, &query, &process, &|hit| {
+                        out.emit(SearchDialogCmdOut::Hit(generation, hit))
+                    });
+                    out.emit(SearchDialogCmdOut::Finished(generation, result));
+                });
+            }
+            Self::Input::CancelRequested => {
+                if let SearchStatus::Running = self.status {
+                    self.process.cancel();
+                    self.status = SearchStatus::Cancelled;
+                }
+            }
+            Self::Input::OpenRequested(hit) => {
+                sender
+                    .output(Self::Output::OpenRequested(hit))
+                    .expect("Could not broadcast search hit");
+                root.close();
+            }
+            Self::Input::RevealRequested(path) => {
+                sender
+                    .output(Self::Output::RevealRequested(path))
+                    .expect("Could not broadcast search hit");
+                root.close();
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            SearchDialogCmdOut::Hit(generation, hit) if generation == self.generation => {
+                if self.hit_count < MAX_HITS {
+                    self.append_hit(&sender, hit);
+                    self.hit_count += 1;
+                }
+                if self.hit_count >= MAX_HITS {
+                    // Ends the search, and is reported as finished rather than cancelled
+                    self.process.cancel();
+                }
+            }
+            SearchDialogCmdOut::Finished(generation, result) if generation == self.generation => {
+                self.status = match (result, &self.status) {
+                    (_, SearchStatus::Cancelled) => SearchStatus::Cancelled,
+                    (_, _) if self.hit_count >= MAX_HITS => SearchStatus::Finished,
+                    (Ok(()), _) => SearchStatus::Finished,
+                    (Err(error), _) => SearchStatus::Failed(error),
+                };
+            }
+            _ => {}
+        }
+    }
+}
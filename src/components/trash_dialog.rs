@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use adw::glib::clone;
+use adw::prelude::{ActionRowExt, AdwDialogExt, AlertDialogExt, BoxExt, ButtonExt};
+use relm4::gtk::prelude::{OrientableExt, WidgetExt};
+use relm4::gtk::{self};
+use relm4::ComponentSender;
+use relm4::{adw, RelmWidgetExt};
+use relm4::{Component, ComponentParts};
+
+use crate::client::{RcloneError, StorageClient};
+use crate::icons::icon_names;
+use crate::path_tools::RclonePath;
+use crate::trash::{list_trash, trash_root, TrashedItem};
+
+#[derive(Debug)]
+enum TrashStatus {
+    Loading,
+    Loaded,
+    Failed(RcloneError),
+}
+
+#[derive(Debug)]
+pub struct TrashDialog {
+    remote: String,
+    folder: String,
+    item_list: gtk::ListBox,
+    item_count: usize,
+    status: TrashStatus,
+}
+
+#[derive(Debug)]
+pub struct TrashDialogInit {
+    pub client: Arc<dyn StorageClient>,
+    /// The remote whose trash is shown, like "foo:".
+    pub remote: String,
+    pub folder: String,
+}
+
+#[derive(Debug)]
+pub enum TrashDialogInMsg {
+    RestoreRequested(adw::ActionRow, TrashedItem),
+    EmptyRequested,
+    EmptyConfirmed,
+}
+
+#[derive(Debug)]
+pub enum TrashDialogOutMsg {
+    RestoreRequested(TrashedItem),
+    /// Permanently delete the trash folder at this path.
+    EmptyRequested(RclonePath),
+}
+
+#[derive(Debug)]
+pub enum TrashDialogCmdOut {
+    Listed(Result<Vec<TrashedItem>, RcloneError>),
+}
+
+impl TrashDialog {
+    fn status_text(&self) -> String {
+        match (&self.status, self.item_count) {
+            (TrashStatus::Loading, _) => String::from("Loading…"),
+            (TrashStatus::Loaded, 0) => String::from("The trash is empty"),
+            (TrashStatus::Loaded, 1) => String::from("1 item"),
+            (TrashStatus::Loaded, n) => format!("{} items", n),
+            (TrashStatus::Failed(error), _) => {
+                format!("{}. {}", error.title(), error.description())
+            }
+        }
+    }
+
+    fn append_item(&self, sender: &ComponentSender<Self>, item: TrashedItem) {
+        let subtitle = match item.listing.formatted_size() {
+            Some(size) if !item.listing.is_dir => {
+                format!("Deleted on {} · {}B", item.deleted_on, size)
+            }
+            _ => format!("Deleted on {}", item.deleted_on),
+        };
+        let row = adw::ActionRow::builder()
+            .title(item.original_path.to_string())
+            .subtitle(subtitle)
+            .use_markup(false)
+            .build();
+        row.add_prefix(&gtk::Image::from_icon_name(match item.listing.is_dir {
+            true => icon_names::FOLDER_FILLED,
+            false => icon_names::PAPER_FILLED,
+        }));
+        let restore_button = gtk::Button::builder()
+            .label("Restore")
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        restore_button.connect_clicked(clone!(
+            #[strong]
+            sender,
+            #[weak]
+            row,
+            move |_| {
+                sender.input(TrashDialogInMsg::RestoreRequested(row, item.clone()));
+            }
+        ));
+        row.add_suffix(&restore_button);
+        self.item_list.append(&row);
+    }
+}
+
+#[relm4::component(pub)]
+impl Component for TrashDialog {
+    type Init = TrashDialogInit;
+    type Input = TrashDialogInMsg;
+    type Output = TrashDialogOutMsg;
+    type CommandOutput = TrashDialogCmdOut;
+
+    view! {
+        #[root]
+        adw::Dialog {
+            set_title: &format!("Trash of {}", model.remote),
+            set_content_width: 640,
+            set_content_height: 520,
+
+            #[wrap(Some)]
+            set_child = &adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {
+                    pack_start = &gtk::Button {
+                        set_label: "Empty Trash",
+                        add_css_class: "destructive-action",
+                        #[watch]
+                        set_sensitive: model.item_count > 0,
+                        connect_clicked => TrashDialogInMsg::EmptyRequested,
+                    },
+                },
+
+                #[wrap(Some)]
+                set_content = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_margin_all: 10,
+                    set_spacing: 10,
+
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 10,
+
+                        gtk::Spinner {
+                            #[watch]
+                            set_spinning: matches!(model.status, TrashStatus::Loading),
+                            #[watch]
+                            set_visible: matches!(model.status, TrashStatus::Loading),
+                        },
+
+                        gtk::Label {
+                            set_halign: gtk::Align::Start,
+                            set_wrap: true,
+                            add_css_class: "dim-label",
+                            #[watch]
+                            set_text: &model.status_text(),
+                        },
+                    },
+
+                    gtk::ScrolledWindow {
+                        set_vexpand: true,
+                        #[watch]
+                        set_visible: model.item_count > 0,
+
+                        set_child: Some(&model.item_list),
+                    },
+                }
+            }
+        }
+    }
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let item_list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+
+        let (client, remote, folder) = (init.client, init.remote.clone(), init.folder.clone());
+        sender.spawn_oneshot_command(move || {
+            TrashDialogCmdOut::Listed(list_trash(client.as_ref(), &remote, &folder))
+        });
+
+        let model = Self {
+            remote: init.remote,
+            folder: init.folder,
+            item_list,
+            item_count: 0,
+            status: TrashStatus::Loading,
+        };
+        let widgets = view_output!();
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
+        match message {
+            Self::Input::RestoreRequested(row, item) => {
+                self.item_list.remove(&row);
+                self.item_count -= 1;
+                sender
+                    .output(Self::Output::RestoreRequested(item))
+                    .expect("Could not broadcast restore request");
+            }
+            Self::Input::EmptyRequested => {
+                let alert = adw::AlertDialog::builder()
+                    .heading("Empty the trash?")
+                    .body(format!(
+                        "The {} items in the trash of {} will be permanently deleted.",
+                        self.item_count, self.remote
+                    ))
+                    .build();
+                alert.add_response("cancel", "Cancel");
+                alert.add_response("empty", "Empty Trash");
+                alert.set_response_appearance("empty", adw::ResponseAppearance::Destructive);
+                alert.connect_response(Some("empty"), move |_, _| {
+                    sender.input(TrashDialogInMsg::EmptyConfirmed);
+                });
+                alert.present(Some(root));
+            }
+            Self::Input::EmptyConfirmed => {
+                if let Some(root_path) = trash_root(&RclonePath::from(&self.remote), &self.folder) {
+                    sender
+                        .output(Self::Output::EmptyRequested(root_path))
+                        .expect("Could not broadcast empty request");
+                }
+                root.close();
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            TrashDialogCmdOut::Listed(Ok(items)) => {
+                self.item_count = items.len();
+                for item in items {
+                    self.append_item(&sender, item);
+                }
+                self.status = TrashStatus::Loaded;
+            }
+            TrashDialogCmdOut::Listed(Err(error)) => {
+                self.status = TrashStatus::Failed(error);
+            }
+        }
+    }
+}
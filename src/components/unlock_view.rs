@@ -1,7 +1,9 @@
 use crate::icons::icon_names;
 use adw::glib::clone;
 use relm4::adw;
-use relm4::gtk::prelude::{BoxExt, ButtonExt, EditableExt, OrientableExt, WidgetExt};
+use relm4::gtk::prelude::{
+    BoxExt, ButtonExt, CheckButtonExt, EditableExt, OrientableExt, WidgetExt,
+};
 use relm4::gtk::{self};
 use relm4::ComponentParts;
 use relm4::ComponentSender;
@@ -11,6 +13,8 @@ use relm4::SimpleComponent;
 pub struct UnlockView {
     password: String,
     password_input: gtk::PasswordEntry,
+    remember_password: bool,
+    keyring_available: bool,
 }
 
 #[derive(Debug)]
@@ -18,15 +22,19 @@ pub struct UnlockView {
 #[allow(clippy::enum_variant_names)]
 pub enum UnlockViewInMsg {
     PasswordFocusRequested,
+    KeyringChecked(bool),
     #[doc(hidden)]
     PasswordEdited(String),
     #[doc(hidden)]
     PasswordSubmitRequested,
+    #[doc(hidden)]
+    RememberPasswordToggled(bool),
 }
 
 #[derive(Debug)]
 pub enum UnlockViewOutMsg {
-    PasswordSubmitted(String),
+    /// The password, and whether to store it in the keyring.
+    PasswordSubmitted(String, bool),
 }
 
 #[relm4::component(pub)]
@@ -59,6 +67,18 @@ impl SimpleComponent for UnlockView {
                     set_icon_name: icon_names::RIGHT_LARGE,
                     connect_clicked => Self::Input::PasswordSubmitRequested,
                 }
+            },
+
+            gtk::CheckButton {
+                set_label: Some("Remember password"),
+                set_tooltip_text: Some("Store the password in the system keyring"),
+                set_halign: gtk::Align::Center,
+                set_margin_top: 10,
+                #[watch]
+                set_visible: model.keyring_available,
+                connect_toggled[sender] => move |button| {
+                    sender.input(Self::Input::RememberPasswordToggled(button.is_active()));
+                },
             }
         }
     }
@@ -87,6 +107,8 @@ impl SimpleComponent for UnlockView {
         let model = Self {
             password: String::from(""),
             password_input,
+            remember_password: false,
+            keyring_available: false,
         };
         let widgets = view_output!();
         ComponentParts { model, widgets }
@@ -97,14 +119,23 @@ impl SimpleComponent for UnlockView {
             Self::Input::PasswordFocusRequested => {
                 self.password_input.grab_focus();
             }
+            Self::Input::KeyringChecked(available) => {
+                self.keyring_available = available;
+            }
             Self::Input::PasswordEdited(password) => {
                 self.password = password;
             }
             Self::Input::PasswordSubmitRequested => {
                 sender
-                    .output(Self::Output::PasswordSubmitted(self.password.clone()))
+                    .output(Self::Output::PasswordSubmitted(
+                        self.password.clone(),
+                        self.remember_password,
+                    ))
                     .unwrap();
             }
+            Self::Input::RememberPasswordToggled(remember_password) => {
+                self.remember_password = remember_password;
+            }
         }
     }
 }
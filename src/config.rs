@@ -1,19 +1,118 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
-fn default_skip_overwrite_warning() -> bool {
+use crate::model::FileSort;
+
+fn default_max_parallel_jobs() -> usize {
+    3
+}
+
+fn default_suspend_running_jobs_on_pause() -> bool {
+    false
+}
+
+fn default_use_rcd_backend() -> bool {
+    false
+}
+
+fn default_auto_retry_attempts() -> u32 {
+    3
+}
+
+fn default_auto_retry_backoff_seconds() -> u64 {
+    5
+}
+
+fn default_file_sort() -> FileSort {
+    FileSort::default()
+}
+
+fn default_compact_file_list() -> bool {
+    false
+}
+
+fn default_show_preview_pane() -> bool {
+    false
+}
+
+fn default_preview_size_limit_mb() -> u64 {
+    20
+}
+
+fn default_cache_size_limit_mb() -> u64 {
+    1000
+}
+
+fn default_use_trash() -> bool {
     false
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+fn default_trash_folder() -> String {
+    String::from(".shuttle-trash")
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AppConfig {
-    #[serde(default = "default_skip_overwrite_warning")]
-    pub skip_overwrite_disclaimer: bool,
+    /// How many jobs may run at the same time. The rest waits in the queue.
+    #[serde(default = "default_max_parallel_jobs")]
+    pub max_parallel_jobs: usize,
+    /// Whether pausing the queue also stops running jobs. They are queued again and restart
+    /// from scratch once the queue resumes.
+    #[serde(default = "default_suspend_running_jobs_on_pause")]
+    pub suspend_running_jobs_on_pause: bool,
+    /// Whether to talk to a long-running `rclone rcd` instead of starting rclone for every
+    /// operation.
+    #[serde(default = "default_use_rcd_backend")]
+    pub use_rcd_backend: bool,
+    /// How many times a job that failed with a transient error is retried automatically.
+    #[serde(default = "default_auto_retry_attempts")]
+    pub auto_retry_attempts: u32,
+    /// Delay before the first automatic retry. Doubles with every further attempt.
+    #[serde(default = "default_auto_retry_backoff_seconds")]
+    pub auto_retry_backoff_seconds: u64,
+    /// The column and direction files are sorted by, the same for every remote.
+    #[serde(default = "default_file_sort")]
+    pub file_sort: FileSort,
+    /// Whether to list only file names, without the size, modified and type columns.
+    #[serde(default = "default_compact_file_list")]
+    pub compact_file_list: bool,
+    /// Whether the side panel that previews the selected file is shown.
+    #[serde(default = "default_show_preview_pane")]
+    pub show_preview_pane: bool,
+    /// Files that have to be downloaded whole to be previewed, like images and PDFs, are only
+    /// fetched after asking when they are larger than this.
+    #[serde(default = "default_preview_size_limit_mb")]
+    pub preview_size_limit_mb: u64,
+    /// How large the cache of opened files may grow before the least recently opened ones are
+    /// removed.
+    #[serde(default = "default_cache_size_limit_mb")]
+    pub cache_size_limit_mb: u64,
+    /// Whether deleting moves files into the trash folder of their remote instead of removing
+    /// them for good.
+    #[serde(default = "default_use_trash")]
+    pub use_trash: bool,
+    /// The trash folder, relative to the root of each remote. Deleted items are kept in a
+    /// subfolder per day.
+    #[serde(default = "default_trash_folder")]
+    pub trash_folder: String,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
-            skip_overwrite_disclaimer: default_skip_overwrite_warning(),
+            max_parallel_jobs: default_max_parallel_jobs(),
+            suspend_running_jobs_on_pause: default_suspend_running_jobs_on_pause(),
+            use_rcd_backend: default_use_rcd_backend(),
+            auto_retry_attempts: default_auto_retry_attempts(),
+            auto_retry_backoff_seconds: default_auto_retry_backoff_seconds(),
+            file_sort: default_file_sort(),
+            compact_file_list: default_compact_file_list(),
+            show_preview_pane: default_show_preview_pane(),
+            preview_size_limit_mb: default_preview_size_limit_mb(),
+            cache_size_limit_mb: default_cache_size_limit_mb(),
+            use_trash: default_use_trash(),
+            trash_folder: default_trash_folder(),
         }
     }
 }
@@ -29,4 +128,53 @@ impl AppConfig {
             Err(err) => println!("Warning: failed to save config. {}", err),
         }
     }
+
+    pub fn preview_size_limit(&self) -> u64 {
+        self.preview_size_limit_mb.saturating_mul(1_000_000)
+    }
+
+    pub fn cache_size_limit(&self) -> u64 {
+        self.cache_size_limit_mb.saturating_mul(1_000_000)
+    }
+
+    /// Delay before automatically retrying a job that already failed `previous_attempts` times
+    /// before, or `None` when the job has run out of attempts.
+    pub fn auto_retry_delay(&self, previous_attempts: u32) -> Option<Duration> {
+        if previous_attempts >= self.auto_retry_attempts {
+            return None;
+        }
+        let factor = 2u64.saturating_pow(previous_attempts);
+        Some(Duration::from_secs(
+            self.auto_retry_backoff_seconds.saturating_mul(factor),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::config::AppConfig;
+
+    #[test]
+    fn auto_retry_delay_doubles() {
+        let config = AppConfig {
+            auto_retry_attempts: 3,
+            auto_retry_backoff_seconds: 5,
+            ..AppConfig::default()
+        };
+        assert_eq!(config.auto_retry_delay(0), Some(Duration::from_secs(5)));
+        assert_eq!(config.auto_retry_delay(1), Some(Duration::from_secs(10)));
+        assert_eq!(config.auto_retry_delay(2), Some(Duration::from_secs(20)));
+        assert_eq!(config.auto_retry_delay(3), None);
+    }
+
+    #[test]
+    fn auto_retry_disabled() {
+        let config = AppConfig {
+            auto_retry_attempts: 0,
+            ..AppConfig::default()
+        };
+        assert_eq!(config.auto_retry_delay(0), None);
+    }
 }
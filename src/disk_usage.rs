@@ -0,0 +1,268 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::client::{ProcessHandle, RcloneError, RcloneFileListing, SearchQuery, StorageClient};
+use crate::path_tools::RclonePath;
+
+/// How many items to list between progress reports while scanning.
+const PROGRESS_INTERVAL: usize = 500;
+
+/// A file or folder along with the space it takes up, counting everything below it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageNode {
+    pub path: RclonePath,
+    pub is_dir: bool,
+    pub bytes: u64,
+    /// The number of files below a folder, or 1 for a file.
+    pub file_count: u64,
+    children: BTreeMap<String, UsageNode>,
+}
+
+impl UsageNode {
+    fn new(path: RclonePath, is_dir: bool, bytes: u64) -> Self {
+        Self {
+            path,
+            is_dir,
+            bytes,
+            file_count: u64::from(!is_dir),
+            children: BTreeMap::new(),
+        }
+    }
+
+    /// Puts together the tree below `root` from a recursive listing of it. Sizes that the remote
+    /// doesn't know count as empty.
+    pub fn build(root: &RclonePath, listings: Vec<RcloneFileListing>) -> Self {
+        let mut tree = Self::new(root.clone(), true, 0);
+        let prefix = root.join("").to_string();
+        for listing in listings {
+            let path = listing.path.to_string();
+            let Some(relative) = path.strip_prefix(&prefix) else {
+                continue;
+            };
+            let names: Vec<&str> = relative.split('/').filter(|n| !n.is_empty()).collect();
+            let Some((last, folders)) = names.split_last() else {
+                continue;
+            };
+            let mut node = &mut tree;
+            for folder in folders {
+                let folder_path = node.path.join(folder);
+                node = node
+                    .children
+                    .entry(folder.to_string())
+                    .or_insert_with(|| Self::new(folder_path, true, 0));
+            }
+            let bytes = u64::try_from(listing.size).unwrap_or_default();
+            let leaf = Self::new(node.path.join(last), listing.is_dir, bytes);
+            node.children.entry(last.to_string()).or_insert(leaf);
+        }
+        tree.add_up();
+        tree
+    }
+
+    /// Sets the totals of folders from what is below them.
+    fn add_up(&mut self) {
+        if !self.is_dir {
+            return;
+        }
+        for child in self.children.values_mut() {
+            child.add_up();
+        }
+        self.bytes = self.children.values().map(|child| child.bytes).sum();
+        self.file_count = self.children.values().map(|child| child.file_count).sum();
+    }
+
+    pub fn name(&self) -> String {
+        self.path.filename()
+    }
+
+    /// What is directly in this folder, largest first.
+    pub fn children(&self) -> Vec<&UsageNode> {
+        let mut children: Vec<&UsageNode> = self.children.values().collect();
+        children.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+        children
+    }
+
+    /// The share of `total` bytes this item takes up, between 0 and 1.
+    pub fn fraction_of(&self, total: u64) -> f64 {
+        match total {
+            0 => 0.0,
+            total => self.bytes as f64 / total as f64,
+        }
+    }
+
+    pub fn formatted_bytes(&self) -> String {
+        format!("{}B", size_format::SizeFormatterSI::new(self.bytes))
+    }
+
+    /// The node at `path`, if it is this one or below it.
+    pub fn find(&self, path: &RclonePath) -> Option<&UsageNode> {
+        if self.path == *path {
+            return Some(self);
+        }
+        self.children
+            .values()
+            .find_map(|child| match child.is_below_or_at(path) {
+                true => child.find(path),
+                false => None,
+            })
+    }
+
+    /// Takes the node at `path` out of the tree, and the space it took up out of the folders
+    /// above it.
+    pub fn remove(&mut self, path: &RclonePath) -> Option<UsageNode> {
+        let name = self
+            .children
+            .iter()
+            .find(|(_, child)| child.is_below_or_at(path))
+            .map(|(name, _)| name.clone())?;
+        let removed = match self.children[&name].path == *path {
+            true => self.children.remove(&name)?,
+            false => self.children.get_mut(&name)?.remove(path)?,
+        };
+        self.bytes -= removed.bytes;
+        self.file_count -= removed.file_count;
+        Some(removed)
+    }
+
+    fn is_below_or_at(&self, path: &RclonePath) -> bool {
+        let (own, other) = (self.path.to_string(), path.to_string());
+        other == own || other.starts_with(&self.path.join("").to_string())
+    }
+}
+
+/// Lists everything below `root` in one go and adds up the sizes, reporting how many items have
+/// been listed so far along the way.
+pub fn scan(
+    client: &dyn StorageClient,
+    root: &RclonePath,
+    process: &ProcessHandle,
+    on_progress: &dyn Fn(usize),
+) -> Result<UsageNode, RcloneError> {
+    let everything = SearchQuery {
+        pattern: String::from("*"),
+        max_depth: None,
+    };
+    let listings = RefCell::new(vec![]);
+    client.search(root, &everything, process, &|listing| {
+        let mut listings = listings.borrow_mut();
+        listings.push(listing);
+        if listings.len() % PROGRESS_INTERVAL == 0 {
+            on_progress(listings.len());
+        }
+    })?;
+    Ok(UsageNode::build(root, listings.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+
+    use crate::client::{ProcessHandle, RcloneFileListing};
+    use crate::disk_usage::{scan, UsageNode};
+    use crate::memory_client::MemoryClient;
+    use crate::path_tools::RclonePath;
+
+    fn listing(path: &str, size: i64, is_dir: bool) -> RcloneFileListing {
+        let path = RclonePath::from(path);
+        RcloneFileListing {
+            name: path.filename(),
+            path,
+            size,
+            mime_type: String::new(),
+            mod_time: OffsetDateTime::UNIX_EPOCH,
+            is_dir,
+            is_bucket: None,
+        }
+    }
+
+    fn tree() -> UsageNode {
+        UsageNode::build(
+            &RclonePath::from("foo:"),
+            vec![
+                listing("foo:a.txt", 10, false),
+                listing("foo:docs", -1, true),
+                listing("foo:docs/b.txt", 200, false),
+                listing("foo:docs/old/c.txt", 50, false),
+                listing("foo:empty", -1, true),
+                // Bucket based remotes leave out folders that only exist through their files
+                listing("foo:photos/d.jpg", 1000, false),
+                listing("foo:unknown.bin", -1, false),
+            ],
+        )
+    }
+
+    fn summary(node: &UsageNode) -> Vec<(String, u64, u64)> {
+        node.children()
+            .iter()
+            .map(|child| (child.name(), child.bytes, child.file_count))
+            .collect()
+    }
+
+    #[test]
+    fn tree_adds_up_sizes() {
+        let tree = tree();
+        assert_eq!((tree.bytes, tree.file_count), (1260, 5));
+        assert_eq!(
+            summary(&tree),
+            vec![
+                (String::from("photos"), 1000, 1),
+                (String::from("docs"), 250, 2),
+                (String::from("a.txt"), 10, 1),
+                (String::from("empty"), 0, 0),
+                (String::from("unknown.bin"), 0, 1),
+            ]
+        );
+        let docs = tree.find(&RclonePath::from("foo:docs")).unwrap();
+        assert_eq!(docs.fraction_of(tree.bytes), 250.0 / 1260.0);
+        assert!(tree.find(&RclonePath::from("foo:doc")).is_none());
+    }
+
+    #[test]
+    fn tree_below_a_folder() {
+        let tree = UsageNode::build(
+            &RclonePath::from("foo:docs"),
+            vec![
+                listing("foo:docs/b.txt", 200, false),
+                listing("foo:docs/old/c.txt", 50, false),
+            ],
+        );
+        let old = tree.find(&RclonePath::from("foo:docs/old")).unwrap();
+        assert_eq!((old.bytes, old.is_dir), (50, true));
+    }
+
+    #[test]
+    fn removal_updates_folders_above() {
+        let mut tree = tree();
+        let removed = tree
+            .remove(&RclonePath::from("foo:docs/old/c.txt"))
+            .unwrap();
+        assert_eq!(removed.bytes, 50);
+        assert_eq!((tree.bytes, tree.file_count), (1210, 4));
+        let docs = tree.find(&RclonePath::from("foo:docs")).unwrap();
+        assert_eq!((docs.bytes, docs.file_count), (200, 1));
+
+        assert!(tree.remove(&RclonePath::from("foo:photos")).is_some());
+        assert_eq!(tree.bytes, 210);
+        assert!(tree.remove(&RclonePath::from("foo:photos")).is_none());
+    }
+
+    #[test]
+    fn scan_lists_everything_below() {
+        let client = MemoryClient::new(&["foo:"])
+            .with_file("foo:docs/a.txt", "abc")
+            .with_file("foo:docs/old/b.txt", "de")
+            .with_folder("foo:docs/empty")
+            .with_file("foo:other.txt", "fgh");
+        let root = RclonePath::from("foo:docs");
+        let tree = scan(&client, &root, &ProcessHandle::default(), &|_| {}).unwrap();
+        assert_eq!((tree.bytes, tree.file_count), (5, 2));
+        assert_eq!(
+            summary(&tree),
+            vec![
+                (String::from("a.txt"), 3, 1),
+                (String::from("old"), 2, 1),
+                (String::from("empty"), 0, 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use dirs::cache_dir;
+use serde::{Deserialize, Serialize};
+
+use crate::{client::RcloneFileListing, path_tools::RclonePath};
+
+const INDEX_FILENAME: &str = "index.json";
+
+/// A local copy of a remote file, as it was when it was downloaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub remote_path: RclonePath,
+    pub local_path: PathBuf,
+    /// Unix time in milliseconds of the last time the copy was opened.
+    pub last_used: u64,
+    /// The copy's modification time right after it was downloaded. Unset while the download
+    /// is still going, and no longer matching once the copy was edited.
+    pub local_mod_time: Option<SystemTime>,
+}
+
+impl CacheEntry {
+    /// Whether the copy was saved since it was downloaded or last uploaded. Such a copy holds
+    /// edits that may exist nowhere else, so it is never replaced or evicted.
+    fn is_edited(&self) -> bool {
+        match (self.local_mod_time, mod_time(&self.local_path)) {
+            (Some(synced), Some(current)) => synced != current,
+            _ => false,
+        }
+    }
+}
+
+/// Keeps the files opened from remotes, so an unchanged file opens again without downloading
+/// it. Copies are told apart by remote path, modification time and size, and the least
+/// recently used ones go first when the cache grows past its limit.
+#[derive(Debug)]
+pub struct FileCache {
+    root: PathBuf,
+    index: BTreeMap<String, CacheEntry>,
+}
+
+impl FileCache {
+    /// Opens the cache in `root`, which also holds temporary files of older versions.
+    pub fn new(root: PathBuf) -> Self {
+        let index = std::fs::read_to_string(root.join(INDEX_FILENAME))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { root, index }
+    }
+
+    /// The cache in the user's cache folder.
+    pub fn load() -> Self {
+        Self::new(
+            cache_dir()
+                .expect("Cannot find cache folder")
+                .join("rclone-shuttle"),
+        )
+    }
+
+    /// The local copy of `listing`, if there is one that is complete and unedited.
+    pub fn lookup(&mut self, listing: &RcloneFileListing) -> Option<PathBuf> {
+        let entry = self.index.get_mut(&cache_key(listing))?;
+        if entry.local_mod_time.is_none() || entry.local_mod_time != mod_time(&entry.local_path) {
+            return None;
+        }
+        entry.last_used = now_millis();
+        let local_path = entry.local_path.clone();
+        self.save();
+        Some(local_path)
+    }
+
+    /// A copy of `listing` with edits that weren't uploaded, if there is one.
+    pub fn edited_copy(&self, listing: &RcloneFileListing) -> Option<PathBuf> {
+        let key = cache_key(listing);
+        self.index
+            .iter()
+            .filter(|(k, entry)| k.starts_with(&key) && entry.is_edited())
+            .max_by_key(|(_, entry)| entry.last_used)
+            .map(|(_, entry)| entry.local_path.clone())
+    }
+
+    /// Sets aside a place for a new copy of `listing` and returns where to download it to. An
+    /// edited copy that is already there stays, and the new one goes next to it.
+    pub fn prepare(&mut self, listing: &RcloneFileListing) -> io::Result<PathBuf> {
+        let key = cache_key(listing);
+        let mut folder = self.root.join(&key);
+        if let Some(edited) = self.index.remove(&key).filter(CacheEntry::is_edited) {
+            // Out of the way of lookups, but still found by `edited_copy`
+            self.index
+                .insert(format!("{}-edited-{}", key, edited.last_used), edited);
+            folder = self.root.join(format!("{}-{}", key, now_millis()));
+        }
+        std::fs::create_dir_all(&folder)?;
+        let local_path = folder.join(listing.path.filename());
+        self.index.insert(
+            key,
+            CacheEntry {
+                remote_path: listing.path.clone(),
+                local_path: local_path.clone(),
+                last_used: now_millis(),
+                local_mod_time: None,
+            },
+        );
+        self.save();
+        Ok(local_path)
+    }
+
+    /// Marks the copy at `local_path` as downloaded, then evicts the least recently used copies
+    /// until the cache fits in `max_bytes` again.
+    pub fn complete(&mut self, local_path: &Path, max_bytes: u64) {
+        let Some((key, entry)) = self
+            .index
+            .iter_mut()
+            .find(|(_, entry)| entry.local_path == local_path)
+        else {
+            return;
+        };
+        entry.local_mod_time = mod_time(local_path);
+        let key = key.clone();
+        self.evict(max_bytes, &key);
+        self.save();
+    }
+
+    /// Marks the copy at `local_path` as no longer edited, once its edits were uploaded.
+    pub fn mark_uploaded(&mut self, local_path: &Path) {
+        if let Some(entry) = self
+            .index
+            .values_mut()
+            .find(|entry| entry.local_path == local_path)
+        {
+            entry.local_mod_time = mod_time(local_path);
+            self.save();
+        }
+    }
+
+    /// Whether `local_path` is a copy this cache looks after.
+    pub fn contains(&self, local_path: &Path) -> bool {
+        self.index
+            .values()
+            .any(|entry| entry.local_path == local_path)
+    }
+
+    /// How many copies hold edits that weren't uploaded.
+    pub fn edited_count(&self) -> usize {
+        self.index
+            .values()
+            .filter(|entry| entry.is_edited())
+            .count()
+    }
+
+    /// How many bytes the cache folder takes up, leftovers of older versions included.
+    pub fn usage(&self) -> u64 {
+        folder_size(&self.root)
+    }
+
+    /// Removes every cached copy.
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.index.clear();
+        match std::fs::remove_dir_all(&self.root) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn evict(&mut self, max_bytes: u64, keep_key: &str) {
+        let mut by_age = self
+            .index
+            .iter()
+            .filter(|(key, entry)| *key != keep_key && !entry.is_edited())
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect::<Vec<(u64, String)>>();
+        by_age.sort();
+
+        let mut usage = self
+            .index
+            .values()
+            .map(|entry| file_size(&entry.local_path))
+            .sum::<u64>();
+        for (_, key) in by_age {
+            if usage <= max_bytes {
+                break;
+            }
+            if let Some(entry) = self.index.remove(&key) {
+                usage = usage.saturating_sub(file_size(&entry.local_path));
+                if let Some(folder) = entry.local_path.parent() {
+                    let _ = std::fs::remove_dir_all(folder);
+                }
+            }
+        }
+    }
+
+    fn save(&self) {
+        let result = std::fs::create_dir_all(&self.root).and_then(|_| {
+            std::fs::write(
+                self.root.join(INDEX_FILENAME),
+                serde_json::to_string(&self.index).expect("Could not serialize cache index"),
+            )
+        });
+        if let Err(err) = result {
+            println!("Warning: failed to save cache index. {}", err);
+        }
+    }
+}
+
+/// Names the folder a copy is kept in. FNV-1a keeps it the same across builds, unlike the
+/// standard library's hasher.
+fn cache_key(listing: &RcloneFileListing) -> String {
+    let raw = format!(
+        "{}\n{}\n{}",
+        listing.path,
+        listing.mod_time.unix_timestamp_nanos(),
+        listing.size
+    );
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in raw.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn mod_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or_default()
+}
+
+fn folder_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .map_while(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => folder_size(&entry.path()),
+            _ => file_size(&entry.path()),
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use time::OffsetDateTime;
+
+    use crate::client::RcloneFileListing;
+    use crate::file_cache::{cache_key, FileCache};
+    use crate::path_tools::RclonePath;
+
+    fn listing(path: &str, size: i64) -> RcloneFileListing {
+        let path = RclonePath::from(path);
+        RcloneFileListing {
+            name: path.filename(),
+            path,
+            size,
+            mime_type: String::from("text/plain"),
+            mod_time: OffsetDateTime::UNIX_EPOCH,
+            is_dir: false,
+            is_bucket: None,
+        }
+    }
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "rclone-shuttle-cache-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        root
+    }
+
+    /// Downloads a fake copy the way an Open job would.
+    fn download(cache: &mut FileCache, listing: &RcloneFileListing, max_bytes: u64) -> PathBuf {
+        let local_path = cache.prepare(listing).unwrap();
+        std::fs::write(&local_path, vec![b'a'; listing.size as usize]).unwrap();
+        cache.complete(&local_path, max_bytes);
+        local_path
+    }
+
+    /// Saves a copy the way an editor would, a while after it was downloaded.
+    fn edit(local_path: &Path, content: &str) {
+        let edited_at = std::fs::metadata(local_path).unwrap().modified().unwrap()
+            + std::time::Duration::from_secs(5);
+        std::fs::write(local_path, content).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(local_path)
+            .unwrap()
+            .set_modified(edited_at)
+            .unwrap();
+    }
+
+    #[test]
+    fn key_changes_with_the_remote_file() {
+        let original = listing("foo:a.txt", 3);
+        assert_eq!(cache_key(&original), cache_key(&listing("foo:a.txt", 3)));
+        assert_ne!(cache_key(&original), cache_key(&listing("foo:a.txt", 4)));
+        assert_ne!(cache_key(&original), cache_key(&listing("foo:b.txt", 3)));
+    }
+
+    #[test]
+    fn unchanged_file_is_reused() {
+        let root = temp_root("reuse");
+        let mut cache = FileCache::new(root.clone());
+        let file = listing("foo:docs/a.txt", 3);
+        assert_eq!(cache.lookup(&file), None);
+
+        let local_path = cache.prepare(&file).unwrap();
+        // Not downloaded yet
+        assert_eq!(cache.lookup(&file), None);
+        std::fs::write(&local_path, "abc").unwrap();
+        cache.complete(&local_path, u64::MAX);
+        assert_eq!(cache.lookup(&file), Some(local_path.clone()));
+        assert!(local_path.ends_with("a.txt"));
+
+        // The index survives a restart
+        let mut reopened = FileCache::new(root.clone());
+        assert_eq!(reopened.lookup(&file), Some(local_path));
+        assert_eq!(reopened.lookup(&listing("foo:docs/a.txt", 5)), None);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn edited_copy_is_not_reused() {
+        let root = temp_root("edited");
+        let mut cache = FileCache::new(root.clone());
+        let file = listing("foo:a.txt", 3);
+        let local_path = download(&mut cache, &file, u64::MAX);
+        edit(&local_path, "abc");
+        assert_eq!(cache.lookup(&file), None);
+        assert_eq!(cache.edited_count(), 1);
+
+        // Opening it again downloads next to the edited copy
+        assert_eq!(cache.edited_copy(&file), Some(local_path.clone()));
+        let new_path = download(&mut cache, &file, u64::MAX);
+        assert_ne!(new_path, local_path);
+        assert_eq!(std::fs::read_to_string(&local_path).unwrap(), "abc");
+        assert_eq!(cache.lookup(&file), Some(new_path));
+        assert_eq!(cache.edited_copy(&file), Some(local_path.clone()));
+
+        cache.mark_uploaded(&local_path);
+        assert_eq!(cache.edited_copy(&file), None);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn edited_copy_is_not_evicted() {
+        let root = temp_root("evict-edited");
+        let mut cache = FileCache::new(root.clone());
+        let a_path = download(&mut cache, &listing("foo:a.txt", 10), u64::MAX);
+        edit(&a_path, "edited");
+        download(&mut cache, &listing("foo:b.txt", 10), 0);
+        assert_eq!(std::fs::read_to_string(&a_path).unwrap(), "edited");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_copies_are_evicted() {
+        let root = temp_root("evict");
+        let mut cache = FileCache::new(root.clone());
+        let (a, b, c) = (
+            listing("foo:a.txt", 10),
+            listing("foo:b.txt", 10),
+            listing("foo:c.txt", 10),
+        );
+        let a_path = download(&mut cache, &a, 25);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let b_path = download(&mut cache, &b, 25);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        cache.lookup(&a).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        download(&mut cache, &c, 25);
+
+        assert!(cache.lookup(&a).is_some());
+        assert!(cache.lookup(&c).is_some());
+        assert_eq!(cache.lookup(&b), None);
+        assert!(!b_path.exists());
+        assert!(cache.contains(&a_path));
+        assert_eq!(
+            cache.usage() - std::fs::metadata(root.join("index.json")).unwrap().len(),
+            20
+        );
+
+        cache.clear().unwrap();
+        assert_eq!(cache.usage(), 0);
+        assert_eq!(cache.lookup(&a), None);
+    }
+}
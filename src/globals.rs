@@ -3,8 +3,16 @@ use std::collections::BTreeMap;
 use relm4::SharedState;
 use uuid::Uuid;
 
+use crate::client::TransferStats;
 use crate::model::{FilePickerMode, RcloneJob};
 
 pub(crate) static JOBS: SharedState<BTreeMap<Uuid, RcloneJob>> = SharedState::new();
 
+/// The latest transfer stats of running jobs, kept apart from the jobs because those are saved
+/// whenever they change.
+pub(crate) static JOB_PROGRESS: SharedState<BTreeMap<Uuid, TransferStats>> = SharedState::new();
+
+/// While set, queued jobs wait instead of being started.
+pub(crate) static QUEUE_PAUSED: SharedState<bool> = SharedState::new();
+
 pub(crate) static FILE_PICKER_MODE: SharedState<FilePickerMode> = SharedState::new();
@@ -0,0 +1,131 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+// The config password is kept in the desktop keyring through the Secret Service API, using
+// libsecret's `secret-tool`. The password itself only ever travels over stdin and stdout.
+
+const APPLICATION: &str = "rclone-shuttle";
+/// How long a keyring may take to answer before it counts as unavailable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+static AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+fn attributes(custom_config_path: &Option<String>) -> [&str; 4] {
+    [
+        "application",
+        APPLICATION,
+        "config",
+        custom_config_path.as_deref().unwrap_or("default"),
+    ]
+}
+
+/// Whether a keyring answers lookups. The first call asks it, which can take a few seconds, so
+/// it is best made off the UI thread. Later calls reuse the answer.
+pub fn is_available() -> bool {
+    *AVAILABLE.get_or_init(probe)
+}
+
+/// Looks up an item that doesn't exist. A keyring that answers makes secret-tool fail quietly,
+/// while a missing Secret Service or D-Bus session makes it complain on stderr.
+fn probe() -> bool {
+    let Ok(mut child) = Command::new("secret-tool")
+        .args(["lookup", "application", APPLICATION, "probe", "probe"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+    else {
+        return false;
+    };
+    let started_at = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started_at.elapsed() < PROBE_TIMEOUT => {
+                thread::sleep(Duration::from_millis(20));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+        }
+    }
+    match child.wait_with_output() {
+        Ok(output) => output.status.success() || output.stderr.trim_ascii().is_empty(),
+        Err(_) => false,
+    }
+}
+
+pub fn lookup_password(custom_config_path: &Option<String>) -> Option<String> {
+    let output = Command::new("secret-tool")
+        .arg("lookup")
+        .args(attributes(custom_config_path))
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout)
+        .ok()
+        .filter(|password| !password.is_empty())
+}
+
+pub fn store_password(custom_config_path: &Option<String>, password: &str) -> Result<(), String> {
+    let mut child = Command::new("secret-tool")
+        .args(["store", "--label=Rclone Shuttle config password"])
+        .args(attributes(custom_config_path))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|_| "secret-tool did not start")?;
+    child
+        .stdin
+        .take()
+        .expect("secret-tool stdin went missing")
+        .write_all(password.as_bytes())
+        .map_err(|err| err.to_string())?;
+    let output = child.wait_with_output().map_err(|err| err.to_string())?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_owned()),
+    }
+}
+
+pub fn forget_password(custom_config_path: &Option<String>) {
+    let _ = Command::new("secret-tool")
+        .arg("clear")
+        .args(attributes(custom_config_path))
+        .stdin(Stdio::null())
+        .output();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keyring::{forget_password, is_available, lookup_password, store_password};
+
+    /// Runs against the session's keyring daemon, e.g. gnome-keyring.
+    #[test]
+    #[ignore = "needs a keyring daemon"]
+    fn password_round_trip() {
+        let config = Some(format!(
+            "/tmp/rclone-shuttle-test-{}.conf",
+            std::process::id()
+        ));
+        assert!(is_available());
+        store_password(&config, "sécret \"quoted\" $HOME").unwrap();
+        assert_eq!(
+            lookup_password(&config),
+            Some(String::from("sécret \"quoted\" $HOME"))
+        );
+        forget_password(&config);
+        assert_eq!(lookup_password(&config), None);
+    }
+}
@@ -3,26 +3,47 @@
     windows_subsystem = "windows"
 )]
 use crate::client::MkdirError;
-use crate::components::file_listing_view::FileListingView;
-use crate::components::remote_view::RemoteView;
+use crate::components::file_listing_view::{
+    FileListingView, MODIFIED_COLUMN_WIDTH, SIZE_COLUMN_WIDTH, TYPE_COLUMN_WIDTH,
+};
+use crate::components::remote_view::{RemoteView, RemoteViewInMsg, RemoteViewInit};
 use crate::components::unlock_view::{UnlockView, UnlockViewInMsg, UnlockViewOutMsg};
-use crate::globals::FILE_PICKER_MODE;
+use crate::globals::{FILE_PICKER_MODE, QUEUE_PAUSED};
 use crate::gtk::DropTarget;
 use crate::icons::icon_names;
 use crate::model::{FilePickerMode, RcloneJobStatus};
 use adw::gio::Cancellable;
 use adw::glib::clone;
-use adw::gtk::ffi::GTK_INVALID_LIST_POSITION;
 use adw::prelude::{AdwDialogExt, ButtonExt, EditableExt};
-use client::{RcloneClient, RcloneFileListing};
-use components::queue_button::QueueButton;
+use client::{
+    ProcessHandle, RcloneClient, RcloneError, RcloneFileListing, RemoteQuota, StorageClient,
+    TransferStats,
+};
+use components::crypt_wizard_dialog::{
+    CryptSetup, CryptWizardDialog, CryptWizardDialogInit, CryptWizardDialogOutMsg,
+};
+use components::disk_usage_dialog::{DiskUsageDialog, DiskUsageDialogInit, DiskUsageDialogOutMsg};
+use components::filter_bar::{FilterBar, FilterBarInMsg, FilterBarOutMsg};
+use components::preview_pane::{PreviewPane, PreviewPaneInMsg, PreviewRequest};
+use components::properties_dialog::{PropertiesDialog, PropertiesDialogInit};
+use components::queue_button::{QueueButton, QueueViewOutMsg};
+use components::remote_editor_dialog::{
+    RemoteEdit, RemoteEditorDialog, RemoteEditorDialogInit, RemoteEditorDialogOutMsg,
+};
+use components::search_dialog::{SearchDialog, SearchDialogInit, SearchDialogOutMsg};
 use components::string_prompt_dialog::{
     StringPromptDialog, StringPromptDialogInit, StringPromptDialogOutMsg,
 };
+use components::trash_dialog::{TrashDialog, TrashDialogInit, TrashDialogOutMsg};
 use config::AppConfig;
-use dirs::cache_dir;
-use globals::JOBS;
-use model::{RcloneJob, RcloneJobType};
+use file_cache::FileCache;
+use globals::{JOBS, JOB_PROGRESS};
+use model::{
+    file_mime_types, get_interrupted_jobs, get_ongoing_jobs, is_queue_paused, jobs_to_dispatch,
+    load_jobs, remote_mod_time, save_jobs, serialize_jobs, sort_listings, upload_sizes,
+    ConflictCheck, ConflictResolution, FileSort, ListingFilter, OpenedFile, PendingTransfers,
+    RcloneJob, RcloneJobType, SortKey, UndoEntry,
+};
 use path_tools::RclonePath;
 use relm4::actions::{AccelsPlus, RelmAction, RelmActionGroup};
 use relm4::adw::prelude::{AdwApplicationWindowExt, AlertDialogExt, NavigationPageExt};
@@ -30,7 +51,7 @@ use relm4::adw::ToolbarStyle;
 use relm4::factory::FactoryVecDeque;
 use relm4::gtk::gdk::{DragAction, FileList};
 use relm4::gtk::prelude::{
-    BoxExt, EntryExt, FileExt, GtkWindowExt, OrientableExt, StaticType, WidgetExt,
+    BoxExt, CheckButtonExt, EntryExt, FileExt, GtkWindowExt, OrientableExt, StaticType, WidgetExt,
 };
 use relm4::gtk::{self, glib};
 use relm4::typed_view::list::TypedListView;
@@ -41,24 +62,44 @@ use relm4::RelmApp;
 use relm4::RelmListBoxExt;
 use relm4::{adw, ComponentController};
 use relm4::{Component, RelmWidgetExt};
+use relm4_components::open_dialog::{
+    OpenDialog, OpenDialogMsg, OpenDialogResponse, OpenDialogSettings,
+};
 use relm4_components::save_dialog::{
     SaveDialog, SaveDialogMsg, SaveDialogResponse, SaveDialogSettings,
 };
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::ffi::OsString;
 use std::ops::Deref;
-use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use trash::{is_in_trash, trash_path};
 use uuid::Uuid;
 
 mod client;
 mod components;
 mod config;
+mod disk_usage;
+mod file_cache;
 mod globals;
 mod icons;
+mod keyring;
+#[cfg(test)]
+mod memory_client;
 mod model;
+mod password_tools;
 mod path_tools;
+mod preview;
+mod rcd;
+mod trash;
 
 const APP_ID: &str = "io.github.pieterdd.RcloneShuttle";
+/// How often opened files are checked for edits that could be uploaded back.
+const OPENED_FILES_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How many finished operations Ctrl+Z can go back through.
+const UNDO_STACK_SIZE: usize = 50;
 
 relm4::new_action_group!(FileListingsViewGroup, "file_listings_view");
 relm4::new_stateless_action!(PathRefreshAction, FileListingsViewGroup, "path_refresh");
@@ -67,6 +108,35 @@ relm4::new_stateless_action!(CopyAction, FileListingsViewGroup, "copy");
 relm4::new_stateless_action!(RenameAction, FileListingsViewGroup, "rename");
 relm4::new_stateless_action!(DownloadAction, FileListingsViewGroup, "download");
 relm4::new_stateless_action!(DeleteAction, FileListingsViewGroup, "delete");
+relm4::new_stateless_action!(SelectAllAction, FileListingsViewGroup, "select_all");
+relm4::new_stateless_action!(SearchAction, FileListingsViewGroup, "search");
+relm4::new_stateless_action!(DiskUsageAction, FileListingsViewGroup, "disk_usage");
+relm4::new_stateless_action!(FilterAction, FileListingsViewGroup, "filter");
+relm4::new_stateless_action!(TrashAction, FileListingsViewGroup, "trash");
+relm4::new_stateless_action!(UndoAction, FileListingsViewGroup, "undo");
+relm4::new_stateless_action!(PropertiesAction, FileListingsViewGroup, "properties");
+relm4::new_stateful_action!(
+    CompactListAction,
+    FileListingsViewGroup,
+    "compact_list",
+    (),
+    bool
+);
+relm4::new_stateful_action!(
+    PreviewPaneAction,
+    FileListingsViewGroup,
+    "preview_pane",
+    (),
+    bool
+);
+relm4::new_stateless_action!(SortByNameAction, FileListingsViewGroup, "sort_by_name");
+relm4::new_stateless_action!(SortBySizeAction, FileListingsViewGroup, "sort_by_size");
+relm4::new_stateless_action!(
+    SortByModifiedAction,
+    FileListingsViewGroup,
+    "sort_by_modified"
+);
+relm4::new_stateless_action!(SortByTypeAction, FileListingsViewGroup, "sort_by_type");
 relm4::new_stateless_action!(PathParentAction, FileListingsViewGroup, "path_parent");
 relm4::new_stateless_action!(PathUndoAction, FileListingsViewGroup, "path_undo");
 relm4::new_stateless_action!(PathRedoAction, FileListingsViewGroup, "path_redo");
@@ -77,20 +147,36 @@ relm4::new_stateless_action!(
     MainWindowMenuViewGroup,
     "remotes_refresh"
 );
+relm4::new_stateless_action!(RemoteAddAction, MainWindowMenuViewGroup, "remote_add");
 relm4::new_stateless_action!(
     RemotesConfigureAction,
     MainWindowMenuViewGroup,
     "remotes_configure"
 );
+relm4::new_stateless_action!(CryptWizardAction, MainWindowMenuViewGroup, "crypt_wizard");
+relm4::new_stateless_action!(ClearCacheAction, MainWindowMenuViewGroup, "clear_cache");
+relm4::new_stateful_action!(
+    UseTrashAction,
+    MainWindowMenuViewGroup,
+    "use_trash",
+    (),
+    bool
+);
 relm4::new_stateless_action!(AboutAction, MainWindowMenuViewGroup, "about");
 
 #[derive(Debug)]
 pub enum AppInMsg {
-    PasswordReceived(String),
+    PasswordReceived(String, bool),
     ClientConnectionFailed,
-    ClientConnected(RcloneClient),
+    ClientConnected(Arc<dyn StorageClient>),
     RemotesRefreshRequested,
+    RemoteCreationRequested,
     RemotesConfigurationRequested,
+    RemoteEditSubmitted(RemoteEdit),
+    RemoteDeletionRequested(String),
+    RemoteDeletionConfirmed(String),
+    CryptWizardRequested,
+    CryptSetupSubmitted(CryptSetup),
     RemoteSelectionChanged(usize),
     PathRefreshRequested,
     PathParentRequested,
@@ -98,8 +184,16 @@ pub enum AppInMsg {
     PathRedoRequested,
     PathEntered(RclonePath),
     PathChanged(RclonePath),
-    OpenRequested(RclonePath),
+    OpenRequested(RcloneFileListing),
+    /// Opens a copy in the cache that holds edits, with the remote path it belongs to.
+    EditedCopyOpenRequested(RclonePath, RclonePath),
     UploadRequested(RclonePath, RclonePath),
+    /// Uploads, moves or copies, started once their clashes with existing files are settled.
+    TransfersRequested(Vec<RcloneJobType>),
+    /// Transfers that were checked for conflicts and may go ahead.
+    TransfersConfirmed(Vec<RcloneJobType>, ConflictCheck),
+    TransferConflictAnswered(ConflictResolution, bool),
+    TransfersCancelled,
     FileListingSelectionChanged,
     FileListingPositionActivated(u32),
     FileListingErrorDetailRequested,
@@ -117,11 +211,34 @@ pub enum AppInMsg {
     RenameSelectionRequested,
     RenameConfirmed(RclonePath, String),
     DeleteSelectionRequested,
-    DeleteConfirmed(RclonePath, bool),
+    DeleteConfirmed(Vec<(RclonePath, bool)>),
+    TrashRequested,
+    TrashEmptyConfirmed(RclonePath),
+    PropertiesRequested,
+    UndoRequested,
+    UseTrashToggled(bool),
     TriggerGenericError(String, String, bool),
     FilePickerModeChange(FilePickerMode),
     DownloadRequested,
     DownloadPathConfirmed(RclonePath),
+    DownloadFolderConfirmed(RclonePath),
+    SelectAllRequested,
+    SearchRequested,
+    DiskUsageRequested,
+    FilterToggled,
+    FilterChanged(ListingFilter),
+    SearchHitRevealRequested(RclonePath),
+    SortRequested(SortKey),
+    ClearCacheRequested,
+    ClearCacheConfirmed,
+    OpenedFileUploadRequested(RclonePath),
+    OpenedFileUploadConfirmed(RclonePath),
+    CompactListToggled(bool),
+    PreviewPaneToggled(bool),
+    JobRetryRequested(Uuid),
+    JobsChanged,
+    InterruptedJobsResumeRequested,
+    QueuePausedChanged(bool),
     RevealSidebar,
     SplitCollapsed(bool),
     NoOperation,
@@ -130,44 +247,632 @@ pub enum AppInMsg {
 #[derive(Debug)]
 enum AppOutCmd {
     FileListingAvailable(Vec<RcloneFileListing>),
-    CommandFailed(String),
+    CommandFailed(RcloneError),
+    /// Whether there is a keyring, and the client unlocked with the password kept there if that
+    /// worked.
+    RememberedPasswordTried(bool, Option<RcloneClient>),
+    BackendConnected(Arc<dyn StorageClient>),
+    /// The folder that was asked for, and whether it is new if creating it worked.
+    FolderCreated(RclonePath, Result<bool, MkdirError>),
     JobUpdated(Uuid, RcloneJobStatus),
+    JobFailed(Uuid, RcloneError),
+    JobProgress(Uuid, TransferStats),
+    JobItemFinished(Uuid, usize),
+    JobRetryDue(Uuid),
+    OpenedFileWatched(OpenedFile),
+    OpenedFilesPollDue,
+    /// The remote's current modification time, checked before uploading a local edit.
+    OpenedFileRemoteChecked(RclonePath, Option<String>),
+    /// The remote's modification time after a local edit was uploaded.
+    OpenedFileSynced(RclonePath, Option<String>),
+    /// Also holds how many bytes the uploads among the transfers send to each remote.
+    TransfersChecked(
+        Vec<RcloneJobType>,
+        Result<ConflictCheck, RcloneError>,
+        BTreeMap<String, u64>,
+    ),
+    /// Items on their way to the trash, with the names they would clash with there.
+    TrashChecked(Vec<RcloneJobType>, Result<ConflictCheck, RcloneError>),
+    UndoRecorded(UndoEntry),
+    RemoteEditorLoaded(Result<RemoteEditorDialogInit, RcloneError>),
+    /// Also holds the remote to open once the change went through.
+    RemoteConfigChanged(Result<(), RcloneError>, Option<String>),
 }
 
 #[derive(Debug, Clone)]
 enum FileListingViewState {
     Loading,
     Loaded,
-    Error(String),
+    Error(RcloneError),
 }
 
 struct App {
     unlock_widget: Controller<UnlockView>,
     remotes_view_wrapper: FactoryVecDeque<RemoteView>,
-    file_listing_view_wrapper: TypedListView<FileListingView, gtk::SingleSelection>,
+    file_listing_view_wrapper: TypedListView<FileListingView, gtk::MultiSelection>,
     file_listing_view_state: FileListingViewState,
+    file_listings: Vec<RcloneFileListing>,
     queue_button: Controller<QueueButton>,
+    filter_bar: Controller<FilterBar>,
+    filter: ListingFilter,
+    preview_pane: Controller<PreviewPane>,
     path: RclonePath,
     undoable_paths: Vec<RclonePath>,
     redoable_paths: Vec<RclonePath>,
-    client: Option<RcloneClient>,
+    client: Option<Arc<dyn StorageClient>>,
     requires_password: bool,
-    selected_file_listing_copy: Option<RcloneFileListing>,
+    selected_file_listings: Vec<RcloneFileListing>,
     active_string_prompt: Option<Controller<StringPromptDialog>>,
+    active_remote_editor: Option<Controller<RemoteEditorDialog>>,
+    active_crypt_wizard: Option<Controller<CryptWizardDialog>>,
+    active_search: Option<Controller<SearchDialog>>,
+    active_disk_usage: Option<Controller<DiskUsageDialog>>,
+    active_trash: Option<Controller<TrashDialog>>,
+    active_properties: Option<Controller<PropertiesDialog>>,
+    /// A search hit to select once the folder it is in has loaded.
+    revealed_path: Option<RclonePath>,
     save_copy_dialog: Option<Controller<SaveDialog>>,
+    download_folder_dialog: Option<Controller<OpenDialog>>,
     split_collapsed: bool,
     reveal_files_on_small_screens: bool,
+    config: AppConfig,
+    persisted_jobs: String,
+    /// Files opened in another app, by the path of their local copy.
+    opened_files: Vec<OpenedFile>,
+    file_cache: FileCache,
+    /// Transfer requests with clashes, asked about one conflict at a time.
+    pending_transfers: VecDeque<PendingTransfers>,
+    toast_overlay: adw::ToastOverlay,
+    /// Finished operations, most recent last, including those that can't be undone.
+    undo_stack: Vec<UndoEntry>,
 }
 
 impl App {
+    /// Tries the password from the keyring, and drops it from there if it no longer works.
+    fn unlock_with_remembered_password(
+        rclone_config_file: &Option<String>,
+    ) -> Option<RcloneClient> {
+        let password = keyring::lookup_password(rclone_config_file)?;
+        match RcloneClient::new(Some(password), rclone_config_file.clone()) {
+            Ok(client) => Some(client),
+            Err(RcloneError::ConfigLocked(_)) => {
+                keyring::forget_password(rclone_config_file);
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Puts an unlocked rclone client behind the backend chosen in the config. Starting rcd can
+    /// take several seconds, so it happens in the background.
+    fn connect_backend(sender: &ComponentSender<App>, client: RcloneClient, config: &AppConfig) {
+        let use_rcd_backend = config.use_rcd_backend;
+        sender.spawn_oneshot_command(move || {
+            if use_rcd_backend {
+                match client.start_rcd() {
+                    Ok(rcd_client) => return AppOutCmd::BackendConnected(Arc::new(rcd_client)),
+                    Err(err) => {
+                        println!("Warning: could not start rclone rcd. {}", err);
+                    }
+                }
+            }
+            AppOutCmd::BackendConnected(Arc::new(client))
+        });
+    }
+
+    /// Runs a job's rclone operation in the background and reports back on its progress.
+    fn spawn_job<F>(sender: &ComponentSender<App>, job: &RcloneJob, run: F)
+    where
+        F: FnOnce(
+                &ProcessHandle,
+                &dyn Fn(TransferStats),
+                &dyn Fn(usize),
+            ) -> Result<(), RcloneError>
+            + Send
+            + 'static,
+    {
+        let uuid = job.uuid;
+        let process = job.process.clone();
+        sender.spawn_command(move |out| {
+            let result = run(
+                &process,
+                &|stats| out.emit(AppOutCmd::JobProgress(uuid, stats)),
+                &|index| out.emit(AppOutCmd::JobItemFinished(uuid, index)),
+            );
+            out.emit(match result {
+                Ok(()) => AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Finished),
+                Err(_) if process.is_cancelled() => {
+                    AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Cancelled)
+                }
+                Err(error) => AppOutCmd::JobFailed(uuid, error),
+            });
+        });
+    }
+
+    /// Starts a queued job with the rclone operations that match its type.
+    fn start_job(&self, sender: &ComponentSender<App>, uuid: Uuid) {
+        let Some(client) = self.client.clone() else {
+            return;
+        };
+        let Some(job) = JOBS.write().get_mut(&uuid).map(|job| {
+            job.set_status(RcloneJobStatus::Ongoing);
+            job.clone()
+        }) else {
+            return;
+        };
+        let finished_items = job.finished_items.clone();
+        let job_type = job.r#type.clone();
+        Self::spawn_job(
+            sender,
+            &job,
+            move |process, on_progress, on_item_finished| {
+                job_type.run(
+                    client.as_ref(),
+                    &finished_items,
+                    process,
+                    on_progress,
+                    on_item_finished,
+                )
+            },
+        );
+    }
+
+    /// Starts as many queued jobs as the parallel job limit allows.
+    fn schedule_jobs(&self, sender: &ComponentSender<App>) {
+        if self.client.is_none() || is_queue_paused() {
+            return;
+        }
+        let uuids = jobs_to_dispatch(&JOBS.read(), self.config.max_parallel_jobs.max(1));
+        for uuid in uuids {
+            self.start_job(sender, uuid);
+        }
+    }
+
+    fn enqueue_job(&self, sender: &ComponentSender<App>, job: RcloneJob) {
+        JOBS.write().insert(job.uuid, job);
+        self.schedule_jobs(sender);
+    }
+
+    /// Starts watching a freshly opened file for edits, remembering the remote's modification
+    /// time to check for conflicts later.
+    fn watch_opened_file(
+        &self,
+        sender: &ComponentSender<App>,
+        remote_path: &RclonePath,
+        local_path: &RclonePath,
+    ) {
+        let Some(client) = self.client.clone() else {
+            return;
+        };
+        let mut opened_file = OpenedFile {
+            remote_path: remote_path.clone(),
+            local_path: local_path.clone(),
+            remote_mod_time: None,
+            local_mod_time: None,
+        };
+        opened_file.local_mod_time = opened_file.current_local_mod_time();
+        sender.spawn_oneshot_command(move || {
+            opened_file.remote_mod_time =
+                remote_mod_time(client.as_ref(), &opened_file.remote_path);
+            AppOutCmd::OpenedFileWatched(opened_file)
+        });
+    }
+
+    /// Takes note of the remote's new modification time once an edit was uploaded back.
+    fn sync_opened_file(
+        &self,
+        sender: &ComponentSender<App>,
+        local_path: &RclonePath,
+        remote_path: &RclonePath,
+    ) {
+        let Some(client) = self.client.clone() else {
+            return;
+        };
+        if !self
+            .opened_files
+            .iter()
+            .any(|opened| opened.local_path == *local_path && opened.remote_path == *remote_path)
+        {
+            return;
+        }
+        let (local_path, remote_path) = (local_path.clone(), remote_path.clone());
+        sender.spawn_oneshot_command(move || {
+            AppOutCmd::OpenedFileSynced(local_path, remote_mod_time(client.as_ref(), &remote_path))
+        });
+    }
+
+    /// Asks whether to upload the edits saved to an opened file.
+    fn offer_upload(
+        sender: &ComponentSender<App>,
+        root: &adw::ApplicationWindow,
+        opened: &OpenedFile,
+    ) {
+        let alert = adw::AlertDialog::builder()
+            .heading(format!(
+                "Upload changes to \"{}\"?",
+                opened.remote_path.filename()
+            ))
+            .body(format!(
+                "The copy you opened was saved. Upload it to {} to keep the changes.",
+                opened.remote_path
+            ))
+            .build();
+        alert.add_response("later", "Not Now");
+        alert.add_response("upload", "Upload");
+        alert.set_response_appearance("upload", adw::ResponseAppearance::Suggested);
+        alert.set_default_response(Some("upload"));
+        let (sender, local_path) = (sender.clone(), opened.local_path.clone());
+        alert.connect_response(Some("upload"), move |_, _| {
+            sender.input(AppInMsg::OpenedFileUploadRequested(local_path.clone()));
+        });
+        alert.present(Some(root));
+    }
+
+    /// Tells that a copy of `listing` in the cache has edits that weren't uploaded, with a
+    /// button to open it.
+    fn offer_edited_copy(
+        &self,
+        sender: &ComponentSender<App>,
+        listing: &RcloneFileListing,
+        edited_path: &Path,
+    ) {
+        let toast = adw::Toast::builder()
+            .title(format!(
+                "An earlier copy of \"{}\" has edits that weren't uploaded",
+                listing.name
+            ))
+            .button_label("Open Edited Copy")
+            .timeout(10)
+            .build();
+        let (sender, remote_path, local_path) = (
+            sender.clone(),
+            listing.path.clone(),
+            RclonePath::from(&edited_path.to_string_lossy().into_owned()),
+        );
+        toast.connect_button_clicked(move |_| {
+            sender.input(AppInMsg::EditedCopyOpenRequested(
+                remote_path.clone(),
+                local_path.clone(),
+            ));
+        });
+        self.toast_overlay.add_toast(toast);
+    }
+
+    /// Tells that a job moved items to the trash, with a button to put them back.
+    fn offer_trash_undo(&self, sender: &ComponentSender<App>, job_type: &RcloneJobType) {
+        let restorations = job_type.restorations();
+        let title = match restorations.as_slice() {
+            [] => return,
+            [RcloneJobType::Move { target_path, .. }] => {
+                format!("\"{}\" moved to the trash", target_path.filename())
+            }
+            items => format!("{} items moved to the trash", items.len()),
+        };
+        let toast = adw::Toast::builder()
+            .title(title)
+            .button_label("Undo")
+            .timeout(10)
+            .build();
+        let sender = sender.clone();
+        toast.connect_button_clicked(move |_| {
+            sender.input(AppInMsg::TransfersRequested(restorations.clone()));
+        });
+        self.toast_overlay.add_toast(toast);
+    }
+
+    fn show_toast(&self, title: String) {
+        self.toast_overlay.add_toast(adw::Toast::new(&title));
+    }
+
+    fn remember_for_undo(&mut self, entry: UndoEntry) {
+        self.undo_stack.push(entry);
+        if self.undo_stack.len() > UNDO_STACK_SIZE {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    /// Asks what to do about the next clash among the pending transfers.
+    fn ask_about_conflict(&self, sender: &ComponentSender<App>, root: &adw::ApplicationWindow) {
+        let Some(pending) = self.pending_transfers.front() else {
+            return;
+        };
+        let Some(conflict) = pending.next_conflict() else {
+            return;
+        };
+        let existing = &conflict.existing;
+        let kind = match existing.is_dir {
+            true => "A folder",
+            false => "A file",
+        };
+        let source_mod_time = match conflict.source_mod_time {
+            Some(mod_time) => format!(
+                "The one you are adding was modified {}.",
+                format_time(mod_time)
+            ),
+            None => String::from("The modification time of the one you are adding is unknown."),
+        };
+        let alert = adw::AlertDialog::builder()
+            .heading(format!("\"{}\" already exists", existing.name))
+            .body(format!(
+                "{} with this name in {} was modified {}. {}",
+                kind,
+                existing.path.resolve_to_parent(),
+                format_time(existing.mod_time),
+                source_mod_time
+            ))
+            .build();
+        let remaining = pending.remaining();
+        let apply_to_all = gtk::CheckButton::builder()
+            .label(format!("Apply to all {} conflicts", remaining))
+            .halign(gtk::Align::Center)
+            .visible(remaining > 1)
+            .build();
+        alert.set_extra_child(Some(&apply_to_all));
+        alert.add_response("cancel", "Cancel");
+        alert.add_response("skip", "Skip");
+        alert.add_response("keep_both", "Keep Both");
+        alert.add_response("if_newer", "Overwrite if Newer");
+        alert.add_response("overwrite", "Overwrite");
+        alert.set_response_appearance("overwrite", adw::ResponseAppearance::Destructive);
+        alert.set_default_response(Some("keep_both"));
+        alert.set_close_response("cancel");
+        let sender = sender.clone();
+        alert.connect_response(None, move |_, response| {
+            let resolution = match response {
+                "skip" => ConflictResolution::Skip,
+                "keep_both" => ConflictResolution::KeepBoth,
+                "if_newer" => ConflictResolution::OverwriteIfNewer,
+                "overwrite" => ConflictResolution::Overwrite,
+                _ => {
+                    sender.input(AppInMsg::TransfersCancelled);
+                    return;
+                }
+            };
+            sender.input(AppInMsg::TransferConflictAnswered(
+                resolution,
+                apply_to_all.is_active(),
+            ));
+        });
+        alert.present(Some(root));
+    }
+
+    /// Queues the transfers of the first pending request once all of its conflicts have answers,
+    /// then moves on to the next request.
+    fn finish_pending_transfers(
+        &mut self,
+        sender: &ComponentSender<App>,
+        root: &adw::ApplicationWindow,
+    ) {
+        if self
+            .pending_transfers
+            .front()
+            .is_some_and(|pending| pending.next_conflict().is_none())
+        {
+            let pending = self.pending_transfers.pop_front().unwrap();
+            let check = pending.check.clone();
+            let items = pending.resolved();
+            let overwrites = items.iter().any(|item| check.overwrites(item));
+            if let Some(job_type) = RcloneJobType::grouped(items) {
+                let mut job = RcloneJob::new(job_type);
+                job.overwrites = overwrites;
+                self.enqueue_job(sender, job);
+            }
+        }
+        self.ask_about_conflict(sender, root);
+    }
+
+    /// Points the preview panel at the selected file, unless the panel is hidden.
+    fn update_preview(&self) {
+        if !self.config.show_preview_pane {
+            return;
+        }
+        self.preview_pane
+            .emit(match self.selected_file_listings.as_slice() {
+                [listing] if self.client.is_some() => PreviewPaneInMsg::Show(PreviewRequest {
+                    client: self.client.clone().unwrap(),
+                    listing: listing.clone(),
+                    size_limit: self.config.preview_size_limit(),
+                }),
+                [] => PreviewPaneInMsg::Clear(String::from("Select a file to preview")),
+                listings => PreviewPaneInMsg::Clear(describe_selection(listings)),
+            });
+    }
+
+    /// Fills the file view with the loaded listings, in the order and layout from the config.
+    fn show_file_listings(&mut self) {
+        let mut listings = self.filter.apply(self.file_listings.clone());
+        sort_listings(&mut listings, self.config.file_sort);
+
+        self.file_listing_view_wrapper.clear();
+        for (index, listing) in listings.into_iter().enumerate() {
+            let is_dir = listing.is_dir;
+            self.file_listing_view_wrapper
+                .append(FileListingView::new(listing, self.config.compact_file_list));
+            if index == 0 && !is_dir {
+                if let FilePickerMode::Copy(_) = &FILE_PICKER_MODE.read().deref() {
+                    // Avoid selecting grayed out files
+                    self.file_listing_view_wrapper
+                        .selection_model
+                        .unselect_all();
+                }
+            }
+        }
+    }
+
+    /// Selects a listing of the current folder and scrolls it into view.
+    fn reveal_file_listing(&self, path: &RclonePath) {
+        let wrapper = &self.file_listing_view_wrapper;
+        let position = (0..wrapper.len()).find(|position| {
+            wrapper
+                .get(*position)
+                .is_some_and(|item| item.borrow().model.path == *path)
+        });
+        if let Some(position) = position {
+            wrapper.selection_model.select_item(position, true);
+            wrapper
+                .view
+                .scroll_to(position, gtk::ListScrollFlags::FOCUS, None);
+        }
+    }
+
+    /// A column header, with an arrow on the column the files are sorted by.
+    fn column_title(&self, key: SortKey) -> String {
+        match self.config.file_sort {
+            FileSort {
+                key: sort_key,
+                descending,
+            } if sort_key == key => {
+                format!("{} {}", key.label(), if descending { "↓" } else { "↑" })
+            }
+            _ => String::from(key.label()),
+        }
+    }
+
+    /// The listings currently selected in the file view, in the order they are shown.
+    fn selected_listings(&self) -> Vec<RcloneFileListing> {
+        let selection = self.file_listing_view_wrapper.selection_model.selection();
+        (0..selection.size() as u32)
+            .filter_map(|index| self.file_listing_view_wrapper.get(selection.nth(index)))
+            .map(|item| item.borrow().model.clone())
+            .collect()
+    }
+
+    fn retry_job(&self, sender: &ComponentSender<App>, uuid: Uuid) {
+        if let Some(job) = JOBS.write().get_mut(&uuid) {
+            if job.is_retryable() {
+                job.reset_for_retry();
+            }
+        }
+        self.schedule_jobs(sender);
+    }
+
+    /// Loads what the remote editor needs in the background, then opens it on `remote`, named
+    /// without the colon, or on a new remote.
+    fn load_remote_editor(&self, sender: &ComponentSender<App>, remote: Option<String>) {
+        let Some(client) = self.client.clone() else {
+            return;
+        };
+        sender.spawn_oneshot_command(move || {
+            AppOutCmd::RemoteEditorLoaded(Self::remote_editor_init(client.as_ref(), remote))
+        });
+    }
+
+    fn remote_editor_init(
+        client: &dyn StorageClient,
+        remote: Option<String>,
+    ) -> Result<RemoteEditorDialogInit, RcloneError> {
+        let existing = match remote {
+            Some(remote) => Some((remote.clone(), client.remote_config(&remote)?)),
+            None => None,
+        };
+        let remotes = client
+            .list_remotes()?
+            .iter()
+            .map(|r| r.trim_end_matches(':').to_owned())
+            .collect();
+        Ok(RemoteEditorDialogInit {
+            providers: client.providers()?,
+            remotes,
+            existing,
+        })
+    }
+
+    fn open_remote_editor(
+        &mut self,
+        sender: &ComponentSender<App>,
+        root: &adw::ApplicationWindow,
+        init: RemoteEditorDialogInit,
+    ) {
+        let dialog =
+            RemoteEditorDialog::builder()
+                .launch(init)
+                .forward(sender.input_sender(), |msg| match msg {
+                    RemoteEditorDialogOutMsg::Saved(edit) => AppInMsg::RemoteEditSubmitted(edit),
+                    RemoteEditorDialogOutMsg::DeleteRequested(remote) => {
+                        AppInMsg::RemoteDeletionRequested(remote)
+                    }
+                });
+        dialog.widget().present(Some(root));
+        self.active_remote_editor = Some(dialog);
+    }
+
+    /// Changes the rclone config in the background. `selected_remote` is opened afterwards if
+    /// the change went through.
+    fn change_remote_config<F>(
+        sender: &ComponentSender<App>,
+        selected_remote: Option<String>,
+        change: F,
+    ) where
+        F: FnOnce() -> Result<(), RcloneError> + Send + 'static,
+    {
+        sender.spawn_oneshot_command(move || {
+            AppOutCmd::RemoteConfigChanged(change(), selected_remote)
+        });
+    }
+
+    /// What a remote in the sidebar last reported about its space.
+    fn remote_quota(&self, remote: &str) -> Option<RemoteQuota> {
+        self.remotes_view_wrapper
+            .iter()
+            .find(|view| view.name == remote)
+            .and_then(|view| view.quota)
+    }
+
+    /// Has the sidebar ask these remotes about their space again.
+    fn refresh_quotas(&self, remotes: &BTreeSet<String>) {
+        let Some(client) = &self.client else {
+            return;
+        };
+        for (index, view) in self.remotes_view_wrapper.iter().enumerate() {
+            if remotes.contains(&view.name) {
+                self.remotes_view_wrapper.send(
+                    index,
+                    RemoteViewInMsg::QuotaRefreshRequested(client.clone()),
+                );
+            }
+        }
+    }
+
+    /// Highlights a remote in the sidebar and opens it.
+    fn select_remote(&self, sender: &ComponentSender<App>, remote: &str) {
+        let index = (0..self.remotes_view_wrapper.len())
+            .find(|i| self.remotes_view_wrapper.get(*i).map(|r| r.name.as_str()) == Some(remote));
+        if let Some(index) = index {
+            if let Some(list_box_row) = self
+                .remotes_view_wrapper
+                .widget()
+                .row_at_index(index as i32)
+            {
+                self.remotes_view_wrapper
+                    .widget()
+                    .select_row(Some(&list_box_row));
+            }
+            sender.input(AppInMsg::RemoteSelectionChanged(index));
+        }
+    }
+
     fn refresh_remotes(&mut self, sender: &ComponentSender<App>) {
-        let remotes = self.client.as_ref().unwrap().list_remotes().unwrap();
+        let client = self.client.clone().unwrap();
+        let remotes = match client.list_remotes() {
+            Ok(remotes) => remotes,
+            Err(error) => {
+                sender.input(AppInMsg::TriggerGenericError(
+                    String::from(error.title()),
+                    error.description(),
+                    false,
+                ));
+                return;
+            }
+        };
         self.remotes_view_wrapper.guard().clear();
         for (i, remote) in remotes.into_iter().enumerate() {
             if i == 0 {
                 sender.input(AppInMsg::PathChanged(RclonePath::from(&remote)));
             }
-            self.remotes_view_wrapper.guard().push_back(remote);
+            self.remotes_view_wrapper.guard().push_back(RemoteViewInit {
+                name: remote,
+                client: client.clone(),
+            });
         }
         if let Some(list_box_row) = self.remotes_view_wrapper.widget().row_at_index(0) {
             self.remotes_view_wrapper
@@ -179,7 +884,8 @@ impl App {
 
 #[relm4::component]
 impl Component for App {
-    type Init = ();
+    /// A ready-made storage backend to use instead of looking for rclone, e.g. in tests.
+    type Init = Option<Arc<dyn StorageClient>>;
     type Input = AppInMsg;
     type Output = ();
     type CommandOutput = AppOutCmd;
@@ -201,293 +907,383 @@ impl Component for App {
                     Some(&glib::Value::from(&true))
                 )
             },
-            adw::ToolbarView {
-                set_top_bar_style: ToolbarStyle::Raised,
-                add_top_bar = &adw::HeaderBar {
-                    pack_start = &gtk::Box {
-                        gtk::Button {
-                            #[watch]
-                            set_visible: model.reveal_files_on_small_screens && model.split_collapsed,
-                            set_icon_name: icon_names::LEAFLET,
-                            set_tooltip_text: Some("Open sidebar"),
-                            connect_clicked => Self::Input::RevealSidebar,
-                        },
-                        gtk::MenuButton {
-                            set_icon_name: icon_names::MENU,
-                            set_tooltip_text: Some("Menu"),
-                            #[wrap(Some)]
-                            set_popover = &gtk::PopoverMenu::from_model(Some(&main_menu)) {}
-                        }
-                    }
-                },
-
+            #[local_ref]
+            toast_overlay -> adw::ToastOverlay {
                 #[wrap(Some)]
-                set_content = &gtk::Box {
-                    set_hexpand: true,
-                    set_vexpand: true,
-
-                    if model.client.is_none() && model.requires_password {
-                        adw::Clamp {
-                            set_hexpand: true,
-                            set_vexpand: true,
-                            #[wrap(Some)]
-                            set_child = model.unlock_widget.widget(),
-                        }
-                    } else if model.client.is_some() && model.remotes_view_wrapper.is_empty() {
-                        adw::StatusPage {
-                            set_title: "No remotes configured",
-                            set_description: "Add a remote via 'rclone config'\nand then come back to browse it.".into(),
-                            set_icon_name: Some(icon_names::INFO_OUTLINE),
-                        }
-                    } else {
-                        #[name = "split_view"]
-                        adw::NavigationSplitView {
-                            #[watch]
-                            set_show_content: model.reveal_files_on_small_screens,
-                            connect_collapsed_notify[sender] => move |value| {
-                                sender.input(Self::Input::SplitCollapsed(value.is_collapsed()));
+                set_child = &adw::ToolbarView {
+                    set_top_bar_style: ToolbarStyle::Raised,
+                    add_top_bar = &adw::HeaderBar {
+                        pack_start = &gtk::Box {
+                            gtk::Button {
+                                #[watch]
+                                set_visible: model.reveal_files_on_small_screens && model.split_collapsed,
+                                set_icon_name: icon_names::LEAFLET,
+                                set_tooltip_text: Some("Open sidebar"),
+                                connect_clicked => Self::Input::RevealSidebar,
                             },
-                            #[wrap(Some)]
-                            set_sidebar = &adw::NavigationPage {
-                                set_title: "Remotes",
+                            gtk::MenuButton {
+                                set_icon_name: icon_names::MENU,
+                                set_tooltip_text: Some("Menu"),
+                                #[wrap(Some)]
+                                set_popover = &gtk::PopoverMenu::from_model(Some(&main_menu)) {}
+                            }
+                        }
+                    },
+
+                    #[wrap(Some)]
+                    set_content = &gtk::Box {
+                        set_hexpand: true,
+                        set_vexpand: true,
 
+                        if model.client.is_none() && model.requires_password {
+                            adw::Clamp {
+                                set_hexpand: true,
+                                set_vexpand: true,
                                 #[wrap(Some)]
-                                set_child = &gtk::Box {
-                                    set_orientation: gtk::Orientation::Vertical,
-
-                                    gtk::ScrolledWindow {
-                                        #[local_ref]
-                                        remotes_view -> gtk::ListBox {
-                                            set_hexpand: true,
-                                            set_vexpand: true,
-                                            add_css_class: "navigation-sidebar",
-                                            connect_row_activated[sender] => move |list_box, row| {
-                                                if let Some(i) = list_box.index_of_child(row) {
-                                                    sender.input(Self::Input::RemoteSelectionChanged(i as usize));
-                                                }
+                                set_child = model.unlock_widget.widget(),
+                            }
+                        } else if model.client.is_some() && model.remotes_view_wrapper.is_empty() {
+                            adw::StatusPage {
+                                set_title: "No remotes configured",
+                                set_description: Some("Add a remote to start browsing it"),
+                                set_icon_name: Some(icon_names::INFO_OUTLINE),
+                                #[wrap(Some)]
+                                set_child = &gtk::Button {
+                                    set_label: "Add Remote",
+                                    set_halign: gtk::Align::Center,
+                                    add_css_class: "pill",
+                                    add_css_class: "suggested-action",
+                                    connect_clicked => Self::Input::RemoteCreationRequested,
+                                },
+                            }
+                        } else {
+                            #[name = "split_view"]
+                            adw::NavigationSplitView {
+                                #[watch]
+                                set_show_content: model.reveal_files_on_small_screens,
+                                connect_collapsed_notify[sender] => move |value| {
+                                    sender.input(Self::Input::SplitCollapsed(value.is_collapsed()));
+                                },
+                                #[wrap(Some)]
+                                set_sidebar = &adw::NavigationPage {
+                                    set_title: "Remotes",
+
+                                    #[wrap(Some)]
+                                    set_child = &gtk::Box {
+                                        set_orientation: gtk::Orientation::Vertical,
+
+                                        gtk::ScrolledWindow {
+                                            #[local_ref]
+                                            remotes_view -> gtk::ListBox {
+                                                set_hexpand: true,
+                                                set_vexpand: true,
+                                                add_css_class: "navigation-sidebar",
+                                                connect_row_activated[sender] => move |list_box, row| {
+                                                    if let Some(i) = list_box.index_of_child(row) {
+                                                        sender.input(Self::Input::RemoteSelectionChanged(i as usize));
+                                                    }
+                                                },
                                             },
                                         },
-                                    },
-
-                                    model.queue_button.widget(),
-                                }
-                            },
-                            #[wrap(Some)]
-                            set_content = &adw::NavigationPage {
-                                set_title: "Files",
 
+                                        model.queue_button.widget(),
+                                    }
+                                },
                                 #[wrap(Some)]
-                                set_child = &adw::ToolbarView {
-                                    set_vexpand: true,
-
-                                    set_top_bar_style: ToolbarStyle::Raised,
-                                    add_top_bar = &gtk::Box {
-                                        set_height_request: 50,
-                                        set_css_classes: &["toolbar"],
-
-                                        gtk::Button {
-                                            set_icon_name: icon_names::LEFT_LARGE,
-                                            set_tooltip_text: Some("Previous (Alt+Left)"),
-                                            connect_clicked => Self::Input::PathUndoRequested,
-                                            #[watch]
-                                            set_sensitive: !model.undoable_paths.is_empty(),
-                                        },
+                                set_content = &adw::NavigationPage {
+                                    set_title: "Files",
 
-                                        gtk::Button {
-                                            set_icon_name: icon_names::RIGHT_LARGE,
-                                            set_tooltip_text: Some("Next (Alt+Right)"),
-                                            connect_clicked => Self::Input::PathRedoRequested,
-                                            #[watch]
-                                            set_sensitive: !model.redoable_paths.is_empty(),
-                                        },
+                                    #[wrap(Some)]
+                                    set_child = &adw::ToolbarView {
+                                        set_vexpand: true,
 
-                                        gtk::Entry {
-                                            set_hexpand: true,
-                                            #[watch]
-                                            set_text: &model.path.to_string(),
-                                            set_margin_horizontal: 5,
-                                            connect_activate[sender] => move |entry| {
-                                                sender.input(Self::Input::PathEntered(RclonePath::from(entry.text().as_ref())));
+                                        set_top_bar_style: ToolbarStyle::Raised,
+                                        add_top_bar = &gtk::Box {
+                                            set_height_request: 50,
+                                            set_css_classes: &["toolbar"],
+
+                                            gtk::Button {
+                                                set_icon_name: icon_names::LEFT_LARGE,
+                                                set_tooltip_text: Some("Previous (Alt+Left)"),
+                                                connect_clicked => Self::Input::PathUndoRequested,
+                                                #[watch]
+                                                set_sensitive: !model.undoable_paths.is_empty(),
                                             },
-                                        },
 
-                                        gtk::Button {
-                                            set_icon_name: icon_names::ARROW_CIRCULAR_TOP_RIGHT,
-                                            set_tooltip_text: Some("Refresh (F5)"),
-                                            connect_clicked => Self::Input::PathRefreshRequested,
-                                        },
+                                            gtk::Button {
+                                                set_icon_name: icon_names::RIGHT_LARGE,
+                                                set_tooltip_text: Some("Next (Alt+Right)"),
+                                                connect_clicked => Self::Input::PathRedoRequested,
+                                                #[watch]
+                                                set_sensitive: !model.redoable_paths.is_empty(),
+                                            },
+
+                                            gtk::Entry {
+                                                set_hexpand: true,
+                                                #[watch]
+                                                set_text: &model.path.to_string(),
+                                                set_margin_horizontal: 5,
+                                                connect_activate[sender] => move |entry| {
+                                                    sender.input(Self::Input::PathEntered(RclonePath::from(entry.text().as_ref())));
+                                                },
+                                            },
+
+                                            gtk::Button {
+                                                set_icon_name: icon_names::ARROW_CIRCULAR_TOP_RIGHT,
+                                                set_tooltip_text: Some("Refresh (F5)"),
+                                                connect_clicked => Self::Input::PathRefreshRequested,
+                                            },
 
-                                        gtk::Button {
-                                            set_icon_name: icon_names::UP_LARGE,
-                                            set_tooltip_text: Some("Up one folder (Alt+Up)"),
-                                            #[watch]
-                                            set_sensitive: model.path.path_has_parent(),
-                                            connect_clicked => Self::Input::PathParentRequested,
+                                            gtk::Button {
+                                                set_icon_name: icon_names::UP_LARGE,
+                                                set_tooltip_text: Some("Up one folder (Alt+Up)"),
+                                                #[watch]
+                                                set_sensitive: model.path.path_has_parent(),
+                                                connect_clicked => Self::Input::PathParentRequested,
+                                            },
                                         },
-                                    },
+                                        add_top_bar: model.filter_bar.widget(),
 
-                                    #[wrap(Some)]
-                                    set_content = &gtk::ScrolledWindow {
-                                        set_vexpand: true,
-                                        set_hscrollbar_policy: gtk::PolicyType::Never,
                                         #[wrap(Some)]
-                                        set_child = match model.file_listing_view_state {
-                                            FileListingViewState::Loading => {
-                                                &gtk::Box {
-                                                    set_halign: gtk::Align::Center,
-                                                    set_valign: gtk::Align::Center,
-
-                                                    gtk::Spinner {
-                                                        set_spinning: true,
-                                                        set_height_request: 30,
-                                                        set_width_request: 30,
-                                                    },
-                                                }
-                                            }
-                                            FileListingViewState::Loaded => {
-                                                &gtk::ScrolledWindow {
-                                                    #[local_ref]
-                                                    file_listing_view -> gtk::ListView {
-                                                        set_hexpand: true,
-                                                        inline_css: "background-color: transparent",
-                                                        connect_activate[sender] => move |_, position| {
-                                                            sender.input(Self::Input::FileListingPositionActivated(position));
-                                                        },
+                                        set_content = &gtk::Box {
+                                            set_orientation: gtk::Orientation::Horizontal,
+
+                                            gtk::ScrolledWindow {
+                                                set_hexpand: true,
+                                                set_vexpand: true,
+                                                set_hscrollbar_policy: gtk::PolicyType::Never,
+                                                #[wrap(Some)]
+                                                set_child = match model.file_listing_view_state {
+                                                    FileListingViewState::Loading => {
+                                                        &gtk::Box {
+                                                            set_halign: gtk::Align::Center,
+                                                            set_valign: gtk::Align::Center,
+
+                                                            gtk::Spinner {
+                                                                set_spinning: true,
+                                                                set_height_request: 30,
+                                                                set_width_request: 30,
+                                                            },
+                                                        }
                                                     }
-                                                }
-                                            }
-                                            FileListingViewState::Error(_) => {
-                                                &gtk::Box {
-                                                    set_orientation: gtk::Orientation::Vertical,
-                                                    set_halign: gtk::Align::Center,
-                                                    set_valign: gtk::Align::Center,
-                                                    set_hexpand: true,
+                                                    FileListingViewState::Loaded => {
+                                                        &gtk::Box {
+                                                            set_orientation: gtk::Orientation::Vertical,
+
+                                                            gtk::Box {
+                                                                set_orientation: gtk::Orientation::Horizontal,
+                                                                set_margin_horizontal: 5,
+                                                                #[watch]
+                                                                set_visible: !model.config.compact_file_list,
+
+                                                                gtk::Button {
+                                                                    add_css_class: "flat",
+                                                                    set_hexpand: true,
+                                                                    #[wrap(Some)]
+                                                                    set_child = &gtk::Label {
+                                                                        set_xalign: 0.0,
+                                                                        #[watch]
+                                                                        set_text: &model.column_title(SortKey::Name),
+                                                                    },
+                                                                    connect_clicked => Self::Input::SortRequested(SortKey::Name),
+                                                                },
+                                                                gtk::Button {
+                                                                    add_css_class: "flat",
+                                                                    set_width_request: SIZE_COLUMN_WIDTH,
+                                                                    #[wrap(Some)]
+                                                                    set_child = &gtk::Label {
+                                                                        set_xalign: 1.0,
+                                                                        #[watch]
+                                                                        set_text: &model.column_title(SortKey::Size),
+                                                                    },
+                                                                    connect_clicked => Self::Input::SortRequested(SortKey::Size),
+                                                                },
+                                                                gtk::Button {
+                                                                    add_css_class: "flat",
+                                                                    set_width_request: MODIFIED_COLUMN_WIDTH,
+                                                                    #[wrap(Some)]
+                                                                    set_child = &gtk::Label {
+                                                                        set_xalign: 0.0,
+                                                                        #[watch]
+                                                                        set_text: &model.column_title(SortKey::Modified),
+                                                                    },
+                                                                    connect_clicked => Self::Input::SortRequested(SortKey::Modified),
+                                                                },
+                                                                gtk::Button {
+                                                                    add_css_class: "flat",
+                                                                    set_width_request: TYPE_COLUMN_WIDTH,
+                                                                    #[wrap(Some)]
+                                                                    set_child = &gtk::Label {
+                                                                        set_xalign: 0.0,
+                                                                        #[watch]
+                                                                        set_text: &model.column_title(SortKey::Type),
+                                                                    },
+                                                                    connect_clicked => Self::Input::SortRequested(SortKey::Type),
+                                                                },
+                                                            },
 
-                                                    adw::StatusPage {
-                                                        set_height_request: 100,
-                                                        set_hexpand: true,
-                                                        set_title: "Error",
-                                                        set_icon_name: Some(icon_names::WARNING_OUTLINE),
-                                                    },
+                                                            gtk::ScrolledWindow {
+                                                                set_vexpand: true,
+
+                                                                #[local_ref]
+                                                                file_listing_view -> gtk::ListView {
+                                                                    set_hexpand: true,
+                                                                    set_enable_rubberband: true,
+                                                                    inline_css: "background-color: transparent",
+                                                                    connect_activate[sender] => move |_, position| {
+                                                                        sender.input(Self::Input::FileListingPositionActivated(position));
+                                                                    },
+                                                                }
+                                                            }
+                                                        }
+                                                    }
+                                                    FileListingViewState::Error(_) => {
+                                                        &gtk::Box {
+                                                            set_orientation: gtk::Orientation::Vertical,
+                                                            set_halign: gtk::Align::Center,
+                                                            set_valign: gtk::Align::Center,
+                                                            set_hexpand: true,
+
+                                                            adw::StatusPage {
+                                                                set_height_request: 100,
+                                                                set_hexpand: true,
+                                                                #[watch]
+                                                                set_title: match &model.file_listing_view_state {
+                                                                    FileListingViewState::Error(error) => error.title(),
+                                                                    _ => "Error",
+                                                                },
+                                                                #[watch]
+                                                                set_description: match &model.file_listing_view_state {
+                                                                    FileListingViewState::Error(error) => Some(error.description()),
+                                                                    _ => None,
+                                                                }.as_deref(),
+                                                                set_icon_name: Some(icon_names::WARNING_OUTLINE),
+                                                            },
 
-                                                    gtk::Button {
-                                                        set_label: "Details",
-                                                        connect_clicked => Self::Input::FileListingErrorDetailRequested,
+                                                            gtk::Button {
+                                                                set_label: "Details",
+                                                                connect_clicked => Self::Input::FileListingErrorDetailRequested,
+                                                            }
+                                                        }
                                                     }
-                                                }
-                                            }
-                                        },
-                                    },
+                                                },
+                                            },
+
+                                            gtk::Separator {
+                                                set_orientation: gtk::Orientation::Vertical,
+                                                #[watch]
+                                                set_visible: model.config.show_preview_pane,
+                                            },
 
-                                    add_bottom_bar = &gtk::Box {
-                                        set_orientation: gtk::Orientation::Horizontal,
-                                        set_height_request: 50,
-                                        set_css_classes: &["toolbar"],
-                                        set_margin_horizontal: 5,
+                                            append: model.preview_pane.widget(),
+                                        },
 
-                                        gtk::Box {
+                                        add_bottom_bar = &gtk::Box {
                                             set_orientation: gtk::Orientation::Horizontal,
-                                            set_halign: gtk::Align::Start,
-                                            set_hexpand: true,
+                                            set_height_request: 50,
+                                            set_css_classes: &["toolbar"],
+                                            set_margin_horizontal: 5,
 
-                                            gtk::Label {
-                                                set_ellipsize: gtk::pango::EllipsizeMode::End,
+                                            gtk::Box {
+                                                set_orientation: gtk::Orientation::Horizontal,
+                                                set_halign: gtk::Align::Start,
                                                 set_hexpand: true,
-                                                #[watch]
-                                                set_text: &match &FILE_PICKER_MODE.read().deref() {
-                                                    FilePickerMode::Select => {
-                                                        if let Some(listing) = &model.selected_file_listing_copy {
-                                                            format!(
-                                                                "\"{}\" selected{}",
-                                                                listing.name,
-                                                                match listing.formatted_size() {
-                                                                    Some(size) => format!(" ({}B)", size),
-                                                                    None => String::from(""),
-                                                                }
-                                                            )
-                                                        } else {
-                                                            String::from("")
+
+                                                gtk::Label {
+                                                    set_ellipsize: gtk::pango::EllipsizeMode::End,
+                                                    set_hexpand: true,
+                                                    #[watch]
+                                                    set_text: &match &FILE_PICKER_MODE.read().deref() {
+                                                        FilePickerMode::Select if model.selected_file_listings.is_empty() && model.filter != ListingFilter::default() => {
+                                                            format!("{} of {} shown", model.file_listing_view_wrapper.len(), model.file_listings.len())
+                                                        }
+                                                        FilePickerMode::Select => describe_selection(&model.selected_file_listings),
+                                                        FilePickerMode::Move(paths) | FilePickerMode::Copy(paths) => match paths.as_slice() {
+                                                            [path] => format!("Select folder for \"{}\"", path.filename()),
+                                                            _ => format!("Select folder for {} items", paths.len()),
                                                         }
-                                                    }
-                                                    FilePickerMode::Move(path) | FilePickerMode::Copy(path) => {
-                                                        format!("Select folder for \"{}\"", path.filename().clone())
                                                     }
                                                 }
-                                            }
-                                        },
+                                            },
 
-                                        match FILE_PICKER_MODE.read().deref() {
-                                            FilePickerMode::Select => {
-                                                &gtk::Box {
-                                                    set_orientation: gtk::Orientation::Horizontal,
-                                                    set_halign: gtk::Align::End,
-                                                    set_hexpand: true,
-                                                    set_spacing: 5,
+                                            match FILE_PICKER_MODE.read().deref() {
+                                                FilePickerMode::Select => {
+                                                    &gtk::Box {
+                                                        set_orientation: gtk::Orientation::Horizontal,
+                                                        set_halign: gtk::Align::End,
+                                                        set_hexpand: true,
+                                                        set_spacing: 5,
 
-                                                    gtk::Button {
-                                                        connect_clicked => AppInMsg::CreateFolderRequested,
-                                                        add_css_class: "flat",
+                                                        gtk::Button {
+                                                            connect_clicked => AppInMsg::CreateFolderRequested,
+                                                            add_css_class: "flat",
 
-                                                        gtk::Box {
-                                                            gtk::Label {
-                                                                set_text: "New folder",
-                                                            },
-                                                        }
-                                                    },
+                                                            gtk::Box {
+                                                                gtk::Label {
+                                                                    set_text: "New folder",
+                                                                },
+                                                            }
+                                                        },
+
+                                                        gtk::MenuButton {
+                                                            set_label: "Edit",
+                                                            set_menu_model: Some(&file_listing_actions),
+                                                        },
 
-                                                    gtk::MenuButton {
-                                                        set_label: "Edit",
-                                                        set_menu_model: Some(&file_listing_actions),
-                                                    },
+                                                        gtk::MenuButton {
+                                                            set_label: "View",
+                                                            set_menu_model: Some(&view_options),
+                                                        },
 
+                                                    }
                                                 }
-                                            }
-                                            FilePickerMode::Move(_) => {
-                                                &gtk::Box {
-                                                    set_orientation: gtk::Orientation::Horizontal,
-                                                    set_halign: gtk::Align::End,
-                                                    set_hexpand: true,
-                                                    set_spacing: 5,
-
-                                                    gtk::Button {
-                                                        set_label: "Move here",
-                                                        set_tooltip_text: Some("Confirm move action (F7)"),
-                                                        connect_clicked => Self::Input::MoveTargetConfirmed,
-                                                    },
-
-                                                    gtk::Button {
-                                                        set_label: "Cancel",
-                                                        set_tooltip_text: Some("Cancel"),
-                                                        connect_clicked => Self::Input::ReturnToSelectModeRequested,
-                                                    },
+                                                FilePickerMode::Move(_) => {
+                                                    &gtk::Box {
+                                                        set_orientation: gtk::Orientation::Horizontal,
+                                                        set_halign: gtk::Align::End,
+                                                        set_hexpand: true,
+                                                        set_spacing: 5,
+
+                                                        gtk::Button {
+                                                            set_label: "Move here",
+                                                            set_tooltip_text: Some("Confirm move action (F7)"),
+                                                            connect_clicked => Self::Input::MoveTargetConfirmed,
+                                                        },
+
+                                                        gtk::Button {
+                                                            set_label: "Cancel",
+                                                            set_tooltip_text: Some("Cancel"),
+                                                            connect_clicked => Self::Input::ReturnToSelectModeRequested,
+                                                        },
+                                                    }
                                                 }
-                                            }
-                                            FilePickerMode::Copy(_) => {
-                                                &gtk::Box {
-                                                    set_orientation: gtk::Orientation::Horizontal,
-                                                    set_halign: gtk::Align::End,
-                                                    set_hexpand: true,
-                                                    set_spacing: 5,
-
-                                                    gtk::Button {
-                                                        set_label: "Copy here",
-                                                        set_tooltip_text: Some("Confirm copy action (F7)"),
-                                                        connect_clicked => Self::Input::CopyTargetConfirmed,
-                                                    },
-
-                                                    gtk::Button {
-                                                        set_label: "Cancel",
-                                                        set_tooltip_text: Some("Cancel"),
-                                                        connect_clicked => Self::Input::ReturnToSelectModeRequested,
-                                                    },
+                                                FilePickerMode::Copy(_) => {
+                                                    &gtk::Box {
+                                                        set_orientation: gtk::Orientation::Horizontal,
+                                                        set_halign: gtk::Align::End,
+                                                        set_hexpand: true,
+                                                        set_spacing: 5,
+
+                                                        gtk::Button {
+                                                            set_label: "Copy here",
+                                                            set_tooltip_text: Some("Confirm copy action (F7)"),
+                                                            connect_clicked => Self::Input::CopyTargetConfirmed,
+                                                        },
+
+                                                        gtk::Button {
+                                                            set_label: "Cancel",
+                                                            set_tooltip_text: Some("Cancel"),
+                                                            connect_clicked => Self::Input::ReturnToSelectModeRequested,
+                                                        },
+                                                    }
                                                 }
-                                            }
-                                        },
+                                            },
+                                        }
                                     }
                                 }
                             }
-                        }
-                    },
+                        },
+                    }
                 }
             }
         }
@@ -497,9 +1293,13 @@ impl Component for App {
         main_menu: {
             section! {
                 "Refresh remotes" => RemotesRefreshAction,
-                "Configure remotes" => RemotesConfigureAction,
+                "Add remote" => RemoteAddAction,
+                "Configure remote" => RemotesConfigureAction,
+                "New encrypted remote" => CryptWizardAction,
             },
             section! {
+                "Move deleted items to trash" => UseTrashAction,
+                "Clear cache" => ClearCacheAction,
                 "About" => AboutAction,
             }
         },
@@ -509,11 +1309,32 @@ impl Component for App {
             "Copy" => CopyAction,
             "Download" => DownloadAction,
             "Delete" => DeleteAction,
-        }
+            "Properties" => PropertiesAction,
+            section! {
+                "Select all" => SelectAllAction,
+                "Search in this folder" => SearchAction,
+                "Disk usage" => DiskUsageAction,
+                "Undo" => UndoAction,
+                "Trash" => TrashAction,
+            }
+        },
+        view_options: {
+            section! {
+                "Filter" => FilterAction,
+                "Compact list" => CompactListAction,
+                "Preview panel" => PreviewPaneAction,
+            },
+            section! {
+                "Sort by name" => SortByNameAction,
+                "Sort by size" => SortBySizeAction,
+                "Sort by modified time" => SortByModifiedAction,
+                "Sort by type" => SortByTypeAction,
+            }
+        }
     }
 
     fn init(
-        _: Self::Init,
+        init_client: Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
@@ -521,26 +1342,37 @@ impl Component for App {
             UnlockView::builder()
                 .launch(())
                 .forward(sender.input_sender(), |msg| match msg {
-                    UnlockViewOutMsg::PasswordSubmitted(password) => {
-                        AppInMsg::PasswordReceived(password)
+                    UnlockViewOutMsg::PasswordSubmitted(password, remember_password) => {
+                        AppInMsg::PasswordReceived(password, remember_password)
                     }
                 });
-        let queue_button = QueueButton::builder().launch(()).detach();
+        *JOBS.write() = load_jobs();
+        let queue_button =
+            QueueButton::builder()
+                .launch(())
+                .forward(sender.input_sender(), |msg| match msg {
+                    QueueViewOutMsg::RetryRequested(uuid) => AppInMsg::JobRetryRequested(uuid),
+                });
+
+        let filter_bar = FilterBar::builder()
+            .launch(())
+            .forward(sender.input_sender(), |msg| match msg {
+                FilterBarOutMsg::FilterChanged(filter) => AppInMsg::FilterChanged(filter),
+            });
+
+        let preview_pane = PreviewPane::builder().launch(()).detach();
 
         let remotes_view_wrapper = FactoryVecDeque::builder()
             .launch(gtk::ListBox::new())
             .detach();
-        let file_listing_view_wrapper: TypedListView<FileListingView, gtk::SingleSelection> =
+        let file_listing_view_wrapper: TypedListView<FileListingView, gtk::MultiSelection> =
             TypedListView::new();
         file_listing_view_wrapper
             .selection_model
-            .set_can_unselect(true);
-        file_listing_view_wrapper
-            .selection_model
-            .connect_selected_item_notify(clone!(
+            .connect_selection_changed(clone!(
                 #[strong]
                 sender,
-                move |_| {
+                move |_, _, _| {
                     sender.input(Self::Input::FileListingSelectionChanged);
                 }
             ));
@@ -558,22 +1390,37 @@ impl Component for App {
             }
         ));
 
+        let config = AppConfig::load();
         let rclone_config_file = std::env::var("RCLONE_CONFIG_FILE").ok();
         let mut requires_password = true;
-        match RcloneClient::is_password_required(&rclone_config_file) {
-            Ok(outcome) => {
-                requires_password = outcome;
-                if requires_password {
-                    unlock_widget.emit(UnlockViewInMsg::PasswordFocusRequested);
-                } else {
-                    match RcloneClient::new(None, rclone_config_file) {
-                        Ok(client) => sender.input(Self::Input::ClientConnected(client)),
-                        Err(_) => sender.input(Self::Input::ClientConnectionFailed),
+        if let Some(client) = init_client {
+            requires_password = false;
+            sender.input(Self::Input::ClientConnected(client));
+        } else {
+            match RcloneClient::is_password_required(&rclone_config_file) {
+                Ok(password_required) => {
+                    requires_password = false;
+                    if password_required {
+                        // The keyring can take a moment to answer, so the password is only asked
+                        // for once it turns out not to have a working one
+                        sender.spawn_oneshot_command(move || {
+                            let keyring_available = keyring::is_available();
+                            let client = match keyring_available {
+                                true => Self::unlock_with_remembered_password(&rclone_config_file),
+                                false => None,
+                            };
+                            AppOutCmd::RememberedPasswordTried(keyring_available, client)
+                        });
+                    } else {
+                        match RcloneClient::new(None, rclone_config_file) {
+                            Ok(client) => Self::connect_backend(&sender, client, &config),
+                            Err(_) => sender.input(Self::Input::ClientConnectionFailed),
+                        }
                     }
                 }
-            }
-            Err(_) => {
-                sender.input(Self::Input::ClientConnectionFailed);
+                Err(_) => {
+                    sender.input(Self::Input::ClientConnectionFailed);
+                }
             }
         }
 
@@ -582,22 +1429,51 @@ impl Component for App {
             remotes_view_wrapper,
             file_listing_view_wrapper,
             file_listing_view_state: FileListingViewState::Loading,
+            file_listings: vec![],
             queue_button,
+            filter_bar,
+            filter: ListingFilter::default(),
+            preview_pane,
             path: RclonePath::from(""),
             undoable_paths: vec![],
             redoable_paths: vec![],
             client: None,
             requires_password,
-            selected_file_listing_copy: None,
+            selected_file_listings: vec![],
             active_string_prompt: None,
+            active_remote_editor: None,
+            active_crypt_wizard: None,
+            active_search: None,
+            active_disk_usage: None,
+            active_trash: None,
+            active_properties: None,
+            revealed_path: None,
             save_copy_dialog: None,
+            download_folder_dialog: None,
             reveal_files_on_small_screens: false,
             split_collapsed: false,
+            config,
+            persisted_jobs: serialize_jobs(&JOBS.read()),
+            opened_files: vec![],
+            file_cache: FileCache::load(),
+            pending_transfers: VecDeque::new(),
+            toast_overlay: adw::ToastOverlay::new(),
+            undo_stack: vec![],
         };
         let remotes_view = model.remotes_view_wrapper.widget();
         let file_listing_view = &model.file_listing_view_wrapper.view;
+        let toast_overlay = &model.toast_overlay;
         file_listing_view.add_controller(drop_target);
         let widgets = view_output!();
+        // Typing anywhere in the window starts filtering the folder
+        model
+            .filter_bar
+            .widget()
+            .set_key_capture_widget(Some(&widgets.window));
+        model
+            .preview_pane
+            .widget()
+            .set_visible(model.config.show_preview_pane);
 
         let app = relm4::main_application();
         let rename_action: RelmAction<RenameAction> = {
@@ -645,6 +1521,95 @@ impl Component for App {
                 }
             ))
         };
+        let compact_list_action: RelmAction<CompactListAction> = {
+            RelmAction::new_stateful(
+                &model.config.compact_file_list,
+                clone!(
+                    #[strong]
+                    sender,
+                    move |_, compact: &mut bool| {
+                        *compact = !*compact;
+                        sender.input(Self::Input::CompactListToggled(*compact));
+                    }
+                ),
+            )
+        };
+        let preview_pane_action: RelmAction<PreviewPaneAction> = {
+            RelmAction::new_stateful(
+                &model.config.show_preview_pane,
+                clone!(
+                    #[strong]
+                    sender,
+                    move |_, show: &mut bool| {
+                        *show = !*show;
+                        sender.input(Self::Input::PreviewPaneToggled(*show));
+                    }
+                ),
+            )
+        };
+        let sort_by_name_action: RelmAction<SortByNameAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::SortRequested(SortKey::Name));
+                }
+            ))
+        };
+        let sort_by_size_action: RelmAction<SortBySizeAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::SortRequested(SortKey::Size));
+                }
+            ))
+        };
+        let sort_by_modified_action: RelmAction<SortByModifiedAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::SortRequested(SortKey::Modified));
+                }
+            ))
+        };
+        let sort_by_type_action: RelmAction<SortByTypeAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::SortRequested(SortKey::Type));
+                }
+            ))
+        };
+        let filter_action: RelmAction<FilterAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::FilterToggled);
+                }
+            ))
+        };
+        let search_action: RelmAction<SearchAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::SearchRequested);
+                }
+            ))
+        };
+        let select_all_action: RelmAction<SelectAllAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::SelectAllRequested);
+                }
+            ))
+        };
         let path_refresh_action: RelmAction<PathRefreshAction> = {
             RelmAction::new_stateless(clone!(
                 #[strong]
@@ -690,6 +1655,15 @@ impl Component for App {
                 }
             ))
         };
+        let remote_add_action: RelmAction<RemoteAddAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::RemoteCreationRequested);
+                }
+            ))
+        };
         let remotes_configure_action: RelmAction<RemotesConfigureAction> = {
             RelmAction::new_stateless(clone!(
                 #[strong]
@@ -699,6 +1673,73 @@ impl Component for App {
                 }
             ))
         };
+        let crypt_wizard_action: RelmAction<CryptWizardAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::CryptWizardRequested);
+                }
+            ))
+        };
+        let trash_action: RelmAction<TrashAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::TrashRequested);
+                }
+            ))
+        };
+        let properties_action: RelmAction<PropertiesAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::PropertiesRequested);
+                }
+            ))
+        };
+        let disk_usage_action: RelmAction<DiskUsageAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::DiskUsageRequested);
+                }
+            ))
+        };
+        let undo_action: RelmAction<UndoAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::UndoRequested);
+                }
+            ))
+        };
+        let use_trash_action: RelmAction<UseTrashAction> = {
+            RelmAction::new_stateful(
+                &model.config.use_trash,
+                clone!(
+                    #[strong]
+                    sender,
+                    move |_, use_trash: &mut bool| {
+                        *use_trash = !*use_trash;
+                        sender.input(Self::Input::UseTrashToggled(*use_trash));
+                    }
+                ),
+            )
+        };
+        let clear_cache_action: RelmAction<ClearCacheAction> = {
+            RelmAction::new_stateless(clone!(
+                #[strong]
+                sender,
+                move |_| {
+                    sender.input(Self::Input::ClearCacheRequested);
+                }
+            ))
+        };
         let cloned_root = root.clone();
         let about_action: RelmAction<AboutAction> = RelmAction::new_stateless(move |_| {
             let dialog = adw::AboutDialog::builder()
@@ -720,6 +1761,10 @@ impl Component for App {
         app.set_accelerators_for_action::<PathParentAction>(&["<Alt>Up"]);
         app.set_accelerators_for_action::<PathUndoAction>(&["<Alt>Left"]);
         app.set_accelerators_for_action::<PathRedoAction>(&["<Alt>Right"]);
+        app.set_accelerators_for_action::<SearchAction>(&["<Ctrl><Shift>F"]);
+        app.set_accelerators_for_action::<FilterAction>(&["<Ctrl>F"]);
+        app.set_accelerators_for_action::<UndoAction>(&["<Ctrl>Z"]);
+        app.set_accelerators_for_action::<PropertiesAction>(&["<Alt>Return"]);
 
         let mut file_listings_view_group = RelmActionGroup::<FileListingsViewGroup>::new();
         file_listings_view_group.add_action(rename_action);
@@ -727,6 +1772,19 @@ impl Component for App {
         file_listings_view_group.add_action(copy_action);
         file_listings_view_group.add_action(save_copy_action);
         file_listings_view_group.add_action(delete_action);
+        file_listings_view_group.add_action(select_all_action);
+        file_listings_view_group.add_action(search_action);
+        file_listings_view_group.add_action(disk_usage_action);
+        file_listings_view_group.add_action(filter_action);
+        file_listings_view_group.add_action(trash_action);
+        file_listings_view_group.add_action(undo_action);
+        file_listings_view_group.add_action(properties_action);
+        file_listings_view_group.add_action(compact_list_action);
+        file_listings_view_group.add_action(preview_pane_action);
+        file_listings_view_group.add_action(sort_by_name_action);
+        file_listings_view_group.add_action(sort_by_size_action);
+        file_listings_view_group.add_action(sort_by_modified_action);
+        file_listings_view_group.add_action(sort_by_type_action);
         file_listings_view_group.add_action(path_refresh_action);
         file_listings_view_group.add_action(path_parent_action);
         file_listings_view_group.add_action(path_undo_action);
@@ -735,10 +1793,18 @@ impl Component for App {
 
         let mut main_menu_group = RelmActionGroup::<MainWindowMenuViewGroup>::new();
         main_menu_group.add_action(remotes_refresh_action);
+        main_menu_group.add_action(remote_add_action);
         main_menu_group.add_action(remotes_configure_action);
+        main_menu_group.add_action(crypt_wizard_action);
+        main_menu_group.add_action(use_trash_action);
+        main_menu_group.add_action(clear_cache_action);
         main_menu_group.add_action(about_action);
         main_menu_group.register_for_widget(&widgets.window);
 
+        JOBS.subscribe(sender.input_sender(), |_| AppInMsg::JobsChanged);
+        QUEUE_PAUSED.subscribe(sender.input_sender(), |paused| {
+            AppInMsg::QueuePausedChanged(*paused)
+        });
         FILE_PICKER_MODE.subscribe(sender.input_sender(), |new_mode| {
             AppInMsg::FilePickerModeChange(new_mode.clone())
         });
@@ -748,19 +1814,39 @@ impl Component for App {
 
     fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
         match message {
-            Self::Input::PasswordReceived(password) => {
+            Self::Input::PasswordReceived(password, remember_password) => {
                 let rclone_config_file = std::env::var("RCLONE_CONFIG_FILE").ok();
-                if let Ok(client) = RcloneClient::new(Some(password), rclone_config_file) {
-                    sender.input(Self::Input::ClientConnected(client));
-                } else {
-                    gtk::AlertDialog::builder()
-                        .modal(true)
-                        .message("Password incorrect")
-                        .detail("Please try again.")
-                        .build()
-                        .show(Some(root));
-                    self.unlock_widget
-                        .emit(UnlockViewInMsg::PasswordFocusRequested);
+                match RcloneClient::new(Some(password.clone()), rclone_config_file.clone()) {
+                    Ok(client) => {
+                        // The keyring can take a while to answer, and may not be there at all
+                        sender.spawn_command(move |_| {
+                            if !keyring::is_available() {
+                                return;
+                            }
+                            if !remember_password {
+                                keyring::forget_password(&rclone_config_file);
+                            } else if let Err(err) =
+                                keyring::store_password(&rclone_config_file, &password)
+                            {
+                                println!("Warning: failed to store password in keyring. {}", err);
+                            }
+                        });
+                        Self::connect_backend(&sender, client, &self.config);
+                    }
+                    Err(error) => {
+                        let detail = match error {
+                            RcloneError::ConfigLocked(_) => String::from("Please try again."),
+                            _ => error.description(),
+                        };
+                        gtk::AlertDialog::builder()
+                            .modal(true)
+                            .message("Password incorrect")
+                            .detail(detail)
+                            .build()
+                            .show(Some(root));
+                        self.unlock_widget
+                            .emit(UnlockViewInMsg::PasswordFocusRequested);
+                    }
                 }
             }
             Self::Input::ClientConnectionFailed => {
@@ -772,35 +1858,96 @@ impl Component for App {
                 self.client = Some(client);
                 self.refresh_remotes(&sender);
 
-                let mut config = AppConfig::load();
-
-                if !config.skip_overwrite_disclaimer {
-                    let dialog = gtk::AlertDialog::builder()
-                        .buttons(["OK", "Don't show again"])
-                        .message("Just so you know")
-                        .detail("Rclone does not warn before overwriting files. If you upload, move or copy a file over another, Rclone will replace it without confirmation.")
-                        .default_button(0)
+                let interrupted_job_count = get_interrupted_jobs().len();
+                if interrupted_job_count > 0 {
+                    let alert = adw::AlertDialog::builder()
+                        .heading("Resume interrupted jobs?")
+                        .body(format!(
+                            "{} job(s) were still running when Rclone Shuttle closed last time.",
+                            interrupted_job_count
+                        ))
                         .build();
-
-                    dialog.choose(Some(root), Some(&Cancellable::default()), move |selection| {
-                        if let Ok(1) = selection {
-                            config.skip_overwrite_disclaimer = true;
-                            config.save();
+                    alert.add_response("later", "Not now");
+                    alert.add_response("resume", "Resume");
+                    alert.set_response_appearance("resume", adw::ResponseAppearance::Suggested);
+                    alert.connect_response(Some("resume"), clone!(
+                        #[strong]
+                        sender,
+                        move |_, _| {
+                            sender.input(Self::Input::InterruptedJobsResumeRequested);
                         }
-                    });
+                    ));
+                    alert.present(Some(root));
                 }
             }
             Self::Input::RemotesRefreshRequested => {
                 self.refresh_remotes(&sender);
             }
+            Self::Input::RemoteCreationRequested => {
+                self.load_remote_editor(&sender, None);
+            }
             Self::Input::RemotesConfigurationRequested => {
-                let dialog = gtk::AlertDialog::builder()
-                    .modal(true)
-                    .buttons(["Got it"])
-                    .message("Terminal time!")
-                    .detail("The Rclone CLI can be used to configure a wide variety of cloud storage providers and protocols. Open up your favorite terminal and enter 'rclone config' to add, edit or delete remotes.")
+                let remote = self.path.remote().map(|r| r.trim_end_matches(':').to_owned());
+                self.load_remote_editor(&sender, remote);
+            }
+            Self::Input::RemoteEditSubmitted(edit) => {
+                if let Some(client) = self.client.clone() {
+                    Self::change_remote_config(&sender, None, move || match &edit.original_name {
+                        Some(original_name) => {
+                            let updated = match edit.values.is_empty() {
+                                true => Ok(()),
+                                false => client.update_remote(original_name, &edit.values),
+                            };
+                            updated.and_then(|_| match *original_name != edit.name {
+                                true => client.rename_remote(original_name, &edit.name),
+                                false => Ok(()),
+                            })
+                        }
+                        None => client.create_remote(&edit.name, &edit.provider, &edit.values, false),
+                    });
+                }
+            }
+            Self::Input::RemoteDeletionRequested(remote) => {
+                let alert = adw::AlertDialog::builder()
+                    .heading(format!("Deleting remote '{}'", remote))
+                    .body("Are you sure? This removes the remote from the rclone config. The files on it are left alone.")
                     .build();
-                dialog.show(Some(root));
+                alert.add_response("delete", "Delete");
+                alert.add_response("cancel", "Cancel");
+                alert.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+                alert.connect_response(Some("delete"), move |_, _| {
+                    sender.input(Self::Input::RemoteDeletionConfirmed(remote.clone()));
+                });
+                alert.present(Some(root));
+            }
+            Self::Input::RemoteDeletionConfirmed(remote) => {
+                if let Some(client) = self.client.clone() {
+                    Self::change_remote_config(&sender, None, move || client.delete_remote(&remote));
+                }
+            }
+            Self::Input::CryptWizardRequested => {
+                let remotes = (0..self.remotes_view_wrapper.len())
+                    .filter_map(|i| self.remotes_view_wrapper.get(i))
+                    .map(|remote| remote.name.clone())
+                    .collect();
+                let dialog = CryptWizardDialog::builder()
+                    .launch(CryptWizardDialogInit {
+                        remotes,
+                        path: self.path.clone(),
+                    })
+                    .forward(sender.input_sender(), |msg| match msg {
+                        CryptWizardDialogOutMsg::Submitted(setup) => AppInMsg::CryptSetupSubmitted(setup),
+                    });
+                dialog.widget().present(Some(root));
+                self.active_crypt_wizard = Some(dialog);
+            }
+            Self::Input::CryptSetupSubmitted(setup) => {
+                if let Some(client) = self.client.clone() {
+                    let remote = format!("{}:", setup.name);
+                    Self::change_remote_config(&sender, Some(remote), move || {
+                        client.create_remote(&setup.name, "crypt", &setup.values(), false)
+                    });
+                }
             }
             Self::Input::RemoteSelectionChanged(row) => {
                 self.reveal_files_on_small_screens = true;
@@ -838,7 +1985,13 @@ impl Component for App {
                 sender.input(Self::Input::PathChanged(path));
             }
             Self::Input::PathChanged(path) => {
-                self.selected_file_listing_copy = None;
+                self.selected_file_listings.clear();
+                self.update_preview();
+                self.file_listings.clear();
+                // The filter belongs to the folder it was typed in, and outlives reloads of it
+                if path != self.path {
+                    self.filter_bar.widget().set_search_mode(false);
+                }
                 self.path = path.clone();
                 self.file_listing_view_state = FileListingViewState::Loading;
                 self.file_listing_view_wrapper.clear();
@@ -862,72 +2015,239 @@ impl Component for App {
                 let path = path.clone();
                 sender.spawn_oneshot_command(move || match client.as_ref().unwrap().ls(&path) {
                     Ok(listings) => AppOutCmd::FileListingAvailable(listings),
-                    Err(error_str) => AppOutCmd::CommandFailed(error_str),
+                    Err(error) => AppOutCmd::CommandFailed(error),
                 });
             }
-            Self::Input::OpenRequested(remote_path) => {
-                let client = self.client.clone();
-
-                let cache_dir = cache_dir()
-                    .expect("Cannot find cache folder")
-                    .join("rclone-shuttle");
-                std::fs::create_dir_all(&cache_dir)
-                    .expect("Could not make temporary download folder");
-                let tmp_filename = format!(
-                    "tmp{}-{}",
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis(),
-                    remote_path.filename()
-                );
-                let tmp_local_path =
-                    RclonePath::from(&cache_dir.into_os_string().into_string().unwrap())
-                        .join(&tmp_filename);
-
+            Self::Input::OpenRequested(listing) => {
+                if let Some(local_path) = self.file_cache.lookup(&listing) {
+                    let local_path =
+                        RclonePath::from(&local_path.into_os_string().into_string().unwrap());
+                    open::that_in_background(OsString::from(&local_path.to_string()));
+                    self.watch_opened_file(&sender, &listing.path, &local_path);
+                    return;
+                }
+                if let Some(edited_path) = self.file_cache.edited_copy(&listing) {
+                    // The download goes next to it, so the edits aren't lost either way
+                    self.offer_edited_copy(&sender, &listing, &edited_path);
+                }
+                let tmp_local_path = match self.file_cache.prepare(&listing) {
+                    Ok(path) => RclonePath::from(&path.into_os_string().into_string().unwrap()),
+                    Err(err) => {
+                        sender.input(Self::Input::TriggerGenericError(
+                            String::from("Could not make temporary download folder"),
+                            err.to_string(),
+                            false,
+                        ));
+                        return;
+                    }
+                };
                 let job = RcloneJob::new(RcloneJobType::Open {
-                    remote_path: remote_path.clone(),
-                    tmp_local_path: tmp_local_path.clone(),
+                    remote_path: listing.path,
+                    tmp_local_path,
                 });
-                let uuid = job.uuid;
-                JOBS.write().insert(job.uuid, job);
-
+                self.enqueue_job(&sender, job);
+            }
+            Self::Input::EditedCopyOpenRequested(remote_path, local_path) => {
+                open::that_in_background(OsString::from(&local_path.to_string()));
+                self.watch_opened_file(&sender, &remote_path, &local_path);
+            }
+            Self::Input::ClearCacheRequested => {
+                let usage = self.file_cache.usage();
+                let edits = match self.file_cache.edited_count() {
+                    0 => String::from("edits that weren't uploaded are lost"),
+                    1 => String::from("the edits in 1 file that weren't uploaded are lost"),
+                    count => format!("the edits in {} files that weren't uploaded are lost", count),
+                };
+                let alert = adw::AlertDialog::builder()
+                    .heading("Clear cache?")
+                    .body(match usage {
+                        0 => String::from("The cache of opened files is empty."),
+                        bytes => format!(
+                            "Opened files take up {}B on this computer. Clearing them means they are downloaded again the next time you open them, and {}.",
+                            size_format::SizeFormatterSI::new(bytes),
+                            edits
+                        ),
+                    })
+                    .build();
+                alert.add_response("cancel", "Cancel");
+                if usage > 0 {
+                    alert.add_response("clear", "Clear");
+                    alert.set_response_appearance("clear", adw::ResponseAppearance::Destructive);
+                }
+                alert.connect_response(Some("clear"), move |_, _| {
+                    sender.input(Self::Input::ClearCacheConfirmed);
+                });
+                alert.present(Some(root));
+            }
+            Self::Input::ClearCacheConfirmed => {
+                self.opened_files.clear();
+                if let Err(err) = self.file_cache.clear() {
+                    sender.input(Self::Input::TriggerGenericError(
+                        String::from("Could not clear the cache"),
+                        err.to_string(),
+                        false,
+                    ));
+                }
+            }
+            Self::Input::OpenedFileUploadRequested(local_path) => {
+                let Some(client) = self.client.clone() else {
+                    return;
+                };
+                let Some(opened) = self
+                    .opened_files
+                    .iter()
+                    .find(|opened| opened.local_path == local_path)
+                else {
+                    return;
+                };
+                let remote_path = opened.remote_path.clone();
                 sender.spawn_oneshot_command(move || {
-                    let result = match client.as_ref().unwrap().copy(&remote_path, &tmp_local_path)
-                    {
-                        Ok(()) => AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Finished),
-                        Err(error_str) => {
-                            AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Failed(error_str))
-                        }
-                    };
-                    result
-                })
+                    AppOutCmd::OpenedFileRemoteChecked(
+                        local_path,
+                        remote_mod_time(client.as_ref(), &remote_path),
+                    )
+                });
+            }
+            Self::Input::OpenedFileUploadConfirmed(local_path) => {
+                if let Some(opened) = self
+                    .opened_files
+                    .iter()
+                    .find(|opened| opened.local_path == local_path)
+                {
+                    let mut job = RcloneJob::new(RcloneJobType::Upload {
+                        local_path,
+                        remote_path: opened.remote_path.clone(),
+                    });
+                    job.overwrites = true;
+                    self.enqueue_job(&sender, job);
+                }
             }
             Self::Input::UploadRequested(local_path, remote_path) => {
-                let client = self.client.clone();
-                let job = RcloneJob::new(RcloneJobType::Upload {
-                    local_path: local_path.clone(),
-                    remote_path: remote_path.clone(),
-                });
-                let uuid = job.uuid;
-                JOBS.write().insert(job.uuid, job);
+                sender.input(Self::Input::TransfersRequested(vec![RcloneJobType::Upload {
+                    local_path,
+                    remote_path,
+                }]));
+            }
+            Self::Input::TransfersRequested(items) => {
+                let Some(client) = self.client.clone() else {
+                    return;
+                };
                 sender.spawn_oneshot_command(move || {
-                    let result = match client.as_ref().unwrap().copy(&local_path, &remote_path) {
-                        Ok(()) => AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Finished),
-                        Err(error_str) => {
-                            AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Failed(error_str))
-                        }
-                    };
-                    result
-                })
+                    let check = ConflictCheck::run(client.as_ref(), &items);
+                    let sizes = upload_sizes(&items);
+                    AppOutCmd::TransfersChecked(items, check, sizes)
+                });
+            }
+            Self::Input::TransfersConfirmed(items, check) => {
+                if check.conflicts.is_empty() {
+                    if let Some(job_type) = RcloneJobType::grouped(items) {
+                        self.enqueue_job(&sender, RcloneJob::new(job_type));
+                    }
+                    return;
+                }
+                self.pending_transfers
+                    .push_back(PendingTransfers::new(items, check));
+                // Further requests wait until the one on screen is answered
+                if self.pending_transfers.len() == 1 {
+                    self.ask_about_conflict(&sender, root);
+                }
+            }
+            Self::Input::TransferConflictAnswered(resolution, apply_to_all) => {
+                if let Some(pending) = self.pending_transfers.front_mut() {
+                    pending.answer(resolution, apply_to_all);
+                }
+                self.finish_pending_transfers(&sender, root);
+            }
+            Self::Input::TransfersCancelled => {
+                self.pending_transfers.pop_front();
+                self.ask_about_conflict(&sender, root);
             }
             Self::Input::FileListingSelectionChanged => {
-                let position = self.file_listing_view_wrapper.selection_model.selected();
-                if let Some(item) = self.file_listing_view_wrapper.get(position) {
-                    let listing_copy = item.borrow().model.clone();
-                    self.selected_file_listing_copy = Some(listing_copy);
+                self.selected_file_listings = self.selected_listings();
+                self.update_preview();
+            }
+            Self::Input::SelectAllRequested => {
+                self.file_listing_view_wrapper.selection_model.select_all();
+            }
+            Self::Input::SearchRequested => {
+                let Some(client) = self.client.clone() else {
+                    return;
+                };
+                if self.path.remote().is_none() {
+                    return;
+                }
+                let dialog = SearchDialog::builder()
+                    .launch(SearchDialogInit {
+                        client,
+                        path: self.path.clone(),
+                    })
+                    .forward(sender.input_sender(), |msg| match msg {
+                        SearchDialogOutMsg::OpenRequested(listing) if listing.is_dir => {
+                            Self::Input::PathEntered(listing.path)
+                        }
+                        SearchDialogOutMsg::OpenRequested(listing) => {
+                            Self::Input::OpenRequested(listing)
+                        }
+                        SearchDialogOutMsg::RevealRequested(path) => {
+                            Self::Input::SearchHitRevealRequested(path)
+                        }
+                    });
+                dialog.widget().present(Some(root));
+                self.active_search = Some(dialog);
+            }
+            Self::Input::DiskUsageRequested => {
+                let Some(client) = self.client.clone() else {
+                    return;
+                };
+                if self.path.remote().is_none() {
+                    return;
+                }
+                let dialog = DiskUsageDialog::builder()
+                    .launch(DiskUsageDialogInit {
+                        client,
+                        path: self.path.clone(),
+                        trash_folder: match self.config.use_trash {
+                            true => Some(self.config.trash_folder.clone()),
+                            false => None,
+                        },
+                    })
+                    .forward(sender.input_sender(), |msg| match msg {
+                        DiskUsageDialogOutMsg::DeleteRequested(path, is_dir) => {
+                            Self::Input::DeleteConfirmed(vec![(path, is_dir)])
+                        }
+                    });
+                dialog.widget().present(Some(root));
+                self.active_disk_usage = Some(dialog);
+            }
+            Self::Input::SearchHitRevealRequested(path) => {
+                sender.input(Self::Input::PathEntered(path.resolve_to_parent()));
+                self.revealed_path = Some(path);
+            }
+            Self::Input::FilterToggled => {
+                self.filter_bar.emit(FilterBarInMsg::Toggled);
+            }
+            Self::Input::FilterChanged(filter) => {
+                if filter != self.filter {
+                    self.filter = filter;
+                    self.show_file_listings();
                 }
             }
+            Self::Input::SortRequested(key) => {
+                self.config.file_sort = self.config.file_sort.toggled(key);
+                self.config.save();
+                self.show_file_listings();
+            }
+            Self::Input::CompactListToggled(compact) => {
+                self.config.compact_file_list = compact;
+                self.config.save();
+                self.show_file_listings();
+            }
+            Self::Input::PreviewPaneToggled(show) => {
+                self.config.show_preview_pane = show;
+                self.config.save();
+                self.preview_pane.widget().set_visible(show);
+                self.update_preview();
+            }
             Self::Input::FileListingPositionActivated(position) => {
                 if let Some(item) = &self.file_listing_view_wrapper.get(position) {
                     let listing = &item.borrow().model;
@@ -936,31 +2256,31 @@ impl Component for App {
                         self.redoable_paths.clear();
                         sender.input(Self::Input::PathChanged(listing.path.clone()));
                     } else {
-                        sender.input(Self::Input::OpenRequested(listing.path.clone()));
+                        sender.input(Self::Input::OpenRequested(listing.clone()));
                     }
                 }
             }
             Self::Input::FileListingErrorDetailRequested => {
-                if let FileListingViewState::Error(error_str) = self.file_listing_view_state.clone()
-                {
+                if let FileListingViewState::Error(error) = self.file_listing_view_state.clone() {
                     gtk::AlertDialog::builder()
                         .modal(true)
-                        .message("Something went wrong")
-                        .detail(error_str)
+                        .message(error.title())
+                        .detail(error.message())
                         .build()
                         .show(Some(root));
                 }
             }
             Self::Input::FilesDropped(file_paths) => {
-                for file_path in file_paths {
+                let items = file_paths.into_iter().map(|file_path| {
                     let local_path =
                         RclonePath::from(&file_path.into_os_string().into_string().unwrap());
                     let local_filename = local_path.filename();
-                    sender.input(Self::Input::UploadRequested(
+                    RcloneJobType::Upload {
                         local_path,
-                        self.path.clone().join(&local_filename),
-                    ));
-                }
+                        remote_path: self.path.clone().join(&local_filename),
+                    }
+                }).collect();
+                sender.input(Self::Input::TransfersRequested(items));
             }
             Self::Input::ReturnToSelectModeRequested => {
                 *FILE_PICKER_MODE.write() = FilePickerMode::Select;
@@ -997,108 +2317,63 @@ impl Component for App {
                 self.active_string_prompt = Some(dialog);
             }
             Self::Input::CreateFolderConfirmed(folder_name) => {
-                if let Some(client) = &self.client {
+                if let Some(client) = self.client.clone() {
                     let path = self.path.join(&folder_name);
-                    match client.mkdir(&path) {
-                        Ok(()) => {
-                            sender.input(Self::Input::PathRefreshRequested);
-                        }
-                        Err(MkdirError::NotAvailableHere) => {
-                            sender.input(Self::Input::TriggerGenericError(
-                                String::from("Cannot create empty folder"),
-                                String::from("This may be a technical limitation of your storage provider, typically with object storage like Amazon S3.\n\nTo persist the folder, upload a file to it before leaving."),
-                                false,
-                            ));
-                            if path != self.path {
-                                self.undoable_paths.push(self.path.clone());
-                                self.redoable_paths.clear();
-                            }
-                            sender.input(Self::Input::PathChanged(path.clone()));
-                        }
-                        Err(MkdirError::Generic(error_str)) => {
-                            sender.input(Self::Input::TriggerGenericError(
-                                String::from("Something went wrong"),
-                                error_str,
-                                false,
-                            ));
-                        }
-                    }
+                    sender.spawn_oneshot_command(move || {
+                        // mkdir also succeeds on a folder that is already there, which undoing
+                        // must not delete
+                        let is_new = matches!(client.stat(&path), Err(RcloneError::NotFound(_)));
+                        let result = client.mkdir(&path).map(|()| is_new);
+                        AppOutCmd::FolderCreated(path, result)
+                    });
                 }
             }
             Self::Input::MoveSelectionRequested => {
-                let position = self.file_listing_view_wrapper.selection_model.selected();
-                if let Some(item) = self.file_listing_view_wrapper.get(position) {
-                    let path = item.borrow().model.path.clone();
-                    *FILE_PICKER_MODE.write() = FilePickerMode::Move(path.clone());
+                let paths: Vec<RclonePath> =
+                    self.selected_listings().into_iter().map(|listing| listing.path).collect();
+                if !paths.is_empty() {
+                    *FILE_PICKER_MODE.write() = FilePickerMode::Move(paths);
                 }
             }
             Self::Input::CopySelectionRequested => {
-                let position = self.file_listing_view_wrapper.selection_model.selected();
-                if let Some(item) = self.file_listing_view_wrapper.get(position) {
-                    let path = item.borrow().model.path.clone();
-                    *FILE_PICKER_MODE.write() = FilePickerMode::Copy(path.clone());
+                let paths: Vec<RclonePath> =
+                    self.selected_listings().into_iter().map(|listing| listing.path).collect();
+                if !paths.is_empty() {
+                    *FILE_PICKER_MODE.write() = FilePickerMode::Copy(paths);
                 }
             }
             Self::Input::MoveTargetConfirmed => {
-                if let FilePickerMode::Move(path) = &FILE_PICKER_MODE.read().deref() {
-                    let source_path = path.clone();
-                    let target_path = self.path.join(&path.filename());
+                if let FilePickerMode::Move(paths) = &FILE_PICKER_MODE.read().deref() {
+                    let items = paths.iter().map(|path| RcloneJobType::Move {
+                        source_path: path.clone(),
+                        target_path: self.path.join(&path.filename()),
+                    }).collect();
                     relm4::spawn_local(async {
                         // Don't read and write in the same cycle to avoid deadlock
                         *FILE_PICKER_MODE.write() = FilePickerMode::Select;
                     });
 
-                    let client = self.client.clone();
-                    let job = RcloneJob::new(RcloneJobType::Move {
-                        source_path: source_path.clone(),
-                        target_path: target_path.clone(),
-                    });
-                    let uuid = job.uuid;
-                    JOBS.write().insert(job.uuid, job);
-                    sender.spawn_oneshot_command(move || {
-                        let result = match client.as_ref().unwrap().mv(&source_path, &target_path)
-                        {
-                            Ok(()) => AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Finished),
-                            Err(error_str) => {
-                                AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Failed(error_str))
-                            }
-                        };
-                        result
-                    })
+                    sender.input(Self::Input::TransfersRequested(items));
                 }
             }
             Self::Input::CopyTargetConfirmed => {
-                if let FilePickerMode::Copy(path) = &FILE_PICKER_MODE.read().deref() {
-                    let source_path = path.clone();
-                    let target_path = self.path.join(&path.filename());
+                if let FilePickerMode::Copy(paths) = &FILE_PICKER_MODE.read().deref() {
+                    let items = paths.iter().map(|path| RcloneJobType::Copy {
+                        source_path: path.clone(),
+                        target_path: self.path.join(&path.filename()),
+                    }).collect();
                     relm4::spawn_local(async {
                         // Don't read and write in the same cycle to avoid deadlock
                         *FILE_PICKER_MODE.write() = FilePickerMode::Select;
                     });
 
-                    let client = self.client.clone();
-                    let job = RcloneJob::new(RcloneJobType::Copy {
-                        source_path: source_path.clone(),
-                        target_path: target_path.clone(),
-                    });
-                    let uuid = job.uuid;
-                    JOBS.write().insert(job.uuid, job);
-                    sender.spawn_oneshot_command(move || {
-                        let result = match client.as_ref().unwrap().copy(&source_path, &target_path)
-                        {
-                            Ok(()) => AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Finished),
-                            Err(error_str) => {
-                                AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Failed(error_str))
-                            }
-                        };
-                        result
-                    })
+                    sender.input(Self::Input::TransfersRequested(items));
                 }
             }
             Self::Input::RenameSelectionRequested => {
-                let position = self.file_listing_view_wrapper.selection_model.selected();
-                if let Some(item) = self.file_listing_view_wrapper.get(position) {
-                    let path = item.borrow().model.path.clone();
+                // Renaming works on one item at a time
+                if let [listing] = self.selected_listings().as_slice() {
+                    let path = listing.path.clone();
                     let dialog = StringPromptDialog::builder().launch(StringPromptDialogInit {
                         title: format!("Rename '{}'", path.filename()),
                         prompt: String::from("Enter a new name to proceed."),
@@ -1112,54 +2387,156 @@ impl Component for App {
                 }
             }
             Self::Input::RenameConfirmed(path, new_filename) => {
-                let client = self.client.clone();
-                let job = RcloneJob::new(RcloneJobType::Rename(path.resolve_to_parent().join(&new_filename)));
-                let uuid = job.uuid;
-                JOBS.write().insert(job.uuid, job);
-                sender.spawn_oneshot_command(move || {
-                    let result = match client.as_ref().unwrap().rename(&path, &new_filename) {
-                        Ok(()) => AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Finished),
-                        Err(error_str) => {
-                            AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Failed(error_str))
-                        }
-                    };
-                    result
-                });
+                if new_filename == path.filename() {
+                    return;
+                }
+                // Renaming onto an existing name replaces it, so it is checked like a transfer
+                sender.input(Self::Input::TransfersRequested(vec![RcloneJobType::Rename {
+                    target_path: path.resolve_to_parent().join(&new_filename),
+                    source_path: path,
+                }]));
             }
             Self::Input::DeleteSelectionRequested => {
-                let position = self.file_listing_view_wrapper.selection_model.selected();
-                if let Some(item) = self.file_listing_view_wrapper.get(position) {
-                    let path = item.borrow().model.path.clone();
-                    let is_dir = item.borrow().model.is_dir;
-                    let alert = adw::AlertDialog::builder()
-                        .heading(format!("Deleting '{}'", path.filename()))
-                        .body(match is_dir {
+                let listings = self.selected_listings();
+                let to_trash =
+                    self.config.use_trash && !is_in_trash(&self.path, &self.config.trash_folder);
+                let (heading, body) = match listings.as_slice() {
+                    [] => return,
+                    [listing] if to_trash => (
+                        format!("Deleting '{}'", listing.name),
+                        String::from("It will be moved to the trash of this remote, from where it can be restored."),
+                    ),
+                    _ if to_trash => (
+                        format!("Deleting {} items", listings.len()),
+                        String::from("They will be moved to the trash of this remote, from where they can be restored."),
+                    ),
+                    [listing] => (
+                        format!("Deleting '{}'", listing.name),
+                        String::from(match listing.is_dir {
                             true => "Are you sure? This will permanently delete the entire folder.",
                             false => "Are you sure? This is permanent.",
-                        }).build();
-                    alert.add_response("delete", "Delete");
-                    alert.add_response("cancel", "Cancel");
-                    alert.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
-                    alert.connect_response(Some("delete"), move |_, _| {
-                        sender.input(Self::Input::DeleteConfirmed(path.clone(), is_dir));
+                        }),
+                    ),
+                    _ => {
+                        let folder_count = listings.iter().filter(|listing| listing.is_dir).count();
+                        (
+                            format!("Deleting {} items", listings.len()),
+                            match folder_count {
+                                0 => String::from("Are you sure? This is permanent."),
+                                _ => format!(
+                                    "Are you sure? This will permanently delete {} files and {} entire folders.",
+                                    listings.len() - folder_count,
+                                    folder_count
+                                ),
+                            },
+                        )
+                    }
+                };
+                let targets: Vec<(RclonePath, bool)> = listings
+                    .into_iter()
+                    .map(|listing| (listing.path, listing.is_dir))
+                    .collect();
+                let alert = adw::AlertDialog::builder().heading(heading).body(body).build();
+                alert.add_response("delete", match to_trash {
+                    true => "Move to Trash",
+                    false => "Delete",
+                });
+                alert.add_response("cancel", "Cancel");
+                alert.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+                alert.connect_response(Some("delete"), move |_, _| {
+                    sender.input(Self::Input::DeleteConfirmed(targets.clone()));
+                });
+                alert.present(Some(root));
+            }
+            Self::Input::DeleteConfirmed(targets) => {
+                let folder = &self.config.trash_folder;
+                let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+                let (mut deletions, mut trashings) = (vec![], vec![]);
+                for (path, is_dir) in targets {
+                    // What is already in the trash can only go for good
+                    match trash_path(&path, folder, &date) {
+                        Some(trash_path) if self.config.use_trash && !is_in_trash(&path, folder) => {
+                            trashings.push(RcloneJobType::Trash { path, trash_path })
+                        }
+                        _ => deletions.push(RcloneJobType::Delete { path, is_dir }),
+                    }
+                }
+                if let Some(job_type) = RcloneJobType::grouped(deletions) {
+                    self.enqueue_job(&sender, RcloneJob::new(job_type));
+                }
+                if let (false, Some(client)) = (trashings.is_empty(), self.client.clone()) {
+                    sender.spawn_oneshot_command(move || {
+                        let check = ConflictCheck::run(client.as_ref(), &trashings);
+                        AppOutCmd::TrashChecked(trashings, check)
                     });
-                    alert.present(Some(root));
                 }
             }
-            Self::Input::DeleteConfirmed(path, is_dir) => {
-                let client = self.client.clone();
-                let job = RcloneJob::new(RcloneJobType::Delete(path.clone()));
-                let uuid = job.uuid;
-                JOBS.write().insert(job.uuid, job);
-                sender.spawn_oneshot_command(move || {
-                    let result = match client.as_ref().unwrap().rm(&path, is_dir) {
-                        Ok(()) => AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Finished),
-                        Err(error_str) => {
-                            AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Failed(error_str))
+            Self::Input::TrashRequested => {
+                let (Some(client), Some(remote)) = (self.client.clone(), self.path.remote()) else {
+                    return;
+                };
+                let dialog = TrashDialog::builder()
+                    .launch(TrashDialogInit {
+                        client,
+                        remote,
+                        folder: self.config.trash_folder.clone(),
+                    })
+                    .forward(sender.input_sender(), |msg| match msg {
+                        TrashDialogOutMsg::RestoreRequested(item) => {
+                            Self::Input::TransfersRequested(vec![RcloneJobType::Move {
+                                source_path: item.listing.path,
+                                target_path: item.original_path,
+                            }])
                         }
-                    };
-                    result
-                })
+                        TrashDialogOutMsg::EmptyRequested(path) => {
+                            Self::Input::TrashEmptyConfirmed(path)
+                        }
+                    });
+                dialog.widget().present(Some(root));
+                self.active_trash = Some(dialog);
+            }
+            Self::Input::TrashEmptyConfirmed(path) => {
+                let job = RcloneJob::new(RcloneJobType::Delete { path, is_dir: true });
+                self.enqueue_job(&sender, job);
+            }
+            Self::Input::PropertiesRequested => {
+                // Properties describe one item at a time
+                let selected_listings = self.selected_listings();
+                let (Some(client), [listing]) = (self.client.clone(), selected_listings.as_slice())
+                else {
+                    return;
+                };
+                let dialog = PropertiesDialog::builder()
+                    .launch(PropertiesDialogInit {
+                        client,
+                        listing: listing.clone(),
+                    })
+                    .detach();
+                dialog.widget().present(Some(root));
+                self.active_properties = Some(dialog);
+            }
+            Self::Input::UndoRequested => {
+                let Some(entry) = self.undo_stack.pop() else {
+                    self.show_toast(String::from("Nothing to undo"));
+                    return;
+                };
+                match entry.inverse {
+                    Some(inverse) => {
+                        self.show_toast(format!("Undoing \"{}\"", entry.description));
+                        let mut job = RcloneJob::new(inverse);
+                        job.undoes = Some(entry.description);
+                        self.enqueue_job(&sender, job);
+                    }
+                    // Dropped all the same, so that earlier operations can still be undone
+                    None => self.show_toast(format!(
+                        "\"{}\" can't be undone",
+                        entry.description
+                    )),
+                }
+            }
+            Self::Input::UseTrashToggled(use_trash) => {
+                self.config.use_trash = use_trash;
+                self.config.save();
             }
             Self::Input::TriggerGenericError(error_title, error_description, fatal) => {
                 let alert = gtk::AlertDialog::builder()
@@ -1180,45 +2557,78 @@ impl Component for App {
                 _ => {
                     self.file_listing_view_wrapper
                         .selection_model
-                        .set_selected(GTK_INVALID_LIST_POSITION);
+                        .unselect_all();
                 }
             },
+            Self::Input::DownloadRequested if self.selected_file_listings.len() > 1 => {
+                let dialog = OpenDialog::builder().transient_for_native(root).launch(OpenDialogSettings {
+                    folder_mode: true,
+                    accept_label: String::from("Download here"),
+                    ..OpenDialogSettings::default()
+                }).forward(sender.input_sender(), |response| match response {
+                    OpenDialogResponse::Accept(path) => Self::Input::DownloadFolderConfirmed(RclonePath::from(&path.into_os_string().into_string().unwrap())),
+                    OpenDialogResponse::Cancel => Self::Input::NoOperation,
+                });
+                dialog.emit(OpenDialogMsg::Open);
+                self.download_folder_dialog = Some(dialog);
+            }
             Self::Input::DownloadRequested => {
                 let dialog = SaveDialog::builder().transient_for_native(root).launch(SaveDialogSettings::default()).forward(sender.input_sender(), |response| match response {
                     SaveDialogResponse::Accept(path) => Self::Input::DownloadPathConfirmed(RclonePath::from(&path.into_os_string().into_string().unwrap())),
                     SaveDialogResponse::Cancel => Self::Input::NoOperation,
                 });
-                let position = self.file_listing_view_wrapper.selection_model.selected();
-                if let Some(item) = self.file_listing_view_wrapper.get(position) {
-                    let selected_remote_item_path = item.borrow().model.path.clone();
-                    dialog.emit(SaveDialogMsg::SaveAs(selected_remote_item_path.filename()));
+                if let Some(listing) = self.selected_listings().first() {
+                    dialog.emit(SaveDialogMsg::SaveAs(listing.path.filename()));
 
                     self.save_copy_dialog = Some(dialog);
                 }
             },
             Self::Input::DownloadPathConfirmed(local_path) => {
-                let position = self.file_listing_view_wrapper.selection_model.selected();
-                if let Some(item) = self.file_listing_view_wrapper.get(position) {
-                    let remote_path = item.borrow().model.path.clone();
-
-                    let client = self.client.clone();
+                if let Some(listing) = self.selected_listings().first() {
                     let job = RcloneJob::new(RcloneJobType::Download {
-                        remote_path: remote_path.clone(),
-                        local_path: local_path.clone(),
-                    });
-                    let uuid = job.uuid;
-                    JOBS.write().insert(job.uuid, job);
-                    sender.spawn_oneshot_command(move || {
-                        let result = match client.as_ref().unwrap().copy(&remote_path, &local_path) {
-                            Ok(()) => AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Finished),
-                            Err(error_str) => {
-                                AppOutCmd::JobUpdated(uuid, RcloneJobStatus::Failed(error_str))
-                            }
-                        };
-                        result
+                        remote_path: listing.path.clone(),
+                        local_path,
                     });
+                    self.enqueue_job(&sender, job);
+                }
+            },
+            Self::Input::DownloadFolderConfirmed(local_folder) => {
+                let items = self.selected_listings().into_iter().map(|listing| RcloneJobType::Download {
+                    local_path: local_folder.join(&listing.path.filename()),
+                    remote_path: listing.path,
+                }).collect();
+                if let Some(job_type) = RcloneJobType::grouped(items) {
+                    self.enqueue_job(&sender, RcloneJob::new(job_type));
                 }
             },
+            Self::Input::JobRetryRequested(uuid) => {
+                self.retry_job(&sender, uuid);
+            }
+            Self::Input::JobsChanged => {
+                let serialized_jobs = serialize_jobs(&JOBS.read());
+                if serialized_jobs != self.persisted_jobs {
+                    save_jobs(&serialized_jobs);
+                    self.persisted_jobs = serialized_jobs;
+                }
+            }
+            Self::Input::InterruptedJobsResumeRequested => {
+                for job in get_interrupted_jobs() {
+                    self.retry_job(&sender, job.uuid);
+                }
+            }
+            Self::Input::QueuePausedChanged(paused) => {
+                if !paused {
+                    self.schedule_jobs(&sender);
+                } else if self.config.suspend_running_jobs_on_pause {
+                    let ongoing_jobs = get_ongoing_jobs();
+                    let mut jobs = JOBS.write();
+                    for uuid in ongoing_jobs.iter().map(|j| j.uuid) {
+                        if let Some(job) = jobs.get_mut(&uuid) {
+                            job.suspend();
+                        }
+                    }
+                }
+            }
             Self::Input::RevealSidebar => {
                 self.reveal_files_on_small_screens = false;
             }
@@ -1233,57 +2643,693 @@ impl Component for App {
         &mut self,
         message: Self::CommandOutput,
         sender: ComponentSender<Self>,
-        _root: &Self::Root,
+        root: &Self::Root,
     ) {
         match message {
             AppOutCmd::FileListingAvailable(listings) => {
-                self.file_listing_view_wrapper.clear();
-                let mut listings_copy = listings.clone();
-                listings_copy.sort_by(|a, b| {
-                    if a.is_dir && !b.is_dir {
-                        return std::cmp::Ordering::Less;
+                self.filter_bar
+                    .emit(FilterBarInMsg::MimeTypesChanged(file_mime_types(&listings)));
+                self.file_listings = listings;
+                self.show_file_listings();
+                self.file_listing_view_state = FileListingViewState::Loaded;
+                if let Some(path) = self.revealed_path.take() {
+                    self.reveal_file_listing(&path);
+                }
+            }
+            AppOutCmd::CommandFailed(error) => {
+                self.revealed_path = None;
+                self.file_listing_view_state = FileListingViewState::Error(error);
+            }
+            AppOutCmd::FolderCreated(path, result) => match result {
+                Ok(is_new) => {
+                    if is_new {
+                        self.remember_for_undo(UndoEntry::folder_created(&path));
                     }
-                    if !a.is_dir && b.is_dir {
-                        return std::cmp::Ordering::Greater;
+                    sender.input(Self::Input::PathRefreshRequested);
+                }
+                Err(MkdirError::NotAvailableHere) => {
+                    sender.input(Self::Input::TriggerGenericError(
+                        String::from("Cannot create empty folder"),
+                        String::from("This may be a technical limitation of your storage provider, typically with object storage like Amazon S3.\n\nTo persist the folder, upload a file to it before leaving."),
+                        false,
+                    ));
+                    if path != self.path {
+                        self.undoable_paths.push(self.path.clone());
+                        self.redoable_paths.clear();
                     }
-                    a.name.cmp(&b.name)
-                });
-
-                for (index, listing) in listings_copy.into_iter().enumerate() {
-                    let is_dir = listing.is_dir;
-                    self.file_listing_view_wrapper
-                        .append(FileListingView::new(listing));
-                    if index == 0 && !is_dir {
-                        if let FilePickerMode::Copy(_) = &FILE_PICKER_MODE.read().deref() {
-                            // Avoid selecting grayed out files
-                            self.file_listing_view_wrapper
-                                .selection_model
-                                .set_selected(GTK_INVALID_LIST_POSITION);
-                        }
+                    sender.input(Self::Input::PathChanged(path.clone()));
+                }
+                Err(MkdirError::Generic(error)) => {
+                    sender.input(Self::Input::TriggerGenericError(
+                        String::from(error.title()),
+                        error.description(),
+                        false,
+                    ));
+                }
+            },
+            AppOutCmd::BackendConnected(client) => {
+                sender.input(AppInMsg::ClientConnected(client));
+            }
+            AppOutCmd::RememberedPasswordTried(keyring_available, client) => {
+                self.unlock_widget
+                    .emit(UnlockViewInMsg::KeyringChecked(keyring_available));
+                match client {
+                    Some(client) => Self::connect_backend(&sender, client, &self.config),
+                    None => {
+                        self.requires_password = true;
+                        self.unlock_widget
+                            .emit(UnlockViewInMsg::PasswordFocusRequested);
                     }
                 }
-                self.file_listing_view_state = FileListingViewState::Loaded;
             }
-            AppOutCmd::CommandFailed(error_str) => {
-                self.file_listing_view_state = FileListingViewState::Error(error_str.clone());
+            AppOutCmd::JobFailed(uuid, error) => {
+                let status = RcloneJobStatus::Failed(error.to_string());
+                self.update_cmd(AppOutCmd::JobUpdated(uuid, status), sender.clone(), root);
+                // Decided on the kind, as the message can contain anything, file names included
+                if !error.is_transient() {
+                    return;
+                }
+                if let Some(job) = JOBS.write().get_mut(&uuid) {
+                    if let Some(delay) = self.config.auto_retry_delay(job.history.len() as u32) {
+                        job.retry_scheduled = true;
+                        sender.spawn_oneshot_command(move || {
+                            std::thread::sleep(delay);
+                            AppOutCmd::JobRetryDue(uuid)
+                        });
+                    }
+                }
             }
             AppOutCmd::JobUpdated(uuid, status) => {
+                if status != RcloneJobStatus::Ongoing {
+                    JOB_PROGRESS.write().remove(&uuid);
+                }
                 if let Some(job) = JOBS.write().get_mut(&uuid) {
                     job.set_status(status.clone());
 
-                    if let RcloneJobType::Open { tmp_local_path, .. } = &job.r#type {
-                        open::that_in_background(OsString::from(&tmp_local_path.to_string()));
+                    if job.suspended && status == RcloneJobStatus::Cancelled {
+                        // Stopped by pausing the queue rather than by the user
+                        job.requeue_suspended();
+                    }
+
+                    if let RcloneJobType::Open {
+                        remote_path,
+                        tmp_local_path,
+                    } = &job.r#type
+                    {
+                        if status == RcloneJobStatus::Finished {
+                            self.file_cache.complete(
+                                Path::new(&tmp_local_path.to_string()),
+                                self.config.cache_size_limit(),
+                            );
+                            open::that_in_background(OsString::from(&tmp_local_path.to_string()));
+                            self.watch_opened_file(&sender, remote_path, tmp_local_path);
+                        }
                     } else if status != RcloneJobStatus::Ongoing {
+                        if let RcloneJobType::Upload {
+                            local_path,
+                            remote_path,
+                        } = &job.r#type
+                        {
+                            if status == RcloneJobStatus::Finished {
+                                self.file_cache
+                                    .mark_uploaded(Path::new(&local_path.to_string()));
+                                self.sync_opened_file(&sender, local_path, remote_path);
+                            }
+                        }
+                        if status == RcloneJobStatus::Finished {
+                            self.offer_trash_undo(&sender, &job.r#type);
+                            self.refresh_quotas(&job.r#type.changed_remotes());
+                            if let (None, true, Some(client)) = (
+                                &job.undoes,
+                                job.r#type.changes_remote(),
+                                self.client.clone(),
+                            ) {
+                                let job = job.clone();
+                                sender.spawn_oneshot_command(move || {
+                                    AppOutCmd::UndoRecorded(UndoEntry::of_job(
+                                        client.as_ref(),
+                                        &job,
+                                    ))
+                                });
+                            }
+                        }
                         sender.input(Self::Input::PathChanged(self.path.clone()));
                     }
                 }
+                self.schedule_jobs(&sender);
+            }
+            AppOutCmd::OpenedFileWatched(opened_file) => {
+                let was_idle = self.opened_files.is_empty();
+                self.opened_files
+                    .retain(|opened| opened.local_path != opened_file.local_path);
+                self.opened_files.push(opened_file);
+                if was_idle {
+                    sender.spawn_oneshot_command(|| {
+                        std::thread::sleep(OPENED_FILES_POLL_INTERVAL);
+                        AppOutCmd::OpenedFilesPollDue
+                    });
+                }
+            }
+            AppOutCmd::OpenedFilesPollDue => {
+                // Copies that were cleaned up can't be edited anymore
+                self.opened_files
+                    .retain(|opened| opened.current_local_mod_time().is_some());
+                for opened in self.opened_files.iter_mut() {
+                    let Some(local_mod_time) = opened.current_local_mod_time() else {
+                        continue;
+                    };
+                    if opened.is_modified(local_mod_time) {
+                        // Asks once per save, even if the answer is "not now"
+                        opened.local_mod_time = Some(local_mod_time);
+                        Self::offer_upload(&sender, root, opened);
+                    }
+                }
+                if !self.opened_files.is_empty() {
+                    sender.spawn_oneshot_command(|| {
+                        std::thread::sleep(OPENED_FILES_POLL_INTERVAL);
+                        AppOutCmd::OpenedFilesPollDue
+                    });
+                }
+            }
+            AppOutCmd::OpenedFileRemoteChecked(local_path, current_mod_time) => {
+                let Some(opened) = self
+                    .opened_files
+                    .iter()
+                    .find(|opened| opened.local_path == local_path)
+                else {
+                    return;
+                };
+                if !opened.has_remote_conflict(current_mod_time.as_deref()) {
+                    sender.input(AppInMsg::OpenedFileUploadConfirmed(local_path));
+                    return;
+                }
+                let alert = adw::AlertDialog::builder()
+                    .heading("The remote file has changed")
+                    .body(format!(
+                        "{} was changed or removed on the remote after you opened it. Uploading your edits will overwrite those changes.",
+                        opened.remote_path
+                    ))
+                    .build();
+                alert.add_response("cancel", "Cancel");
+                alert.add_response("overwrite", "Overwrite");
+                alert.set_response_appearance("overwrite", adw::ResponseAppearance::Destructive);
+                alert.connect_response(Some("overwrite"), move |_, _| {
+                    sender.input(AppInMsg::OpenedFileUploadConfirmed(local_path.clone()));
+                });
+                alert.present(Some(root));
+            }
+            AppOutCmd::TransfersChecked(items, Ok(check), sizes) => {
+                let shortage = sizes.into_iter().find_map(|(remote, bytes)| {
+                    let free = self.remote_quota(&remote)?.free?;
+                    (bytes > free).then_some((remote, bytes, free))
+                });
+                let Some((remote, bytes, free)) = shortage else {
+                    sender.input(AppInMsg::TransfersConfirmed(items, check));
+                    return;
+                };
+                let alert = adw::AlertDialog::builder()
+                    .heading(format!("Not enough space on {}", remote))
+                    .body(format!(
+                        "The upload adds up to {}B, but {} only has {}B free. It will likely fail partway.",
+                        size_format::SizeFormatterSI::new(bytes),
+                        remote,
+                        size_format::SizeFormatterSI::new(free)
+                    ))
+                    .build();
+                alert.add_response("cancel", "Cancel");
+                alert.add_response("upload", "Upload Anyway");
+                alert.set_response_appearance("upload", adw::ResponseAppearance::Destructive);
+                alert.connect_response(Some("upload"), move |_, _| {
+                    sender.input(AppInMsg::TransfersConfirmed(items.clone(), check.clone()));
+                });
+                alert.present(Some(root));
+            }
+            AppOutCmd::UndoRecorded(entry) => self.remember_for_undo(entry),
+            AppOutCmd::RemoteEditorLoaded(Ok(init)) => {
+                self.open_remote_editor(&sender, root, init);
+            }
+            AppOutCmd::RemoteConfigChanged(outcome, selected_remote) => {
+                self.refresh_remotes(&sender);
+                match (outcome, selected_remote) {
+                    (Err(error), _) => {
+                        sender.input(AppInMsg::TriggerGenericError(
+                            String::from(error.title()),
+                            error.description(),
+                            false,
+                        ));
+                    }
+                    (Ok(()), Some(remote)) => self.select_remote(&sender, &remote),
+                    (Ok(()), None) => {}
+                }
+            }
+            AppOutCmd::TrashChecked(items, Ok(check)) => {
+                // Something deleted twice on the same day is kept twice
+                let resolutions = vec![ConflictResolution::KeepBoth; check.conflicts.len()];
+                if let Some(job_type) = RcloneJobType::grouped(check.resolve(items, &resolutions)) {
+                    self.enqueue_job(&sender, RcloneJob::new(job_type));
+                }
+            }
+            AppOutCmd::TransfersChecked(_, Err(error), _)
+            | AppOutCmd::TrashChecked(_, Err(error))
+            | AppOutCmd::RemoteEditorLoaded(Err(error)) => {
+                sender.input(AppInMsg::TriggerGenericError(
+                    String::from(error.title()),
+                    error.description(),
+                    false,
+                ));
+            }
+            AppOutCmd::OpenedFileSynced(local_path, remote_mod_time) => {
+                if let Some(opened) = self
+                    .opened_files
+                    .iter_mut()
+                    .find(|opened| opened.local_path == local_path)
+                {
+                    opened.remote_mod_time = remote_mod_time;
+                }
+            }
+            AppOutCmd::JobRetryDue(uuid) => {
+                let still_scheduled = JOBS
+                    .read()
+                    .get(&uuid)
+                    .is_some_and(|job| job.retry_scheduled);
+                if still_scheduled {
+                    self.retry_job(&sender, uuid);
+                }
+            }
+            AppOutCmd::JobProgress(uuid, stats) => {
+                JOB_PROGRESS.write().insert(uuid, stats);
+            }
+            AppOutCmd::JobItemFinished(uuid, index) => {
+                if let Some(job) = JOBS.write().get_mut(&uuid) {
+                    job.finished_items.insert(index);
+                }
             }
         }
     }
 }
 
+/// Formats a modification time like the file view does.
+fn format_time(time: OffsetDateTime) -> String {
+    chrono::DateTime::from_timestamp(time.unix_timestamp(), 0)
+        .map(|time| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+/// Describes the selected listings for the bottom bar, with the total size of the files.
+fn describe_selection(listings: &[RcloneFileListing]) -> String {
+    let size = |bytes: Option<String>| match bytes {
+        Some(size) => format!(" ({}B)", size),
+        None => String::from(""),
+    };
+    match listings {
+        [] => String::from(""),
+        [listing] => format!(
+            "\"{}\" selected{}",
+            listing.name,
+            size(listing.formatted_size())
+        ),
+        _ => {
+            let total_size: i64 = listings
+                .iter()
+                .filter(|listing| !listing.is_dir)
+                .map(|listing| listing.size.max(0))
+                .sum();
+            let total_size = match total_size {
+                0 => None,
+                bytes => Some(size_format::SizeFormatterSI::new(bytes as u64).to_string()),
+            };
+            format!("{} items selected{}", listings.len(), size(total_size))
+        }
+    }
+}
+
 fn main() {
     let app = RelmApp::new(APP_ID);
     relm4_icons::initialize_icons(icon_names::GRESOURCE_BYTES, icon_names::RESOURCE_PREFIX);
-    app.run::<App>(());
+    app.run::<App>(None);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::AssertUnwindSafe;
+    use std::sync::{mpsc, Arc, Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+
+    use relm4::adw;
+    use relm4::gtk::glib;
+    use relm4::{Component, ComponentController, Controller};
+
+    use crate::client::{RemoteConfig, StorageClient};
+    use crate::components::remote_editor_dialog::RemoteEdit;
+    use crate::config::AppConfig;
+    use crate::globals::FILE_PICKER_MODE;
+    use crate::icons::icon_names;
+    use crate::memory_client::MemoryClient;
+    use crate::model::{save_jobs, ConflictResolution, FilePickerMode, RcloneJobType};
+    use crate::path_tools::RclonePath;
+    use crate::trash::list_trash;
+    use crate::{App, AppInMsg, FileListingViewState, FilterBarInMsg};
+
+    /// Runs the main loop until the app reaches the expected state, or gives up after a while.
+    fn wait_until(app: &Controller<App>, condition: impl Fn(&App) -> bool) -> bool {
+        let context = glib::MainContext::default();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            while context.iteration(false) {}
+            if condition(&app.model()) {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    fn shows(app: &App, path: &str, names: &[&str]) -> bool {
+        let listed_names = (0..app.file_listing_view_wrapper.len())
+            .filter_map(|i| app.file_listing_view_wrapper.get(i))
+            .map(|item| item.borrow().model.name.clone())
+            .collect::<Vec<String>>();
+        app.path == RclonePath::from(path)
+            && matches!(app.file_listing_view_state, FileListingViewState::Loaded)
+            && listed_names == names
+    }
+
+    fn remotes(app: &App) -> Vec<String> {
+        (0..app.remotes_view_wrapper.len())
+            .filter_map(|i| app.remotes_view_wrapper.get(i))
+            .map(|remote| remote.name.clone())
+            .collect()
+    }
+
+    type Flow = Box<dyn FnOnce() + Send>;
+    type FlowResult = std::thread::Result<()>;
+
+    /// Runs a flow against the main window. GTK only works on the thread that set it up, so
+    /// every flow runs on one thread kept for them, one at a time. A flow that fails still lets
+    /// the next ones run.
+    fn run_flow(flow: impl FnOnce() + Send + 'static) {
+        static GTK_THREAD: OnceLock<Mutex<mpsc::Sender<(Flow, mpsc::Sender<FlowResult>)>>> =
+            OnceLock::new();
+        let flows = GTK_THREAD.get_or_init(|| {
+            let (flows, receiver) = mpsc::channel::<(Flow, mpsc::Sender<FlowResult>)>();
+            std::thread::spawn(move || {
+                // Keep the user's own settings and job history out of reach. These tests are
+                // ignored by default, so no other test reads these while they change.
+                let sandbox = std::env::temp_dir()
+                    .join(format!("rclone-shuttle-test-{}", std::process::id()));
+                std::env::set_var("XDG_CONFIG_HOME", sandbox.join("config"));
+                std::env::set_var("XDG_DATA_HOME", sandbox.join("data"));
+                std::env::set_var("XDG_CACHE_HOME", sandbox.join("cache"));
+                adw::init().expect("Could not set up GTK. These tests need a display.");
+                relm4_icons::initialize_icons(
+                    icon_names::GRESOURCE_BYTES,
+                    icon_names::RESOURCE_PREFIX,
+                );
+                for (flow, done) in receiver {
+                    let _ = done.send(std::panic::catch_unwind(AssertUnwindSafe(flow)));
+                }
+            });
+            Mutex::new(flows)
+        });
+        let (done, result) = mpsc::channel();
+        flows
+            .lock()
+            .unwrap()
+            .send((Box::new(flow), done))
+            .expect("The GTK thread has stopped");
+        if let Err(panic) = result.recv().expect("The GTK thread has stopped") {
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    /// Opens the main window with default settings and no job history, and waits until it shows
+    /// the first remote.
+    fn launch(client: &Arc<MemoryClient>, root_names: &[&str]) -> Controller<App> {
+        AppConfig::default().save();
+        save_jobs("[]");
+        let app = App::builder().launch(Some(client.clone() as Arc<dyn StorageClient>));
+        assert!(wait_until(&app, |app| shows(app, "foo:", root_names)));
+        app
+    }
+
+    fn enter(app: &Controller<App>, path: &str, names: &[&str]) {
+        app.emit(AppInMsg::PathEntered(RclonePath::from(path)));
+        assert!(wait_until(app, |app| shows(app, path, names)));
+    }
+
+    // These drive the main window against an in-memory backend. They need a display, so run
+    // them with `cargo test -- --ignored`.
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn app_shows_remote_quota() {
+        run_flow(|| {
+            let client = Arc::new(
+                MemoryClient::new(&["foo:"])
+                    .with_quota("foo:", 1000)
+                    .with_file("foo:docs/a.txt", "hello")
+                    .with_file("foo:b.txt", "world"),
+            );
+            // The first remote opens on launch, folders first
+            let app = launch(&client, &["docs", "b.txt"]);
+
+            assert!(wait_until(&app, |app| {
+                app.remote_quota("foo:")
+                    .is_some_and(|quota| quota.free == Some(990))
+            }));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn app_navigates_back_and_forward() {
+        run_flow(|| {
+            let client = Arc::new(
+                MemoryClient::new(&["foo:"])
+                    .with_file("foo:docs/a.txt", "hello")
+                    .with_file("foo:b.txt", "world"),
+            );
+            let app = launch(&client, &["docs", "b.txt"]);
+
+            enter(&app, "foo:docs", &["a.txt"]);
+            app.emit(AppInMsg::PathUndoRequested);
+            assert!(wait_until(&app, |app| shows(
+                app,
+                "foo:",
+                &["docs", "b.txt"]
+            )));
+            app.emit(AppInMsg::PathRedoRequested);
+            assert!(wait_until(&app, |app| shows(app, "foo:docs", &["a.txt"])));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn app_creates_moves_and_deletes() {
+        run_flow(|| {
+            let client =
+                Arc::new(MemoryClient::new(&["foo:"]).with_file("foo:docs/a.txt", "hello"));
+            let app = launch(&client, &["docs"]);
+            enter(&app, "foo:docs", &["a.txt"]);
+
+            app.emit(AppInMsg::CreateFolderConfirmed(String::from("archive")));
+            assert!(wait_until(&app, |app| {
+                shows(app, "foo:docs", &["archive", "a.txt"])
+            }));
+
+            *FILE_PICKER_MODE.write() =
+                FilePickerMode::Move(vec![RclonePath::from("foo:docs/a.txt")]);
+            enter(&app, "foo:docs/archive", &[]);
+            app.emit(AppInMsg::MoveTargetConfirmed);
+            assert!(wait_until(&app, |app| {
+                shows(app, "foo:docs/archive", &["a.txt"])
+            }));
+            assert!(!client.exists(&RclonePath::from("foo:docs/a.txt")));
+
+            app.emit(AppInMsg::DeleteConfirmed(vec![(
+                RclonePath::from("foo:docs/archive/a.txt"),
+                false,
+            )]));
+            assert!(wait_until(&app, |app| shows(app, "foo:docs/archive", &[])));
+            assert!(!client.exists(&RclonePath::from("foo:docs/archive/a.txt")));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn app_keeps_both_when_copying_over_a_file() {
+        run_flow(|| {
+            let client = Arc::new(
+                MemoryClient::new(&["foo:"])
+                    .with_file("foo:b.txt", "world")
+                    .with_file("foo:docs/b.txt", "older"),
+            );
+            let app = launch(&client, &["docs", "b.txt"]);
+            enter(&app, "foo:docs", &["b.txt"]);
+
+            *FILE_PICKER_MODE.write() = FilePickerMode::Copy(vec![RclonePath::from("foo:b.txt")]);
+            app.emit(AppInMsg::CopyTargetConfirmed);
+            assert!(wait_until(&app, |app| app.pending_transfers.len() == 1));
+            app.emit(AppInMsg::TransferConflictAnswered(
+                ConflictResolution::KeepBoth,
+                false,
+            ));
+            assert!(wait_until(&app, |app| {
+                shows(app, "foo:docs", &["b (1).txt", "b.txt"])
+            }));
+            assert!(client.exists(&RclonePath::from("foo:b.txt")));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn app_keeps_the_filter_when_a_job_reloads_the_folder() {
+        run_flow(|| {
+            let client = Arc::new(
+                MemoryClient::new(&["foo:"])
+                    .with_file("foo:a.txt", "a")
+                    .with_file("foo:b.txt", "b")
+                    .with_file("foo:c.pdf", "c"),
+            );
+            let app = launch(&client, &["a.txt", "b.txt", "c.pdf"]);
+
+            app.emit(AppInMsg::FilterToggled);
+            app.model()
+                .filter_bar
+                .emit(FilterBarInMsg::PatternChanged(String::from("*.txt")));
+            assert!(wait_until(&app, |app| shows(
+                app,
+                "foo:",
+                &["a.txt", "b.txt"]
+            )));
+
+            app.emit(AppInMsg::DeleteConfirmed(vec![(
+                RclonePath::from("foo:b.txt"),
+                false,
+            )]));
+            assert!(wait_until(&app, |app| {
+                !client.exists(&RclonePath::from("foo:b.txt")) && shows(app, "foo:", &["a.txt"])
+            }));
+            assert!(app.model().filter_bar.widget().is_search_mode());
+            assert_eq!(app.model().filter.pattern, "*.txt");
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn app_deletes_into_trash_and_restores() {
+        run_flow(|| {
+            let client = Arc::new(
+                MemoryClient::new(&["foo:"])
+                    .with_file("foo:docs/a.txt", "hello")
+                    .with_file("foo:docs/b.txt", "world"),
+            );
+            let app = launch(&client, &["docs"]);
+            enter(&app, "foo:docs", &["a.txt", "b.txt"]);
+
+            app.emit(AppInMsg::UseTrashToggled(true));
+            app.emit(AppInMsg::DeleteConfirmed(vec![(
+                RclonePath::from("foo:docs/a.txt"),
+                false,
+            )]));
+            assert!(wait_until(&app, |app| shows(app, "foo:docs", &["b.txt"])));
+            let trashed = list_trash(client.as_ref(), "foo:", ".shuttle-trash").unwrap();
+            let [item] = trashed.as_slice() else {
+                panic!("Expected one item in the trash, found {:?}", trashed);
+            };
+            assert_eq!(item.original_path, RclonePath::from("foo:docs/a.txt"));
+
+            app.emit(AppInMsg::TransfersRequested(vec![RcloneJobType::Move {
+                source_path: item.listing.path.clone(),
+                target_path: item.original_path.clone(),
+            }]));
+            assert!(wait_until(&app, |app| {
+                shows(app, "foo:docs", &["a.txt", "b.txt"])
+            }));
+            assert!(list_trash(client.as_ref(), "foo:", ".shuttle-trash")
+                .unwrap()
+                .is_empty());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn app_undoes_finished_operations() {
+        run_flow(|| {
+            let client = Arc::new(
+                MemoryClient::new(&["foo:"])
+                    .with_file("foo:docs/a.txt", "hello")
+                    .with_folder("foo:archive"),
+            );
+            let app = launch(&client, &["archive", "docs"]);
+            enter(&app, "foo:docs", &["a.txt"]);
+
+            *FILE_PICKER_MODE.write() =
+                FilePickerMode::Move(vec![RclonePath::from("foo:docs/a.txt")]);
+            enter(&app, "foo:archive", &[]);
+            app.emit(AppInMsg::MoveTargetConfirmed);
+            assert!(wait_until(&app, |app| {
+                app.undo_stack
+                    .last()
+                    .is_some_and(|entry| entry.description.starts_with("Move "))
+            }));
+            app.emit(AppInMsg::CreateFolderConfirmed(String::from("scratch")));
+            assert!(wait_until(&app, |app| {
+                shows(app, "foo:archive", &["scratch", "a.txt"])
+            }));
+
+            app.emit(AppInMsg::UndoRequested);
+            assert!(wait_until(&app, |app| shows(
+                app,
+                "foo:archive",
+                &["a.txt"]
+            )));
+            app.emit(AppInMsg::UndoRequested);
+            assert!(wait_until(&app, |app| shows(app, "foo:archive", &[])));
+            assert!(client.exists(&RclonePath::from("foo:docs/a.txt")));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn app_does_not_undo_creating_an_existing_folder() {
+        run_flow(|| {
+            let client =
+                Arc::new(MemoryClient::new(&["foo:"]).with_file("foo:docs/a.txt", "hello"));
+            let app = launch(&client, &["docs"]);
+
+            // Undoing this would delete what was already in the folder
+            app.emit(AppInMsg::CreateFolderConfirmed(String::from("docs")));
+            app.emit(AppInMsg::CreateFolderConfirmed(String::from("new")));
+            assert!(wait_until(&app, |app| shows(app, "foo:", &["docs", "new"])));
+            let model = app.model();
+            assert_eq!(model.undo_stack.len(), 1);
+            assert!(model.undo_stack[0]
+                .description
+                .starts_with("Create folder new "));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn app_adds_and_deletes_remotes() {
+        run_flow(|| {
+            let client = Arc::new(MemoryClient::new(&["foo:"]).with_file("foo:b.txt", "world"));
+            let app = launch(&client, &["b.txt"]);
+
+            app.emit(AppInMsg::RemoteEditSubmitted(RemoteEdit {
+                original_name: None,
+                name: String::from("bar"),
+                provider: String::from("memory"),
+                values: RemoteConfig::new(),
+            }));
+            assert!(wait_until(&app, |app| remotes(app) == ["bar:", "foo:"]));
+            app.emit(AppInMsg::RemoteDeletionConfirmed(String::from("bar")));
+            assert!(wait_until(&app, |app| remotes(app) == ["foo:"]));
+        });
+    }
 }
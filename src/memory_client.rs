@@ -0,0 +1,638 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use time::OffsetDateTime;
+
+use crate::{
+    client::{
+        flatten_metadata, FileMetadata, FolderSize, ImportedFileListing, MkdirError, ProcessHandle,
+        RcloneError, RcloneFileListing, RemoteConfig, RemoteProvider, RemoteQuota, SearchQuery,
+        StorageClient, TransferStats,
+    },
+    path_tools::RclonePath,
+};
+
+#[derive(Debug, Clone)]
+struct MemoryEntry {
+    is_dir: bool,
+    content: Vec<u8>,
+    mod_time: OffsetDateTime,
+}
+
+/// Keeps a fake set of remotes in memory, so the UI can be exercised without rclone.
+#[derive(Debug, Default)]
+pub struct MemoryClient {
+    entries: Mutex<BTreeMap<String, MemoryEntry>>,
+    remote_configs: Mutex<BTreeMap<String, RemoteConfig>>,
+    /// The total space of remotes that report it. Others don't support `about`.
+    quotas: Mutex<BTreeMap<String, u64>>,
+}
+
+impl MemoryClient {
+    pub fn new(remotes: &[&str]) -> Self {
+        let client = Self::default();
+        for remote in remotes {
+            client.insert(&RclonePath::from(remote), true, vec![]);
+            client.remote_configs.lock().unwrap().insert(
+                remote.trim_end_matches(':').to_owned(),
+                RemoteConfig::from([(String::from("type"), String::from("memory"))]),
+            );
+        }
+        client
+    }
+
+    pub fn with_folder(self, path: &str) -> Self {
+        self.insert(&RclonePath::from(path), true, vec![]);
+        self
+    }
+
+    pub fn with_file(self, path: &str, content: &str) -> Self {
+        self.insert(&RclonePath::from(path), false, content.as_bytes().to_vec());
+        self
+    }
+
+    pub fn with_quota(self, remote: &str, total: u64) -> Self {
+        self.quotas.lock().unwrap().insert(remote.to_owned(), total);
+        self
+    }
+
+    pub fn exists(&self, path: &RclonePath) -> bool {
+        self.entries.lock().unwrap().contains_key(&key(path))
+    }
+
+    /// Adds an entry along with any missing parent folders, like rclone does on upload.
+    fn insert(&self, path: &RclonePath, is_dir: bool, content: Vec<u8>) {
+        let mut entries = self.entries.lock().unwrap();
+        let mut current = path.clone();
+        while current.path_has_parent() {
+            let parent = current.resolve_to_parent();
+            if parent == current {
+                break;
+            }
+            entries.entry(key(&parent)).or_insert_with(|| MemoryEntry {
+                is_dir: true,
+                content: vec![],
+                mod_time: OffsetDateTime::now_utc(),
+            });
+            current = parent;
+        }
+        entries.insert(
+            key(path),
+            MemoryEntry {
+                is_dir,
+                content,
+                mod_time: OffsetDateTime::now_utc(),
+            },
+        );
+    }
+
+    /// Copies an entry and everything below it to a new location.
+    fn transfer(
+        &self,
+        source_path: &RclonePath,
+        target_path: &RclonePath,
+        remove_source: bool,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        if process.is_cancelled() {
+            return Err(RcloneError::Unknown(String::from("Cancelled before start")));
+        }
+        let source_key = key(source_path);
+        let moved_entries = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(k, _)| **k == source_key || is_below(k, &source_key))
+            .map(|(k, entry)| (k.clone(), entry.clone()))
+            .collect::<Vec<(String, MemoryEntry)>>();
+        if moved_entries.is_empty() {
+            return Err(RcloneError::NotFound(format!(
+                "{}: object not found",
+                source_path
+            )));
+        }
+
+        let total_bytes = moved_entries
+            .iter()
+            .map(|(_, entry)| entry.content.len() as u64)
+            .sum();
+        for (k, entry) in moved_entries {
+            let target =
+                RclonePath::from(&format!("{}{}", key(target_path), &k[source_key.len()..]));
+            if remove_source {
+                self.entries.lock().unwrap().remove(&k);
+            }
+            self.insert(&target, entry.is_dir, entry.content);
+        }
+        on_progress(TransferStats {
+            bytes: total_bytes,
+            total_bytes,
+            speed: 0.0,
+            eta: Some(0),
+        });
+        Ok(())
+    }
+}
+
+fn listing(k: &str, entry: &MemoryEntry) -> RcloneFileListing {
+    let path = RclonePath::from(k);
+    RcloneFileListing {
+        name: path.filename(),
+        path,
+        size: match entry.is_dir {
+            true => -1,
+            false => entry.content.len() as i64,
+        },
+        mime_type: String::from(match entry.is_dir {
+            true => "inode/directory",
+            false => "application/octet-stream",
+        }),
+        mod_time: entry.mod_time,
+        is_dir: entry.is_dir,
+        is_bucket: None,
+    }
+}
+
+impl StorageClient for MemoryClient {
+    fn list_remotes(&self) -> Result<Vec<String>, RcloneError> {
+        Ok(self
+            .entries
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|k| k.ends_with(':'))
+            .cloned()
+            .collect())
+    }
+
+    fn ls(&self, path: &RclonePath) -> Result<Vec<RcloneFileListing>, RcloneError> {
+        let path_key = key(path);
+        let entries = self.entries.lock().unwrap();
+        match entries.get(&path_key) {
+            Some(entry) if entry.is_dir => {}
+            _ => {
+                return Err(RcloneError::NotFound(format!(
+                    "{}: directory not found",
+                    path
+                )))
+            }
+        }
+        Ok(entries
+            .iter()
+            .filter(|(k, _)| {
+                relative_key(k, &path_key).is_some_and(|remainder| !remainder.contains('/'))
+            })
+            .map(|(k, entry)| listing(k, entry))
+            .collect())
+    }
+
+    fn copy(
+        &self,
+        source_path: &RclonePath,
+        target_path: &RclonePath,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        self.transfer(source_path, target_path, false, process, on_progress)
+    }
+
+    fn mv(
+        &self,
+        source_path: &RclonePath,
+        target_path: &RclonePath,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        self.transfer(source_path, target_path, true, process, on_progress)
+    }
+
+    fn rename(
+        &self,
+        source_path: &RclonePath,
+        new_filename: &str,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        let target_path = source_path.resolve_to_parent().join(new_filename);
+        self.transfer(source_path, &target_path, true, process, on_progress)
+    }
+
+    fn rm(
+        &self,
+        path: &RclonePath,
+        is_dir: bool,
+        process: &ProcessHandle,
+    ) -> Result<(), RcloneError> {
+        if process.is_cancelled() {
+            return Err(RcloneError::Unknown(String::from("Cancelled before start")));
+        }
+        let path_key = key(path);
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&path_key) {
+            Some(entry) if entry.is_dir == is_dir => {}
+            _ => return Err(RcloneError::NotFound(format!("{}: object not found", path))),
+        }
+        entries.retain(|k, _| *k != path_key && !is_below(k, &path_key));
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &RclonePath) -> Result<(), MkdirError> {
+        if self
+            .entries
+            .lock()
+            .unwrap()
+            .get(&key(path))
+            .is_some_and(|entry| !entry.is_dir)
+        {
+            return Err(MkdirError::Generic(RcloneError::Unknown(format!(
+                "{}: is a file",
+                path
+            ))));
+        }
+        self.insert(path, true, vec![]);
+        Ok(())
+    }
+
+    fn cat(&self, path: &RclonePath, max_bytes: u64) -> Result<Vec<u8>, RcloneError> {
+        match self.entries.lock().unwrap().get(&key(path)) {
+            Some(entry) if !entry.is_dir => Ok(entry
+                .content
+                .iter()
+                .take(max_bytes as usize)
+                .copied()
+                .collect()),
+            _ => Err(RcloneError::NotFound(format!("{}: object not found", path))),
+        }
+    }
+
+    fn stat(&self, path: &RclonePath) -> Result<FileMetadata, RcloneError> {
+        let path_key = key(path);
+        let entries = self.entries.lock().unwrap();
+        let entry = entries
+            .get(&path_key)
+            .ok_or_else(|| RcloneError::NotFound(format!("{}: object not found", path)))?;
+        let listing = listing(&path_key, entry);
+        let imported = ImportedFileListing {
+            path: listing.name.clone(),
+            name: listing.name,
+            size: listing.size,
+            mime_type: listing.mime_type,
+            mod_time: listing.mod_time,
+            is_dir: listing.is_dir,
+            is_bucket: None,
+        };
+        let value =
+            serde_json::to_value(imported).map_err(|err| RcloneError::Unknown(err.to_string()))?;
+        Ok(flatten_metadata(&value))
+    }
+
+    fn search(
+        &self,
+        path: &RclonePath,
+        query: &SearchQuery,
+        process: &ProcessHandle,
+        on_hit: &dyn Fn(RcloneFileListing),
+    ) -> Result<(), RcloneError> {
+        let name_regex = query.name_regex()?;
+        let path_key = key(path);
+        let hits = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(k, _)| {
+                relative_key(k, &path_key).is_some_and(|remainder| {
+                    query
+                        .max_depth
+                        .is_none_or(|depth| remainder.split('/').count() <= depth as usize)
+                })
+            })
+            .map(|(k, entry)| listing(k, entry))
+            .filter(|hit| name_regex.is_match(&hit.name))
+            .collect::<Vec<RcloneFileListing>>();
+        for hit in hits {
+            if process.is_cancelled() {
+                return Err(RcloneError::Unknown(String::from("Cancelled")));
+            }
+            on_hit(hit);
+        }
+        Ok(())
+    }
+
+    fn hash_types(&self, _path: &RclonePath) -> Result<Vec<String>, RcloneError> {
+        Ok(vec![String::from("crc32")])
+    }
+
+    fn hashsum(&self, path: &RclonePath, hash_type: &str) -> Result<String, RcloneError> {
+        if hash_type != "crc32" {
+            return Err(RcloneError::Unknown(format!(
+                "hash type {} not supported",
+                hash_type
+            )));
+        }
+        match self.entries.lock().unwrap().get(&key(path)) {
+            Some(entry) if !entry.is_dir => Ok(format!("{:08x}", crc32(&entry.content))),
+            _ => Err(RcloneError::NotFound(format!("{}: object not found", path))),
+        }
+    }
+
+    fn size(&self, path: &RclonePath) -> Result<FolderSize, RcloneError> {
+        let path_key = key(path);
+        let entries = self.entries.lock().unwrap();
+        if !entries.contains_key(&path_key) {
+            return Err(RcloneError::NotFound(format!(
+                "{}: directory not found",
+                path
+            )));
+        }
+        let files = entries
+            .iter()
+            .filter(|(k, entry)| !entry.is_dir && (**k == path_key || is_below(k, &path_key)))
+            .map(|(_, entry)| entry.content.len() as i64)
+            .collect::<Vec<i64>>();
+        Ok(FolderSize {
+            count: files.len() as i64,
+            bytes: files.iter().sum(),
+        })
+    }
+
+    fn about(&self, remote: &str) -> Result<RemoteQuota, RcloneError> {
+        let total = self
+            .quotas
+            .lock()
+            .unwrap()
+            .get(remote)
+            .copied()
+            .ok_or_else(|| RcloneError::Unknown(format!("{} doesn't support about", remote)))?;
+        let used = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(k, _)| is_below(k, remote))
+            .map(|(_, entry)| entry.content.len() as u64)
+            .sum();
+        Ok(RemoteQuota {
+            total: Some(total),
+            used: Some(used),
+            free: Some(total.saturating_sub(used)),
+        })
+    }
+
+    fn providers(&self) -> Result<Vec<RemoteProvider>, RcloneError> {
+        Ok(vec![RemoteProvider {
+            name: String::from("memory"),
+            description: String::from("In memory object storage system."),
+            options: vec![],
+            hide: false,
+        }])
+    }
+
+    fn remote_config(&self, remote: &str) -> Result<RemoteConfig, RcloneError> {
+        self.remote_configs
+            .lock()
+            .unwrap()
+            .get(remote)
+            .cloned()
+            .ok_or_else(|| RcloneError::NotFound(format!("Remote {} not found", remote)))
+    }
+
+    fn create_remote(
+        &self,
+        remote: &str,
+        provider: &str,
+        values: &RemoteConfig,
+        _values_obscured: bool,
+    ) -> Result<(), RcloneError> {
+        let mut config = values.clone();
+        config.insert(String::from("type"), provider.to_owned());
+        self.remote_configs
+            .lock()
+            .unwrap()
+            .insert(remote.to_owned(), config);
+        self.insert(&RclonePath::from(&format!("{}:", remote)), true, vec![]);
+        Ok(())
+    }
+
+    fn update_remote(&self, remote: &str, values: &RemoteConfig) -> Result<(), RcloneError> {
+        match self.remote_configs.lock().unwrap().get_mut(remote) {
+            Some(config) => {
+                config.extend(values.clone());
+                Ok(())
+            }
+            None => Err(RcloneError::NotFound(format!(
+                "Remote {} not found",
+                remote
+            ))),
+        }
+    }
+
+    fn delete_remote(&self, remote: &str) -> Result<(), RcloneError> {
+        self.remote_configs.lock().unwrap().remove(remote);
+        let remote_key = format!("{}:", remote);
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|k, _| *k != remote_key && !is_below(k, &remote_key));
+        Ok(())
+    }
+}
+
+/// Normalizes a path so that `foo:bar` and `foo:bar/` end up as the same entry.
+/// The CRC-32 checksum rclone reports as "crc32".
+fn crc32(content: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in content {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+fn key(path: &RclonePath) -> String {
+    let raw = path.to_string();
+    match raw.strip_suffix('/') {
+        Some(stripped) if !stripped.ends_with(':') && !stripped.is_empty() => stripped.to_owned(),
+        _ => raw,
+    }
+}
+
+/// The part of `candidate_key` below `parent_key`, if it is below it at all.
+fn relative_key<'a>(candidate_key: &'a str, parent_key: &str) -> Option<&'a str> {
+    let prefix = match parent_key.ends_with(':') || parent_key.ends_with('/') {
+        true => parent_key.to_owned(),
+        false => format!("{}/", parent_key),
+    };
+    candidate_key
+        .strip_prefix(&prefix)
+        .filter(|remainder| !remainder.is_empty())
+}
+
+fn is_below(candidate_key: &str, parent_key: &str) -> bool {
+    relative_key(candidate_key, parent_key).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crate::client::{FolderSize, ProcessHandle, RemoteConfig, SearchQuery, StorageClient};
+    use crate::memory_client::MemoryClient;
+    use crate::path_tools::RclonePath;
+
+    fn names(client: &MemoryClient, path: &str) -> Vec<String> {
+        client
+            .ls(&RclonePath::from(path))
+            .unwrap()
+            .into_iter()
+            .map(|l| l.name)
+            .collect()
+    }
+
+    #[test]
+    fn ls_lists_direct_children_only() {
+        let client = MemoryClient::new(&["foo:"]).with_file("foo:docs/a.txt", "a");
+        assert_eq!(names(&client, "foo:"), vec!["docs"]);
+        assert_eq!(names(&client, "foo:docs"), vec!["a.txt"]);
+        assert!(client.ls(&RclonePath::from("foo:nope")).is_err());
+    }
+
+    #[test]
+    fn move_folder_moves_contents() {
+        let client = MemoryClient::new(&["foo:"]).with_file("foo:docs/a.txt", "a");
+        client
+            .mv(
+                &RclonePath::from("foo:docs"),
+                &RclonePath::from("foo:archive"),
+                &ProcessHandle::default(),
+                &|_| {},
+            )
+            .unwrap();
+        assert!(!client.exists(&RclonePath::from("foo:docs")));
+        assert!(client.exists(&RclonePath::from("foo:archive/a.txt")));
+    }
+
+    #[test]
+    fn rm_removes_folder_recursively() {
+        let client = MemoryClient::new(&["foo:"]).with_file("foo:docs/sub/a.txt", "a");
+        client
+            .rm(
+                &RclonePath::from("foo:docs"),
+                true,
+                &ProcessHandle::default(),
+            )
+            .unwrap();
+        assert_eq!(names(&client, "foo:"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn rename_remote_keeps_its_config() {
+        let client = MemoryClient::new(&["foo:"]);
+        client
+            .update_remote(
+                "foo",
+                &RemoteConfig::from([(String::from("root"), String::from("/data"))]),
+            )
+            .unwrap();
+        client.rename_remote("foo", "bar").unwrap();
+        assert_eq!(client.list_remotes().unwrap(), vec!["bar:"]);
+        assert_eq!(client.remote_config("bar").unwrap()["root"], "/data");
+        assert!(client.remote_config("foo").is_err());
+    }
+
+    #[test]
+    fn cat_reads_up_to_the_limit() {
+        let client = MemoryClient::new(&["foo:"]).with_file("foo:a.txt", "hello");
+        let path = RclonePath::from("foo:a.txt");
+        assert_eq!(client.cat(&path, 3).unwrap(), b"hel");
+        assert_eq!(client.cat(&path, 100).unwrap(), b"hello");
+        assert!(client.cat(&RclonePath::from("foo:"), 3).is_err());
+    }
+
+    #[test]
+    fn stat_reports_listing_fields() {
+        let client = MemoryClient::new(&["foo:"]).with_file("foo:docs/a.txt", "hello");
+        let metadata = client.stat(&RclonePath::from("foo:docs/a.txt")).unwrap();
+        assert_eq!(metadata["Name"], "a.txt");
+        assert_eq!(metadata["Size"], "5");
+        assert_eq!(metadata["IsDir"], "false");
+        assert!(client.stat(&RclonePath::from("foo:b.txt")).is_err());
+    }
+
+    #[test]
+    fn search_respects_depth_limit() {
+        let client = MemoryClient::new(&["foo:"])
+            .with_file("foo:report.pdf", "a")
+            .with_file("foo:docs/Report 2024.pdf", "b")
+            .with_file("foo:docs/old/report-2019.pdf", "c")
+            .with_file("foo:docs/notes.txt", "d");
+        let search = |max_depth| {
+            let hits = RefCell::new(vec![]);
+            let query = SearchQuery {
+                pattern: String::from("report"),
+                max_depth,
+            };
+            client
+                .search(
+                    &RclonePath::from("foo:"),
+                    &query,
+                    &ProcessHandle::default(),
+                    &|hit| hits.borrow_mut().push(hit.path.to_string()),
+                )
+                .unwrap();
+            hits.into_inner()
+        };
+        assert_eq!(
+            search(None),
+            vec![
+                "foo:docs/Report 2024.pdf",
+                "foo:docs/old/report-2019.pdf",
+                "foo:report.pdf"
+            ]
+        );
+        assert_eq!(
+            search(Some(2)),
+            vec!["foo:docs/Report 2024.pdf", "foo:report.pdf"]
+        );
+    }
+
+    #[test]
+    fn size_counts_files_below_a_folder() {
+        let client = MemoryClient::new(&["foo:"])
+            .with_file("foo:docs/a.txt", "abc")
+            .with_file("foo:docs/old/b.txt", "de")
+            .with_file("foo:other.txt", "fgh");
+        assert_eq!(
+            client.size(&RclonePath::from("foo:docs")).unwrap(),
+            FolderSize { count: 2, bytes: 5 }
+        );
+        assert!(client.size(&RclonePath::from("foo:nope")).is_err());
+    }
+
+    #[test]
+    fn hashsum_of_a_file() {
+        let client = MemoryClient::new(&["foo:"]).with_file("foo:a.txt", "123456789");
+        let path = RclonePath::from("foo:a.txt");
+        assert_eq!(client.hash_types(&path).unwrap(), vec!["crc32"]);
+        assert_eq!(client.hashsum(&path, "crc32").unwrap(), "cbf43926");
+        assert!(client.hashsum(&path, "md5").is_err());
+        assert!(client.hashsum(&RclonePath::from("foo:"), "crc32").is_err());
+    }
+
+    #[test]
+    fn about_reports_configured_quota() {
+        let client = MemoryClient::new(&["foo:", "bar:"])
+            .with_quota("foo:", 100)
+            .with_file("foo:docs/a.txt", "abc")
+            .with_file("bar:b.txt", "de");
+        let quota = client.about("foo:").unwrap();
+        assert_eq!((quota.used, quota.free), (Some(3), Some(97)));
+        assert!(client.about("bar:").is_err());
+    }
+}
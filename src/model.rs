@@ -1,11 +1,25 @@
-use std::iter::Iterator;
+use std::{
+    cell::Cell,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    iter::Iterator,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
-use time::OffsetDateTime;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 use uuid::Uuid;
 
-use crate::{globals::JOBS, path_tools::RclonePath};
+use crate::{
+    client::{
+        name_pattern, ProcessHandle, RcloneError, RcloneFileListing, StorageClient, TransferStats,
+    },
+    globals::{JOBS, JOB_PROGRESS, QUEUE_PAUSED},
+    path_tools::RclonePath,
+};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
 pub enum RcloneJobType {
     Upload {
         local_path: RclonePath,
@@ -19,46 +33,595 @@ pub enum RcloneJobType {
         source_path: RclonePath,
         target_path: RclonePath,
     },
-    Rename(RclonePath),
+    Rename {
+        source_path: RclonePath,
+        target_path: RclonePath,
+    },
     Download {
         local_path: RclonePath,
         remote_path: RclonePath,
     },
-    Delete(RclonePath),
+    Delete {
+        path: RclonePath,
+        is_dir: bool,
+    },
+    /// Moves a file or folder into the trash of its remote, from where it can be restored.
+    Trash {
+        path: RclonePath,
+        trash_path: RclonePath,
+    },
     Open {
         remote_path: RclonePath,
         tmp_local_path: RclonePath,
     },
+    /// Several operations of the same kind, listed as one entry in the queue.
+    Batch {
+        items: Vec<RcloneJobType>,
+    },
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl RcloneJobType {
+    /// Groups operations into one job. A single operation stays as it is.
+    pub fn grouped(mut items: Vec<RcloneJobType>) -> Option<Self> {
+        match items.len() {
+            0 => None,
+            1 => items.pop(),
+            _ => Some(Self::Batch { items }),
+        }
+    }
+
+    /// The file or folder the operation works on.
+    pub fn subject(&self) -> Option<&RclonePath> {
+        match self {
+            Self::Upload { local_path, .. } => Some(local_path),
+            Self::Move { source_path, .. }
+            | Self::Copy { source_path, .. }
+            | Self::Rename { source_path, .. } => Some(source_path),
+            Self::Download { remote_path, .. } | Self::Open { remote_path, .. } => {
+                Some(remote_path)
+            }
+            Self::Delete { path, .. } | Self::Trash { path, .. } => Some(path),
+            Self::Batch { .. } => None,
+        }
+    }
+
+    /// Where a transfer writes to, for the operations that can clash with an existing file.
+    pub fn target(&self) -> Option<&RclonePath> {
+        match self {
+            Self::Upload { remote_path, .. } => Some(remote_path),
+            Self::Move { target_path, .. }
+            | Self::Copy { target_path, .. }
+            | Self::Rename { target_path, .. } => Some(target_path),
+            Self::Trash { trash_path, .. } => Some(trash_path),
+            _ => None,
+        }
+    }
+
+    /// The same transfer, writing to `target` instead.
+    pub fn with_target(self, target: RclonePath) -> Self {
+        match self {
+            Self::Upload { local_path, .. } => Self::Upload {
+                local_path,
+                remote_path: target,
+            },
+            Self::Move { source_path, .. } => Self::Move {
+                source_path,
+                target_path: target,
+            },
+            Self::Copy { source_path, .. } => Self::Copy {
+                source_path,
+                target_path: target,
+            },
+            Self::Rename { source_path, .. } => Self::Rename {
+                source_path,
+                target_path: target,
+            },
+            Self::Trash { path, .. } => Self::Trash {
+                path,
+                trash_path: target,
+            },
+            other => other,
+        }
+    }
+
+    /// The moves that put what a trash job deleted back where it was.
+    pub fn restorations(&self) -> Vec<RcloneJobType> {
+        match self {
+            Self::Trash { path, trash_path } => vec![Self::Move {
+                source_path: trash_path.clone(),
+                target_path: path.clone(),
+            }],
+            Self::Batch { items } => items.iter().flat_map(Self::restorations).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Whether the operation changes anything on a remote, as opposed to only reading from it.
+    pub fn changes_remote(&self) -> bool {
+        match self {
+            Self::Download { .. } | Self::Open { .. } => false,
+            Self::Batch { items } => items.iter().any(Self::changes_remote),
+            _ => true,
+        }
+    }
+
+    /// The remotes whose contents this operation changes, like "foo:".
+    pub fn changed_remotes(&self) -> BTreeSet<String> {
+        let paths = match self {
+            Self::Upload { remote_path, .. } => vec![remote_path],
+            Self::Copy { target_path, .. } => vec![target_path],
+            Self::Move {
+                source_path,
+                target_path,
+            }
+            | Self::Rename {
+                source_path,
+                target_path,
+            } => vec![source_path, target_path],
+            Self::Delete { path, .. } | Self::Trash { path, .. } => vec![path],
+            Self::Download { .. } | Self::Open { .. } => vec![],
+            Self::Batch { items } => {
+                return items.iter().flat_map(Self::changed_remotes).collect();
+            }
+        };
+        paths.into_iter().filter_map(RclonePath::remote).collect()
+    }
+
+    /// The operation that takes this one back, if there is one. Copies are taken back by
+    /// deleting what they created, so `is_dir` tells whether a created path is a folder.
+    pub fn inverse(&self, is_dir: &dyn Fn(&RclonePath) -> bool) -> Option<Self> {
+        match self {
+            Self::Upload {
+                remote_path: target_path,
+                ..
+            }
+            | Self::Copy { target_path, .. } => Some(Self::Delete {
+                path: target_path.clone(),
+                is_dir: is_dir(target_path),
+            }),
+            Self::Move {
+                source_path,
+                target_path,
+            } => Some(Self::Move {
+                source_path: target_path.clone(),
+                target_path: source_path.clone(),
+            }),
+            Self::Rename {
+                source_path,
+                target_path,
+            } => Some(Self::Rename {
+                source_path: target_path.clone(),
+                target_path: source_path.clone(),
+            }),
+            Self::Trash { path, trash_path } => Some(Self::Move {
+                source_path: trash_path.clone(),
+                target_path: path.clone(),
+            }),
+            Self::Delete { .. } | Self::Download { .. } | Self::Open { .. } => None,
+            // Taken back last item first, and only as a whole
+            Self::Batch { items } => Self::grouped(
+                items
+                    .iter()
+                    .rev()
+                    .map(|item| item.inverse(is_dir))
+                    .collect::<Option<Vec<Self>>>()?,
+            ),
+        }
+    }
+
+    /// Describes the operation in a line, like "Move a.txt to foo:docs".
+    pub fn describe(&self) -> String {
+        self.describe_with_subject(None)
+    }
+
+    /// Describes an operation, naming `subject` instead of the file it works on if given.
+    fn describe_with_subject(&self, subject: Option<String>) -> String {
+        match self.clone() {
+            Self::Upload {
+                local_path: source,
+                remote_path: dest,
+            } => {
+                format!(
+                    "Upload {} to {}",
+                    subject.unwrap_or(source.filename()),
+                    dest.resolve_to_parent()
+                )
+            }
+            Self::Move {
+                source_path,
+                target_path,
+            } => {
+                format!(
+                    "Move {} to {}",
+                    subject.unwrap_or(source_path.filename()),
+                    target_path.resolve_to_parent()
+                )
+            }
+            Self::Copy {
+                source_path,
+                target_path,
+            } => {
+                format!(
+                    "Copy {} to {}",
+                    subject.unwrap_or(source_path.filename()),
+                    target_path.resolve_to_parent()
+                )
+            }
+            Self::Rename { target_path, .. } => {
+                format!(
+                    "Rename {} in {}",
+                    subject.unwrap_or(target_path.filename()),
+                    target_path.resolve_to_parent()
+                )
+            }
+            Self::Download {
+                local_path,
+                remote_path,
+            } => {
+                format!(
+                    "Download {} to {}",
+                    subject.unwrap_or(remote_path.filename()),
+                    local_path.resolve_to_parent()
+                )
+            }
+            Self::Delete { path, .. } => {
+                format!(
+                    "Delete {} from {}",
+                    subject.unwrap_or(path.filename()),
+                    path.resolve_to_parent()
+                )
+            }
+            Self::Trash { path, .. } => {
+                format!(
+                    "Move {} from {} to the trash",
+                    subject.unwrap_or(path.filename()),
+                    path.resolve_to_parent()
+                )
+            }
+            Self::Open { remote_path, .. } => {
+                format!(
+                    "Open {} from {}",
+                    subject.unwrap_or(remote_path.filename()),
+                    remote_path.resolve_to_parent()
+                )
+            }
+            Self::Batch { items } => match items.first() {
+                Some(item) => item.describe_with_subject(Some(format!("{} items", items.len()))),
+                None => String::from("Empty batch"),
+            },
+        }
+    }
+
+    /// Runs the rclone operations behind a job. Batches go through their items one after the
+    /// other, skipping those in `finished_items`, and carry on past items that fail.
+    pub fn run(
+        &self,
+        client: &dyn StorageClient,
+        finished_items: &BTreeSet<usize>,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+        on_item_finished: &dyn Fn(usize),
+    ) -> Result<(), RcloneError> {
+        let items = match self {
+            Self::Batch { items } => items,
+            _ => return self.run_single(client, process, on_progress),
+        };
+        let transferred_bytes = Cell::new(0);
+        let mut failures = vec![];
+        for (index, item) in items.iter().enumerate() {
+            if finished_items.contains(&index) {
+                continue;
+            }
+            if process.is_cancelled() {
+                return Err(RcloneError::Unknown(String::from("Cancelled before start")));
+            }
+            let item_bytes = Cell::new(0);
+            let result = item.run_single(client, process, &|stats| {
+                item_bytes.set(stats.total_bytes);
+                on_progress(TransferStats {
+                    bytes: transferred_bytes.get() + stats.bytes,
+                    total_bytes: transferred_bytes.get() + stats.total_bytes,
+                    speed: stats.speed,
+                    eta: None,
+                });
+            });
+            match result {
+                Ok(()) => {
+                    // Failed items count as not transferred, so that progress doesn't overstate
+                    transferred_bytes.set(transferred_bytes.get() + item_bytes.get());
+                    on_item_finished(index);
+                }
+                Err(error) => failures.push((item, error)),
+            }
+        }
+
+        let Some((_, first_error)) = failures.first() else {
+            return Ok(());
+        };
+        let details = failures
+            .iter()
+            .map(|(item, error)| match item.subject() {
+                Some(subject) => format!("{}: {}", subject, error.message()),
+                None => String::from(error.message()),
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        Err(first_error.with_message(format!(
+            "{} of {} items failed.\n\n{}",
+            failures.len(),
+            items.len(),
+            details
+        )))
+    }
+
+    fn run_single(
+        &self,
+        client: &dyn StorageClient,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        match self {
+            Self::Upload {
+                local_path,
+                remote_path,
+            } => client.copy(local_path, remote_path, process, on_progress),
+            Self::Move {
+                source_path,
+                target_path,
+            } => client.mv(source_path, target_path, process, on_progress),
+            Self::Copy {
+                source_path,
+                target_path,
+            } => client.copy(source_path, target_path, process, on_progress),
+            Self::Rename {
+                source_path,
+                target_path,
+            } => client.rename(source_path, &target_path.filename(), process, on_progress),
+            Self::Download {
+                local_path,
+                remote_path,
+            } => client.copy(remote_path, local_path, process, on_progress),
+            Self::Delete { path, is_dir } => client.rm(path, *is_dir, process),
+            Self::Trash { path, trash_path } => client.mv(path, trash_path, process, on_progress),
+            Self::Open {
+                remote_path,
+                tmp_local_path,
+            } => client.copy(remote_path, tmp_local_path, process, on_progress),
+            Self::Batch { .. } => self.run(client, &BTreeSet::new(), process, on_progress, &|_| {}),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum RcloneJobStatus {
+    /// Waiting for a free slot in the scheduler.
+    Queued,
     Ongoing,
     Finished,
     Failed(String),
+    Cancelled,
+    /// Was still running when the app exited during an earlier session.
+    Interrupted,
 }
 
-#[derive(Debug, Clone)]
+/// An earlier, failed run of a job that has since been retried.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RcloneJobAttempt {
+    pub error: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub failed_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RcloneJob {
+    #[serde(
+        serialize_with = "serialize_uuid",
+        deserialize_with = "deserialize_uuid"
+    )]
     pub uuid: Uuid,
     pub status: RcloneJobStatus,
     pub r#type: RcloneJobType,
+    #[serde(with = "time::serde::rfc3339")]
     pub started_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub queued_at: OffsetDateTime,
+    #[serde(skip)]
+    pub process: ProcessHandle,
+    #[serde(default)]
+    pub history: Vec<RcloneJobAttempt>,
+    #[serde(skip)]
+    pub retry_scheduled: bool,
+    #[serde(skip)]
+    pub suspended: bool,
+    /// The items of a batch that are done, so that a retry can skip them.
+    #[serde(default)]
+    pub finished_items: BTreeSet<usize>,
+    /// Whether the job replaces existing files, which means it can't be undone.
+    #[serde(default)]
+    pub overwrites: bool,
+    /// Set on jobs that take back an earlier operation, which this describes.
+    #[serde(default)]
+    pub undoes: Option<String>,
+}
+
+fn serialize_uuid<S: Serializer>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&uuid.to_string())
+}
+
+fn deserialize_uuid<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+    let raw = String::deserialize(deserializer)?;
+    Uuid::parse_str(&raw).map_err(serde::de::Error::custom)
 }
 
 impl RcloneJob {
     pub fn new(r#type: RcloneJobType) -> Self {
         Self {
             uuid: Uuid::new_v4(),
-            status: RcloneJobStatus::Ongoing,
+            status: RcloneJobStatus::Queued,
             r#type,
             started_at: OffsetDateTime::now_utc(),
+            queued_at: OffsetDateTime::now_utc(),
+            process: ProcessHandle::default(),
+            history: vec![],
+            retry_scheduled: false,
+            suspended: false,
+            finished_items: BTreeSet::new(),
+            overwrites: false,
+            undoes: None,
         }
     }
 
     pub fn set_status(&mut self, new_status: RcloneJobStatus) {
         self.status = new_status;
     }
+
+    /// Archives the outcome of the last run and prepares the job to run again.
+    pub fn reset_for_retry(&mut self) {
+        let error = match &self.status {
+            RcloneJobStatus::Failed(error) => Some(error.clone()),
+            RcloneJobStatus::Interrupted => Some(String::from("Interrupted when the app closed")),
+            _ => None,
+        };
+        if let Some(error) = error {
+            self.history.push(RcloneJobAttempt {
+                error,
+                failed_at: OffsetDateTime::now_utc(),
+            });
+        }
+        self.status = RcloneJobStatus::Queued;
+        self.queued_at = OffsetDateTime::now_utc();
+        self.process = ProcessHandle::default();
+        self.retry_scheduled = false;
+        self.suspended = false;
+    }
+
+    /// Whether the job is running or waiting to run.
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self.status,
+            RcloneJobStatus::Queued | RcloneJobStatus::Ongoing
+        )
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self.status,
+            RcloneJobStatus::Failed(_) | RcloneJobStatus::Cancelled | RcloneJobStatus::Interrupted
+        )
+    }
+
+    pub fn cancel(&self) {
+        self.process.cancel();
+    }
+
+    /// Stops a running job so that it can be picked up again later.
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+        self.process.cancel();
+    }
+
+    /// Puts a suspended job back in the queue without losing its place.
+    pub fn requeue_suspended(&mut self) {
+        self.status = RcloneJobStatus::Queued;
+        self.process = ProcessHandle::default();
+        self.suspended = false;
+    }
+}
+
+fn jobs_file() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("rclone-shuttle").join("jobs.json"))
+}
+
+/// Serializes the job queue, oldest job first.
+pub fn serialize_jobs(jobs: &BTreeMap<Uuid, RcloneJob>) -> String {
+    let mut ordered_jobs = jobs.values().collect::<Vec<&RcloneJob>>();
+    ordered_jobs.sort_by_key(|job| job.started_at);
+    serde_json::to_string_pretty(&ordered_jobs).expect("Could not serialize jobs")
+}
+
+/// Restores a serialized job queue. Jobs that never finished are marked as interrupted.
+pub fn deserialize_jobs(raw: &str) -> Result<BTreeMap<Uuid, RcloneJob>, String> {
+    let jobs: Vec<RcloneJob> = serde_json::from_str(raw).map_err(|err| err.to_string())?;
+    Ok(jobs
+        .into_iter()
+        .map(|mut job| {
+            if matches!(
+                job.status,
+                RcloneJobStatus::Queued | RcloneJobStatus::Ongoing
+            ) {
+                job.status = RcloneJobStatus::Interrupted;
+            }
+            (job.uuid, job)
+        })
+        .collect())
+}
+
+pub fn load_jobs() -> BTreeMap<Uuid, RcloneJob> {
+    let Some(raw) = jobs_file().and_then(|path| std::fs::read_to_string(path).ok()) else {
+        return BTreeMap::new();
+    };
+    deserialize_jobs(&raw).unwrap_or_else(|err| {
+        println!("Warning: failed to restore job history. {}", err);
+        BTreeMap::new()
+    })
+}
+
+pub fn save_jobs(serialized_jobs: &str) {
+    let Some(path) = jobs_file() else {
+        println!("Warning: failed to save job history. No data folder found.");
+        return;
+    };
+    let result = path
+        .parent()
+        .map(std::fs::create_dir_all)
+        .unwrap_or(Ok(()))
+        .and_then(|_| std::fs::write(&path, serialized_jobs));
+    if let Err(err) = result {
+        println!("Warning: failed to save job history. {}", err);
+    }
+}
+
+/// Picks the queued jobs that can start right away, first in first out.
+pub fn jobs_to_dispatch(jobs: &BTreeMap<Uuid, RcloneJob>, max_parallel_jobs: usize) -> Vec<Uuid> {
+    let running_job_count = jobs
+        .values()
+        .filter(|j| j.status == RcloneJobStatus::Ongoing)
+        .count();
+    let mut queued_jobs = jobs
+        .values()
+        .filter(|j| j.status == RcloneJobStatus::Queued)
+        .collect::<Vec<&RcloneJob>>();
+    queued_jobs.sort_by_key(|j| j.queued_at);
+    queued_jobs
+        .into_iter()
+        .take(max_parallel_jobs.saturating_sub(running_job_count))
+        .map(|j| j.uuid)
+        .collect()
+}
+
+pub fn get_queued_jobs() -> Vec<RcloneJob> {
+    JOBS.read()
+        .values()
+        .filter(|&j| j.status == RcloneJobStatus::Queued)
+        .cloned()
+        .collect::<Vec<RcloneJob>>()
+}
+
+pub fn is_queue_paused() -> bool {
+    *QUEUE_PAUSED.read()
+}
+
+pub fn toggle_queue_paused() {
+    let paused = is_queue_paused();
+    *QUEUE_PAUSED.write() = !paused;
+}
+
+pub fn get_interrupted_jobs() -> Vec<RcloneJob> {
+    JOBS.read()
+        .values()
+        .filter(|&j| j.status == RcloneJobStatus::Interrupted)
+        .cloned()
+        .collect::<Vec<RcloneJob>>()
 }
 
 pub fn get_ongoing_jobs() -> Vec<RcloneJob> {
@@ -69,6 +632,21 @@ pub fn get_ongoing_jobs() -> Vec<RcloneJob> {
         .collect::<Vec<RcloneJob>>()
 }
 
+/// Combined completion of all ongoing jobs that have reported transfer stats.
+pub fn get_overall_progress() -> Option<f64> {
+    let progress = JOB_PROGRESS.read();
+    let (bytes, total_bytes) = get_ongoing_jobs()
+        .iter()
+        .filter_map(|j| progress.get(&j.uuid))
+        .fold((0, 0), |(bytes, total_bytes), p| {
+            (bytes + p.bytes, total_bytes + p.total_bytes)
+        });
+    match total_bytes {
+        0 => None,
+        _ => Some((bytes as f64 / total_bytes as f64).min(1.0)),
+    }
+}
+
 pub fn has_failed_jobs() -> bool {
     JOBS.read()
         .values()
@@ -77,10 +655,1041 @@ pub fn has_failed_jobs() -> bool {
         > 0
 }
 
+/// What to do about a transfer whose target already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    Overwrite,
+    Skip,
+    /// Transfers under a free name like "name (1).ext" instead.
+    KeepBoth,
+    /// Overwrites only if the source was modified after the existing file.
+    OverwriteIfNewer,
+}
+
+/// A requested transfer whose target already exists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferConflict {
+    /// The position of the transfer among those requested.
+    pub index: usize,
+    pub existing: RcloneFileListing,
+    pub source_mod_time: Option<OffsetDateTime>,
+}
+
+/// The transfers among a request whose targets already exist.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConflictCheck {
+    pub conflicts: Vec<TransferConflict>,
+    /// The names in each target folder, to find a free one when keeping both.
+    taken_names: BTreeMap<RclonePath, BTreeSet<String>>,
+}
+
+impl ConflictCheck {
+    /// Looks for existing targets among `items`, listing every target folder once.
+    pub fn run(client: &dyn StorageClient, items: &[RcloneJobType]) -> Result<Self, RcloneError> {
+        let mut check = Self::default();
+        let mut folders: BTreeMap<RclonePath, Vec<RcloneFileListing>> = BTreeMap::new();
+        for (index, item) in items.iter().enumerate() {
+            let Some(target) = item.target() else {
+                continue;
+            };
+            let folder = target.resolve_to_parent();
+            if !folders.contains_key(&folder) {
+                let listings = match client.ls(&folder) {
+                    Ok(listings) => listings,
+                    // Created along the way, so there is nothing to clash with
+                    Err(RcloneError::NotFound(_)) => vec![],
+                    Err(error) => return Err(error),
+                };
+                check.taken_names.insert(
+                    folder.clone(),
+                    listings
+                        .iter()
+                        .map(|listing| listing.name.clone())
+                        .collect(),
+                );
+                folders.insert(folder.clone(), listings);
+            }
+            let name = target.filename();
+            if let Some(existing) = folders[&folder].iter().find(|l| l.name == name) {
+                let source_mod_time = item
+                    .subject()
+                    .and_then(|source| client.stat(source).ok())
+                    .and_then(|metadata| {
+                        OffsetDateTime::parse(metadata.get("ModTime")?, &Rfc3339).ok()
+                    });
+                check.conflicts.push(TransferConflict {
+                    index,
+                    existing: existing.clone(),
+                    source_mod_time,
+                });
+            }
+        }
+        Ok(check)
+    }
+
+    /// Whether `item` still writes over one of the existing targets once resolved.
+    pub fn overwrites(&self, item: &RcloneJobType) -> bool {
+        self.conflicts
+            .iter()
+            .any(|conflict| item.target() == Some(&conflict.existing.path))
+    }
+
+    /// Applies one answer per conflict, in order, and drops the transfers that are skipped.
+    pub fn resolve(
+        &self,
+        items: Vec<RcloneJobType>,
+        resolutions: &[ConflictResolution],
+    ) -> Vec<RcloneJobType> {
+        let mut taken_names = self.taken_names.clone();
+        let mut resolved = vec![];
+        for (index, item) in items.into_iter().enumerate() {
+            let Some(position) = self.conflicts.iter().position(|c| c.index == index) else {
+                resolved.push(item);
+                continue;
+            };
+            let conflict = &self.conflicts[position];
+            let resolution = resolutions
+                .get(position)
+                .copied()
+                .unwrap_or(ConflictResolution::Skip);
+            match resolution {
+                ConflictResolution::Overwrite => resolved.push(item),
+                ConflictResolution::Skip => {}
+                ConflictResolution::OverwriteIfNewer => {
+                    if conflict
+                        .source_mod_time
+                        .is_some_and(|source| source > conflict.existing.mod_time)
+                    {
+                        resolved.push(item);
+                    }
+                }
+                ConflictResolution::KeepBoth => {
+                    let Some(folder) = item.target().map(RclonePath::resolve_to_parent) else {
+                        continue;
+                    };
+                    let names = taken_names.entry(folder.clone()).or_default();
+                    let name = free_name(&conflict.existing.name, conflict.existing.is_dir, names);
+                    names.insert(name.clone());
+                    resolved.push(item.with_target(folder.join(&name)));
+                }
+            }
+        }
+        resolved
+    }
+}
+
+/// A finished operation as remembered for Ctrl+Z.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndoEntry {
+    pub description: String,
+    /// The operation that takes it back, or `None` when it can't be undone.
+    pub inverse: Option<RcloneJobType>,
+}
+
+impl UndoEntry {
+    /// Remembers a finished job. Looks up on the remote whether what a copy created is a
+    /// folder, so this belongs off the main thread.
+    pub fn of_job(client: &dyn StorageClient, job: &RcloneJob) -> Self {
+        let is_dir = |path: &RclonePath| {
+            client
+                .stat(path)
+                .is_ok_and(|metadata| metadata.get("IsDir").is_some_and(|v| v == "true"))
+        };
+        Self {
+            description: job.r#type.describe(),
+            inverse: match job.overwrites {
+                true => None,
+                false => job.r#type.inverse(&is_dir),
+            },
+        }
+    }
+
+    pub fn folder_created(path: &RclonePath) -> Self {
+        Self {
+            description: format!(
+                "Create folder {} in {}",
+                path.filename(),
+                path.resolve_to_parent()
+            ),
+            inverse: Some(RcloneJobType::Delete {
+                path: path.clone(),
+                is_dir: true,
+            }),
+        }
+    }
+}
+
+/// Transfers waiting for answers about their conflicts.
+#[derive(Debug, Clone)]
+pub struct PendingTransfers {
+    pub items: Vec<RcloneJobType>,
+    pub check: ConflictCheck,
+    resolutions: Vec<ConflictResolution>,
+}
+
+impl PendingTransfers {
+    pub fn new(items: Vec<RcloneJobType>, check: ConflictCheck) -> Self {
+        Self {
+            items,
+            check,
+            resolutions: vec![],
+        }
+    }
+
+    /// The first conflict that has no answer yet.
+    pub fn next_conflict(&self) -> Option<&TransferConflict> {
+        self.check.conflicts.get(self.resolutions.len())
+    }
+
+    /// How many conflicts have no answer yet.
+    pub fn remaining(&self) -> usize {
+        self.check.conflicts.len() - self.resolutions.len()
+    }
+
+    /// Answers the next conflict, or all of those left.
+    pub fn answer(&mut self, resolution: ConflictResolution, apply_to_all: bool) {
+        let count = match apply_to_all {
+            true => self.remaining(),
+            false => self.remaining().min(1),
+        };
+        self.resolutions
+            .extend(std::iter::repeat_n(resolution, count));
+    }
+
+    /// The transfers to run, once every conflict has an answer.
+    pub fn resolved(self) -> Vec<RcloneJobType> {
+        self.check.resolve(self.items, &self.resolutions)
+    }
+}
+
+/// A name like "name (1).ext" that isn't among `taken`. Folders keep dots in their name.
+pub fn free_name(name: &str, is_dir: bool, taken: &BTreeSet<String>) -> String {
+    let (stem, extension) = match name.rfind('.') {
+        Some(position) if position > 0 && !is_dir => name.split_at(position),
+        _ => (name, ""),
+    };
+    (1..)
+        .map(|n| format!("{} ({}){}", stem, n, extension))
+        .find(|candidate| !taken.contains(candidate))
+        .expect("Ran out of numbers for a free name")
+}
+
 #[derive(Debug, Clone, Default)]
 pub enum FilePickerMode {
     #[default]
     Select,
-    Move(RclonePath),
-    Copy(RclonePath),
+    Move(Vec<RclonePath>),
+    Copy(Vec<RclonePath>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    #[default]
+    Name,
+    Size,
+    Modified,
+    Type,
+}
+
+impl SortKey {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Name => "Name",
+            Self::Size => "Size",
+            Self::Modified => "Modified",
+            Self::Type => "Type",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FileSort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl FileSort {
+    /// Sorting by the current column again flips the direction. Another column starts out
+    /// ascending.
+    pub fn toggled(self, key: SortKey) -> Self {
+        Self {
+            key,
+            descending: key == self.key && !self.descending,
+        }
+    }
+}
+
+/// Orders listings by the chosen column, with ties broken by name. Folders always come first.
+pub fn sort_listings(listings: &mut [RcloneFileListing], sort: FileSort) {
+    listings.sort_by(|a, b| {
+        let ordering = match sort.key {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Modified => a.mod_time.cmp(&b.mod_time),
+            SortKey::Type => a.mime_type.cmp(&b.mime_type),
+        }
+        .then_with(|| a.name.cmp(&b.name));
+        b.is_dir.cmp(&a.is_dir).then(match sort.descending {
+            true => ordering.reverse(),
+            false => ordering,
+        })
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListingKind {
+    #[default]
+    All,
+    Folders,
+    Files,
+}
+
+/// Narrows the loaded listing of a folder down, without asking rclone again.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ListingFilter {
+    /// Part of the name, a glob like `*.pdf`, or a regex if `use_regex` is set.
+    pub pattern: String,
+    pub use_regex: bool,
+    pub kind: ListingKind,
+    /// Only lets through files of this type, unless just folders are shown.
+    pub mime_type: Option<String>,
+}
+
+impl ListingFilter {
+    /// The compiled name pattern, or `None` if there is no pattern to match.
+    pub fn name_regex(&self) -> Result<Option<regex::Regex>, regex::Error> {
+        if self.pattern.is_empty() {
+            return Ok(None);
+        }
+        let pattern = match self.use_regex {
+            true => self.pattern.clone(),
+            false => name_pattern(&self.pattern),
+        };
+        regex::Regex::new(&format!("(?i){}", pattern)).map(Some)
+    }
+
+    /// Keeps the listings that pass. A pattern that doesn't compile leaves the names unfiltered.
+    pub fn apply(&self, listings: Vec<RcloneFileListing>) -> Vec<RcloneFileListing> {
+        let name_regex = self.name_regex().ok().flatten();
+        listings
+            .into_iter()
+            .filter(|listing| {
+                name_regex
+                    .as_ref()
+                    .is_none_or(|regex| regex.is_match(&listing.name))
+            })
+            .filter(|listing| match self.kind {
+                ListingKind::All => true,
+                ListingKind::Folders => listing.is_dir,
+                ListingKind::Files => !listing.is_dir,
+            })
+            .filter(|listing| {
+                self.kind == ListingKind::Folders
+                    || self
+                        .mime_type
+                        .as_ref()
+                        .is_none_or(|mime_type| !listing.is_dir && listing.mime_type == *mime_type)
+            })
+            .collect()
+    }
+}
+
+/// The distinct types of the files among `listings`, for the type filter.
+pub fn file_mime_types(listings: &[RcloneFileListing]) -> Vec<String> {
+    listings
+        .iter()
+        .filter(|listing| !listing.is_dir && !listing.mime_type.is_empty())
+        .map(|listing| listing.mime_type.clone())
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+/// A remote file that was opened from a temporary local copy, watched so that edits saved in
+/// another app can be uploaded back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenedFile {
+    pub remote_path: RclonePath,
+    pub local_path: RclonePath,
+    /// The remote's modification time when the copy was last synced, to notice when someone
+    /// changed it in the meantime.
+    pub remote_mod_time: Option<String>,
+    /// The local copy's modification time when it was last synced or offered for upload.
+    pub local_mod_time: Option<SystemTime>,
+}
+
+impl OpenedFile {
+    /// The current modification time of the local copy, or `None` once it is gone.
+    pub fn current_local_mod_time(&self) -> Option<SystemTime> {
+        std::fs::metadata(self.local_path.to_string())
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Whether the local copy was saved since it was last synced. Rclone gives downloads the
+    /// remote's modification time, so this looks for any change rather than a newer time.
+    pub fn is_modified(&self, local_mod_time: SystemTime) -> bool {
+        self.local_mod_time != Some(local_mod_time)
+    }
+
+    /// Whether the remote copy changed or went away since it was last synced. Unknown if rclone
+    /// couldn't tell when it was opened, in which case there is nothing to warn about.
+    pub fn has_remote_conflict(&self, remote_mod_time: Option<&str>) -> bool {
+        match &self.remote_mod_time {
+            Some(known) => remote_mod_time != Some(known.as_str()),
+            None => false,
+        }
+    }
+}
+
+/// The modification time rclone reports for a remote file, as it appears in `lsjson`.
+pub fn remote_mod_time(client: &dyn StorageClient, path: &RclonePath) -> Option<String> {
+    client
+        .stat(path)
+        .ok()
+        .and_then(|mut metadata| metadata.remove("ModTime"))
+}
+
+/// How many bytes the uploads among `items` send to each remote, counting local folders with
+/// everything in them.
+pub fn upload_sizes(items: &[RcloneJobType]) -> BTreeMap<String, u64> {
+    let mut sizes = BTreeMap::new();
+    for item in items {
+        if let RcloneJobType::Upload {
+            local_path,
+            remote_path,
+        } = item
+        {
+            if let Some(remote) = remote_path.remote() {
+                *sizes.entry(remote).or_default() += local_size(Path::new(&local_path.to_string()));
+            }
+        }
+    }
+    sizes
+}
+
+/// The size of a local file, or of everything in a local folder. Like rclone, this doesn't
+/// follow links.
+fn local_size(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| local_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use time::Duration;
+
+    use crate::client::{ProcessHandle, RcloneError, RcloneFileListing, StorageClient};
+    use crate::memory_client::MemoryClient;
+    use crate::model::{
+        deserialize_jobs, file_mime_types, free_name, jobs_to_dispatch, remote_mod_time,
+        serialize_jobs, sort_listings, upload_sizes, ConflictCheck, ConflictResolution, FileSort,
+        ListingFilter, ListingKind, OpenedFile, PendingTransfers, RcloneJob, RcloneJobStatus,
+        RcloneJobType, SortKey, UndoEntry,
+    };
+    use crate::path_tools::RclonePath;
+    use test_case::test_case;
+
+    #[test]
+    fn reset_for_retry_keeps_history() {
+        let mut job = RcloneJob::new(RcloneJobType::Delete {
+            path: RclonePath::from("foo:bar"),
+            is_dir: false,
+        });
+        job.set_status(RcloneJobStatus::Failed(String::from("first")));
+        job.reset_for_retry();
+        job.set_status(RcloneJobStatus::Failed(String::from("second")));
+        job.reset_for_retry();
+        assert_eq!(job.status, RcloneJobStatus::Queued);
+        assert_eq!(
+            job.history
+                .iter()
+                .map(|a| a.error.as_str())
+                .collect::<Vec<_>>(),
+            vec!["first", "second"]
+        );
+    }
+
+    #[test]
+    fn requeue_suspended_keeps_place_in_queue() {
+        let mut job = make_delete_job("foo:bar", RcloneJobStatus::Ongoing, 5);
+        let queued_at = job.queued_at;
+        job.suspend();
+        job.set_status(RcloneJobStatus::Cancelled);
+        job.requeue_suspended();
+        assert_eq!(job.status, RcloneJobStatus::Queued);
+        assert_eq!(job.queued_at, queued_at);
+        assert!(!job.suspended);
+        assert!(!job.process.is_cancelled());
+        assert!(job.history.is_empty());
+    }
+
+    #[test]
+    fn jobs_survive_round_trip() {
+        let mut finished_job = RcloneJob::new(RcloneJobType::Upload {
+            local_path: RclonePath::from("/home/foo/bar.txt"),
+            remote_path: RclonePath::from("foo:bar.txt"),
+        });
+        finished_job.set_status(RcloneJobStatus::Failed(String::from("first")));
+        finished_job.reset_for_retry();
+        finished_job.set_status(RcloneJobStatus::Finished);
+        let ongoing_job = RcloneJob::new(RcloneJobType::Rename {
+            source_path: RclonePath::from("foo:a.txt"),
+            target_path: RclonePath::from("foo:b.txt"),
+        });
+        let jobs = BTreeMap::from([
+            (finished_job.uuid, finished_job.clone()),
+            (ongoing_job.uuid, ongoing_job.clone()),
+        ]);
+
+        let restored_jobs = deserialize_jobs(&serialize_jobs(&jobs)).unwrap();
+
+        let restored_finished_job = restored_jobs.get(&finished_job.uuid).unwrap();
+        assert_eq!(restored_finished_job.status, RcloneJobStatus::Finished);
+        assert_eq!(restored_finished_job.r#type, finished_job.r#type);
+        assert_eq!(restored_finished_job.history, finished_job.history);
+        let restored_ongoing_job = restored_jobs.get(&ongoing_job.uuid).unwrap();
+        assert_eq!(restored_ongoing_job.status, RcloneJobStatus::Interrupted);
+        assert!(restored_ongoing_job.is_retryable());
+    }
+
+    #[test]
+    fn deserialize_jobs_rejects_garbage() {
+        assert!(deserialize_jobs("{not json").is_err());
+    }
+
+    fn make_delete_job(path: &str, status: RcloneJobStatus, queued_minutes_ago: i64) -> RcloneJob {
+        let mut job = RcloneJob::new(RcloneJobType::Delete {
+            path: RclonePath::from(path),
+            is_dir: false,
+        });
+        job.set_status(status);
+        job.queued_at -= Duration::minutes(queued_minutes_ago);
+        job
+    }
+
+    #[test]
+    fn jobs_to_dispatch_first_in_first_out() {
+        let oldest = make_delete_job("foo:a", RcloneJobStatus::Queued, 3);
+        let middle = make_delete_job("foo:b", RcloneJobStatus::Queued, 2);
+        let newest = make_delete_job("foo:c", RcloneJobStatus::Queued, 1);
+        let jobs = BTreeMap::from_iter(
+            [newest.clone(), oldest.clone(), middle.clone()]
+                .into_iter()
+                .map(|j| (j.uuid, j)),
+        );
+        assert_eq!(jobs_to_dispatch(&jobs, 2), vec![oldest.uuid, middle.uuid]);
+    }
+
+    #[test]
+    fn jobs_to_dispatch_respects_running_jobs() {
+        let running = make_delete_job("foo:a", RcloneJobStatus::Ongoing, 5);
+        let finished = make_delete_job("foo:b", RcloneJobStatus::Finished, 4);
+        let queued = make_delete_job("foo:c", RcloneJobStatus::Queued, 3);
+        let jobs = BTreeMap::from_iter(
+            [running, finished, queued.clone()]
+                .into_iter()
+                .map(|j| (j.uuid, j)),
+        );
+        assert!(jobs_to_dispatch(&jobs, 1).is_empty());
+        assert_eq!(jobs_to_dispatch(&jobs, 2), vec![queued.uuid]);
+    }
+
+    fn delete_item(path: &str) -> RcloneJobType {
+        RcloneJobType::Delete {
+            path: RclonePath::from(path),
+            is_dir: false,
+        }
+    }
+
+    #[test]
+    fn grouped_keeps_single_operation_as_is() {
+        assert_eq!(RcloneJobType::grouped(vec![]), None);
+        assert_eq!(
+            RcloneJobType::grouped(vec![delete_item("foo:a")]),
+            Some(delete_item("foo:a"))
+        );
+        assert!(matches!(
+            RcloneJobType::grouped(vec![delete_item("foo:a"), delete_item("foo:b")]),
+            Some(RcloneJobType::Batch { .. })
+        ));
+    }
+
+    #[test]
+    fn batch_carries_on_past_failures() {
+        let client = MemoryClient::new(&["foo:"])
+            .with_file("foo:a", "a")
+            .with_file("foo:c", "c");
+        let batch = RcloneJobType::grouped(vec![
+            delete_item("foo:a"),
+            delete_item("foo:b"),
+            delete_item("foo:c"),
+        ])
+        .unwrap();
+        let finished_items = RefCell::new(BTreeSet::new());
+        let result = batch.run(
+            &client as &dyn StorageClient,
+            &BTreeSet::new(),
+            &ProcessHandle::default(),
+            &|_| {},
+            &|index| {
+                finished_items.borrow_mut().insert(index);
+            },
+        );
+        assert!(
+            matches!(result, Err(RcloneError::NotFound(message)) if message.starts_with("1 of 3 items failed."))
+        );
+        assert_eq!(*finished_items.borrow(), BTreeSet::from([0, 2]));
+        assert!(!client.exists(&RclonePath::from("foo:c")));
+    }
+
+    #[test]
+    fn batch_retry_skips_finished_items() {
+        let client = MemoryClient::new(&["foo:"]).with_file("foo:b", "b");
+        let batch =
+            RcloneJobType::grouped(vec![delete_item("foo:a"), delete_item("foo:b")]).unwrap();
+        let result = batch.run(
+            &client,
+            &BTreeSet::from([0]),
+            &ProcessHandle::default(),
+            &|_| {},
+            &|_| {},
+        );
+        assert!(result.is_ok());
+    }
+
+    fn listing(name: &str, size: i64, minutes_old: i64, mime_type: &str) -> RcloneFileListing {
+        RcloneFileListing {
+            path: RclonePath::from(&format!("foo:{}", name)),
+            name: String::from(name),
+            size,
+            mime_type: String::from(mime_type),
+            mod_time: time::OffsetDateTime::UNIX_EPOCH + Duration::days(1)
+                - Duration::minutes(minutes_old),
+            is_dir: mime_type == "inode/directory",
+            is_bucket: None,
+        }
+    }
+
+    #[test_case(SortKey::Name, false, &["docs", "photos", "a.txt", "b.png", "c.txt"] ; "name")]
+    #[test_case(SortKey::Name, true, &["photos", "docs", "c.txt", "b.png", "a.txt"] ; "name descending")]
+    #[test_case(SortKey::Size, false, &["docs", "photos", "c.txt", "a.txt", "b.png"] ; "size")]
+    #[test_case(SortKey::Size, true, &["photos", "docs", "b.png", "a.txt", "c.txt"] ; "size descending")]
+    #[test_case(SortKey::Modified, false, &["photos", "docs", "b.png", "c.txt", "a.txt"] ; "modified")]
+    #[test_case(SortKey::Type, false, &["docs", "photos", "b.png", "a.txt", "c.txt"] ; "type")]
+    fn listings_sort_with_folders_first(key: SortKey, descending: bool, expected: &[&str]) {
+        let mut listings = vec![
+            listing("c.txt", 5, 20, "text/plain"),
+            listing("photos", -1, 50, "inode/directory"),
+            listing("a.txt", 30, 10, "text/plain"),
+            listing("b.png", 200, 40, "image/png"),
+            listing("docs", -1, 30, "inode/directory"),
+        ];
+        sort_listings(&mut listings, FileSort { key, descending });
+        let names: Vec<&str> = listings.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn sorting_by_same_column_flips_direction() {
+        let sort = FileSort::default().toggled(SortKey::Size);
+        assert_eq!(
+            sort,
+            FileSort {
+                key: SortKey::Size,
+                descending: false
+            }
+        );
+        let sort = sort.toggled(SortKey::Size);
+        assert_eq!(
+            sort,
+            FileSort {
+                key: SortKey::Size,
+                descending: true
+            }
+        );
+        let sort = sort.toggled(SortKey::Name);
+        assert_eq!(
+            sort,
+            FileSort {
+                key: SortKey::Name,
+                descending: false
+            }
+        );
+    }
+
+    fn sample_listings() -> Vec<RcloneFileListing> {
+        vec![
+            listing("photos", -1, 50, "inode/directory"),
+            listing("Report.pdf", 30, 10, "application/pdf"),
+            listing("report-draft.txt", 5, 20, "text/plain"),
+            listing("cat.png", 200, 40, "image/png"),
+        ]
+    }
+
+    #[test_case("", false, ListingKind::All, None, &["photos", "Report.pdf", "report-draft.txt", "cat.png"] ; "no filter")]
+    #[test_case("report", false, ListingKind::All, None, &["Report.pdf", "report-draft.txt"] ; "part of the name")]
+    #[test_case("*.p??", false, ListingKind::All, None, &["Report.pdf", "cat.png"] ; "glob")]
+    #[test_case("^r.*\\.(pdf|txt)$", true, ListingKind::All, None, &["Report.pdf", "report-draft.txt"] ; "regex")]
+    #[test_case("(", true, ListingKind::All, None, &["photos", "Report.pdf", "report-draft.txt", "cat.png"] ; "invalid regex")]
+    #[test_case("", false, ListingKind::Folders, None, &["photos"] ; "folders only")]
+    #[test_case("", false, ListingKind::Files, None, &["Report.pdf", "report-draft.txt", "cat.png"] ; "files only")]
+    #[test_case("", false, ListingKind::All, Some("image/png"), &["cat.png"] ; "mime type")]
+    #[test_case("", false, ListingKind::Folders, Some("image/png"), &["photos"] ; "folders ignore mime type")]
+    fn listing_filter(
+        pattern: &str,
+        use_regex: bool,
+        kind: ListingKind,
+        mime_type: Option<&str>,
+        expected: &[&str],
+    ) {
+        let filter = ListingFilter {
+            pattern: String::from(pattern),
+            use_regex,
+            kind,
+            mime_type: mime_type.map(String::from),
+        };
+        let filtered = filter.apply(sample_listings());
+        let names: Vec<&str> = filtered.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn mime_types_of_files() {
+        assert_eq!(
+            file_mime_types(&sample_listings()),
+            vec!["application/pdf", "image/png", "text/plain"]
+        );
+    }
+
+    #[test]
+    fn opened_file_notices_local_edits() {
+        let saved_at = std::time::SystemTime::UNIX_EPOCH;
+        let opened = OpenedFile {
+            remote_path: RclonePath::from("foo:a.txt"),
+            local_path: RclonePath::from("/tmp/a.txt"),
+            remote_mod_time: None,
+            local_mod_time: Some(saved_at),
+        };
+        assert!(!opened.is_modified(saved_at));
+        // An editor may save with an older time than the one rclone gave the download
+        assert!(opened.is_modified(saved_at - std::time::Duration::from_secs(60)));
+        assert!(opened.is_modified(saved_at + std::time::Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn opened_file_remote_conflicts() {
+        let client = MemoryClient::new(&["foo:"]).with_file("foo:a.txt", "hello");
+        let path = RclonePath::from("foo:a.txt");
+        let opened = OpenedFile {
+            remote_path: path.clone(),
+            local_path: RclonePath::from("/tmp/a.txt"),
+            remote_mod_time: remote_mod_time(&client, &path),
+            local_mod_time: None,
+        };
+        assert!(opened.remote_mod_time.is_some());
+        assert!(!opened.has_remote_conflict(remote_mod_time(&client, &path).as_deref()));
+        assert!(opened.has_remote_conflict(Some("2001-01-01T00:00:00Z")));
+        assert!(opened.has_remote_conflict(None));
+
+        let unknown = OpenedFile {
+            remote_mod_time: None,
+            ..opened
+        };
+        assert!(!unknown.has_remote_conflict(Some("2001-01-01T00:00:00Z")));
+    }
+
+    #[test_case("report.pdf", false, &[], "report (1).pdf" ; "file")]
+    #[test_case("report.pdf", false, &["report (1).pdf"], "report (2).pdf" ; "next free number")]
+    #[test_case("archive.tar.gz", false, &[], "archive.tar (1).gz" ; "last extension")]
+    #[test_case(".bashrc", false, &[], ".bashrc (1)" ; "hidden file")]
+    #[test_case("photos.2024", true, &[], "photos.2024 (1)" ; "folder")]
+    fn free_names(name: &str, is_dir: bool, taken: &[&str], expected: &str) {
+        let taken = taken.iter().map(|name| String::from(*name)).collect();
+        assert_eq!(free_name(name, is_dir, &taken), expected);
+    }
+
+    #[test]
+    fn conflicting_transfers_are_resolved() {
+        let client = MemoryClient::new(&["foo:"])
+            .with_file("foo:archive/a.txt", "old")
+            .with_file("foo:archive/b.txt", "old")
+            .with_file("foo:inbox/a.txt", "new")
+            .with_file("foo:inbox/b.txt", "new")
+            .with_file("foo:inbox/c.txt", "new");
+        let items: Vec<RcloneJobType> = ["a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|name| RcloneJobType::Copy {
+                source_path: RclonePath::from("foo:inbox").join(name),
+                target_path: RclonePath::from("foo:archive").join(name),
+            })
+            .collect();
+        let check = ConflictCheck::run(&client, &items).unwrap();
+        let conflicting: Vec<usize> = check.conflicts.iter().map(|c| c.index).collect();
+        assert_eq!(conflicting, vec![0, 1]);
+        assert!(check.conflicts[0].source_mod_time.is_some());
+
+        let targets = |resolved: Vec<RcloneJobType>| -> Vec<String> {
+            resolved
+                .iter()
+                .filter_map(|item| item.target().map(|target| target.to_string()))
+                .collect()
+        };
+        let resolved = check.resolve(
+            items.clone(),
+            &[ConflictResolution::KeepBoth, ConflictResolution::Skip],
+        );
+        assert_eq!(
+            targets(resolved),
+            vec!["foo:archive/a (1).txt", "foo:archive/c.txt"]
+        );
+        let resolved = check.resolve(
+            items.clone(),
+            &[
+                ConflictResolution::Overwrite,
+                ConflictResolution::OverwriteIfNewer,
+            ],
+        );
+        // The sources were written after the targets
+        assert_eq!(
+            targets(resolved),
+            vec![
+                "foo:archive/a.txt",
+                "foo:archive/b.txt",
+                "foo:archive/c.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn transfers_into_new_folder_have_no_conflicts() {
+        let client = MemoryClient::new(&["foo:"]).with_file("foo:a.txt", "a");
+        let items = vec![RcloneJobType::Upload {
+            local_path: RclonePath::from("/home/me/a.txt"),
+            remote_path: RclonePath::from("foo:new/a.txt"),
+        }];
+        assert!(ConflictCheck::run(&client, &items)
+            .unwrap()
+            .conflicts
+            .is_empty());
+    }
+
+    #[test]
+    fn rename_onto_existing_name_conflicts() {
+        let client = MemoryClient::new(&["foo:"])
+            .with_file("foo:a.txt", "a")
+            .with_file("foo:b.txt", "b");
+        let items = vec![RcloneJobType::Rename {
+            source_path: RclonePath::from("foo:a.txt"),
+            target_path: RclonePath::from("foo:b.txt"),
+        }];
+        let check = ConflictCheck::run(&client, &items).unwrap();
+        assert_eq!(check.conflicts.len(), 1);
+        assert!(check.overwrites(&items[0]));
+        let resolved = check.resolve(items, &[ConflictResolution::KeepBoth]);
+        assert_eq!(
+            resolved,
+            vec![RcloneJobType::Rename {
+                source_path: RclonePath::from("foo:a.txt"),
+                target_path: RclonePath::from("foo:b (1).txt"),
+            }]
+        );
+    }
+
+    #[test]
+    fn pending_transfers_apply_answer_to_all() {
+        let client = MemoryClient::new(&["foo:"])
+            .with_file("foo:a.txt", "a")
+            .with_file("foo:b.txt", "b")
+            .with_file("foo:c.txt", "c");
+        let items: Vec<RcloneJobType> = ["a.txt", "b.txt", "c.txt"]
+            .iter()
+            .map(|name| RcloneJobType::Copy {
+                source_path: RclonePath::from("foo:").join(name),
+                target_path: RclonePath::from("foo:").join(name),
+            })
+            .collect();
+        let check = ConflictCheck::run(&client, &items).unwrap();
+        let mut pending = PendingTransfers::new(items, check);
+        assert_eq!(pending.remaining(), 3);
+        pending.answer(ConflictResolution::Skip, false);
+        assert_eq!(pending.next_conflict().unwrap().existing.name, "b.txt");
+        pending.answer(ConflictResolution::KeepBoth, true);
+        assert!(pending.next_conflict().is_none());
+        let targets: Vec<String> = pending
+            .resolved()
+            .iter()
+            .filter_map(|item| item.target().map(|target| target.to_string()))
+            .collect();
+        assert_eq!(targets, vec!["foo:b (1).txt", "foo:c (1).txt"]);
+    }
+
+    #[test]
+    fn changed_remotes() {
+        let batch = RcloneJobType::Batch {
+            items: vec![
+                RcloneJobType::Move {
+                    source_path: RclonePath::from("foo:a.txt"),
+                    target_path: RclonePath::from("bar:a.txt"),
+                },
+                RcloneJobType::Copy {
+                    source_path: RclonePath::from("baz:b.txt"),
+                    target_path: RclonePath::from("foo:b.txt"),
+                },
+            ],
+        };
+        assert_eq!(
+            batch.changed_remotes(),
+            BTreeSet::from([String::from("bar:"), String::from("foo:")])
+        );
+        let download = RcloneJobType::Download {
+            local_path: RclonePath::from("/tmp/a.txt"),
+            remote_path: RclonePath::from("foo:a.txt"),
+        };
+        assert!(download.changed_remotes().is_empty());
+    }
+
+    #[test]
+    fn inverse_operations() {
+        let path = RclonePath::from;
+        let is_dir = |p: &RclonePath| p.filename() == "docs";
+        let rename = RcloneJobType::Rename {
+            source_path: path("foo:a.txt"),
+            target_path: path("foo:b.txt"),
+        };
+        assert_eq!(
+            rename.inverse(&is_dir),
+            Some(RcloneJobType::Rename {
+                source_path: path("foo:b.txt"),
+                target_path: path("foo:a.txt"),
+            })
+        );
+        let copies = RcloneJobType::Batch {
+            items: vec![
+                RcloneJobType::Copy {
+                    source_path: path("foo:a.txt"),
+                    target_path: path("bar:a.txt"),
+                },
+                RcloneJobType::Copy {
+                    source_path: path("foo:docs"),
+                    target_path: path("bar:docs"),
+                },
+            ],
+        };
+        assert_eq!(
+            copies.inverse(&is_dir),
+            Some(RcloneJobType::Batch {
+                items: vec![
+                    RcloneJobType::Delete {
+                        path: path("bar:docs"),
+                        is_dir: true,
+                    },
+                    RcloneJobType::Delete {
+                        path: path("bar:a.txt"),
+                        is_dir: false,
+                    },
+                ],
+            })
+        );
+        let delete = RcloneJobType::Delete {
+            path: path("foo:a.txt"),
+            is_dir: false,
+        };
+        assert_eq!(delete.inverse(&is_dir), None);
+        // One item that can't be taken back makes the whole batch final
+        let mixed = RcloneJobType::Batch {
+            items: vec![rename, delete],
+        };
+        assert_eq!(mixed.inverse(&is_dir), None);
+    }
+
+    #[test]
+    fn undo_entries_of_jobs() {
+        let client = MemoryClient::new(&["foo:"])
+            .with_file("foo:docs/a.txt", "a")
+            .with_file("foo:copy/a.txt", "a");
+        let copy = RcloneJobType::Copy {
+            source_path: RclonePath::from("foo:docs"),
+            target_path: RclonePath::from("foo:copy"),
+        };
+        let entry = UndoEntry::of_job(&client, &RcloneJob::new(copy.clone()));
+        assert_eq!(entry.description, "Copy docs to foo:");
+        assert_eq!(
+            entry.inverse,
+            Some(RcloneJobType::Delete {
+                path: RclonePath::from("foo:copy"),
+                is_dir: true,
+            })
+        );
+
+        let mut overwriting = RcloneJob::new(copy);
+        overwriting.overwrites = true;
+        assert_eq!(UndoEntry::of_job(&client, &overwriting).inverse, None);
+    }
+
+    #[test]
+    fn overwriting_transfers_are_detected() {
+        let client = MemoryClient::new(&["foo:"])
+            .with_file("foo:a.txt", "a")
+            .with_file("foo:docs/a.txt", "a");
+        let item = RcloneJobType::Copy {
+            source_path: RclonePath::from("foo:a.txt"),
+            target_path: RclonePath::from("foo:docs/a.txt"),
+        };
+        let check = ConflictCheck::run(&client, std::slice::from_ref(&item)).unwrap();
+        assert!(check.overwrites(&item));
+        let kept = check.resolve(vec![item], &[ConflictResolution::KeepBoth]);
+        assert!(!check.overwrites(&kept[0]));
+    }
+
+    #[test]
+    fn upload_sizes_add_up_local_folders() {
+        let root =
+            std::env::temp_dir().join(format!("rclone-shuttle-upload-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("photos/2024")).unwrap();
+        std::fs::write(root.join("photos/a.jpg"), vec![0; 300]).unwrap();
+        std::fs::write(root.join("photos/2024/b.jpg"), vec![0; 200]).unwrap();
+        std::fs::write(root.join("notes.txt"), vec![0; 5]).unwrap();
+        let local = |name: &str| RclonePath::from(root.join(name).to_str().unwrap());
+        let items = vec![
+            RcloneJobType::Upload {
+                local_path: local("photos"),
+                remote_path: RclonePath::from("foo:photos"),
+            },
+            RcloneJobType::Upload {
+                local_path: local("notes.txt"),
+                remote_path: RclonePath::from("bar:notes.txt"),
+            },
+            RcloneJobType::Upload {
+                local_path: local("missing.txt"),
+                remote_path: RclonePath::from("bar:missing.txt"),
+            },
+            RcloneJobType::Delete {
+                path: RclonePath::from("foo:old.txt"),
+                is_dir: false,
+            },
+        ];
+        assert_eq!(
+            upload_sizes(&items),
+            BTreeMap::from([(String::from("bar:"), 5), (String::from("foo:"), 500)])
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
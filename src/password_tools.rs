@@ -0,0 +1,100 @@
+const PASSWORD_ALPHABET: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum PasswordStrength {
+    Empty,
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl PasswordStrength {
+    /// Rates a password by how hard it would be to guess through brute force.
+    pub fn of(password: &str) -> Self {
+        if password.is_empty() {
+            return Self::Empty;
+        }
+        match entropy_bits(password) {
+            bits if bits < 50.0 => Self::Weak,
+            bits if bits < 80.0 => Self::Fair,
+            _ => Self::Strong,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Empty => "",
+            Self::Weak => "Weak",
+            Self::Fair => "Fair",
+            Self::Strong => "Strong",
+        }
+    }
+
+    /// How full a strength meter should be.
+    pub fn fraction(&self) -> f64 {
+        match self {
+            Self::Empty => 0.0,
+            Self::Weak => 0.25,
+            Self::Fair => 0.6,
+            Self::Strong => 1.0,
+        }
+    }
+}
+
+/// Estimates the entropy of a password from its length and the kinds of characters it uses.
+fn entropy_bits(password: &str) -> f64 {
+    let uses = |test: fn(&char) -> bool| password.chars().any(|c| test(&c));
+    let pool_size = [
+        (uses(char::is_ascii_lowercase), 26),
+        (uses(char::is_ascii_uppercase), 26),
+        (uses(char::is_ascii_digit), 10),
+        (uses(|c| c.is_ascii() && !c.is_ascii_alphanumeric()), 33),
+        (uses(|c| !c.is_ascii()), 100),
+    ]
+    .iter()
+    .filter(|(used, _)| *used)
+    .map(|(_, size)| size)
+    .sum::<u32>();
+    password.chars().count() as f64 * f64::from(pool_size).log2()
+}
+
+/// Generates a random password of `length` URL-safe characters, 6 bits of entropy each.
+pub fn generate_password(length: usize) -> String {
+    // Bytes at or above the largest multiple of the alphabet size are dropped, so that every
+    // character is equally likely
+    let limit = 256 - 256 % PASSWORD_ALPHABET.len();
+    let mut password = String::with_capacity(length);
+    let mut random_bytes = [0u8; 64];
+    while password.len() < length {
+        getrandom::fill(&mut random_bytes).expect("Could not read from the system's random source");
+        for byte in random_bytes.iter().map(|b| usize::from(*b)) {
+            if byte < limit && password.len() < length {
+                password.push(PASSWORD_ALPHABET[byte % PASSWORD_ALPHABET.len()] as char);
+            }
+        }
+    }
+    password
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::password_tools::{generate_password, PasswordStrength};
+    use test_case::test_case;
+
+    #[test_case("", PasswordStrength::Empty ; "empty")]
+    #[test_case("hunter2", PasswordStrength::Weak ; "short")]
+    #[test_case("horsebattery", PasswordStrength::Fair ; "lowercase words")]
+    #[test_case("Correct-Horse-Battery-9", PasswordStrength::Strong ; "long and mixed")]
+    fn password_strength(password: &str, expected: PasswordStrength) {
+        assert_eq!(PasswordStrength::of(password), expected);
+    }
+
+    #[test]
+    fn generated_passwords_are_strong_and_unique() {
+        let password = generate_password(32);
+        assert_eq!(password.len(), 32);
+        assert_eq!(PasswordStrength::of(&password), PasswordStrength::Strong);
+        assert_ne!(password, generate_password(32));
+    }
+}
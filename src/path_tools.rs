@@ -1,8 +1,10 @@
 use std::fmt::Display;
 
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct RclonePath {
     inner: String,
 }
@@ -0,0 +1,162 @@
+use std::process::{Command, Stdio};
+
+use dirs::cache_dir;
+use uuid::Uuid;
+
+use crate::client::RcloneFileListing;
+
+/// How much of a file is fetched to preview it as text.
+pub const TEXT_PREVIEW_BYTES: u64 = 64 * 1024;
+
+/// Image types GTK can show without extra loaders.
+const IMAGE_MIME_TYPES: [&str; 8] = [
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/bmp",
+    "image/webp",
+    "image/tiff",
+    "image/svg+xml",
+    "image/x-icon",
+];
+
+/// How a file is shown in the preview panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewKind {
+    Folder,
+    Image,
+    Pdf,
+    /// Anything else that might be text. Only the start of the file is fetched, and it is
+    /// dropped if it turns out to be binary.
+    Text,
+    /// Types that are certainly not text, like audio, video and archives.
+    Unsupported,
+}
+
+impl PreviewKind {
+    pub fn of(listing: &RcloneFileListing) -> Self {
+        let mime_type = listing.mime_type.as_str();
+        if listing.is_dir {
+            Self::Folder
+        } else if IMAGE_MIME_TYPES.contains(&mime_type) {
+            Self::Image
+        } else if mime_type == "application/pdf" {
+            Self::Pdf
+        } else if mime_type.starts_with("audio/")
+            || mime_type.starts_with("video/")
+            || mime_type.starts_with("image/")
+            || mime_type.starts_with("font/")
+            || mime_type.contains("zip")
+            || mime_type.contains("compressed")
+            || mime_type.ends_with("-tar")
+        {
+            Self::Unsupported
+        } else {
+            Self::Text
+        }
+    }
+
+    /// Whether the whole file has to be fetched, as opposed to just its start.
+    pub fn needs_whole_file(&self) -> bool {
+        matches!(self, Self::Image | Self::Pdf)
+    }
+}
+
+/// Decodes the start of a file as text, or `None` if it looks binary. A character cut off at
+/// the end of the fetched range is replaced rather than taken as a sign of binary content.
+pub fn text_preview(bytes: &[u8]) -> Option<String> {
+    if bytes.contains(&0) {
+        return None;
+    }
+    let text = String::from_utf8_lossy(bytes);
+    let replaced = text.matches(char::REPLACEMENT_CHARACTER).count();
+    match replaced > 4 && replaced * 100 > text.chars().count() {
+        true => None,
+        false => Some(text.into_owned()),
+    }
+}
+
+/// Renders the first page of a PDF to PNG with `pdftoppm` from poppler.
+pub fn render_pdf_page(pdf: &[u8]) -> Result<Vec<u8>, String> {
+    // Kept apart from the cache of opened files, which counts and clears everything in its folder
+    let folder = cache_dir()
+        .ok_or_else(|| String::from("Cannot find cache folder"))?
+        .join("rclone-shuttle-preview");
+    std::fs::create_dir_all(&folder).map_err(|err| err.to_string())?;
+    let prefix = folder.join(format!("preview-{}", Uuid::new_v4().simple()));
+    let pdf_path = prefix.with_extension("pdf");
+    let png_path = prefix.with_extension("png");
+    std::fs::write(&pdf_path, pdf).map_err(|err| err.to_string())?;
+
+    let output = Command::new("pdftoppm")
+        .args([
+            "-png",
+            "-f",
+            "1",
+            "-l",
+            "1",
+            "-singlefile",
+            "-scale-to",
+            "1024",
+        ])
+        .arg(&pdf_path)
+        .arg(&prefix)
+        .stdin(Stdio::null())
+        .output();
+    let _ = std::fs::remove_file(&pdf_path);
+    let output = output
+        .map_err(|_| String::from("Install pdftoppm from poppler-utils to preview PDF files"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Could not render the PDF. {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let png = std::fs::read(&png_path).map_err(|err| err.to_string());
+    let _ = std::fs::remove_file(&png_path);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+
+    use crate::client::RcloneFileListing;
+    use crate::path_tools::RclonePath;
+    use crate::preview::{text_preview, PreviewKind};
+    use test_case::test_case;
+
+    #[test_case("image/png", false, PreviewKind::Image ; "image")]
+    #[test_case("image/x-canon-cr2", false, PreviewKind::Unsupported ; "raw photo")]
+    #[test_case("application/pdf", false, PreviewKind::Pdf ; "pdf")]
+    #[test_case("text/markdown", false, PreviewKind::Text ; "text")]
+    #[test_case("application/octet-stream", false, PreviewKind::Text ; "unknown type")]
+    #[test_case("video/mp4", false, PreviewKind::Unsupported ; "video")]
+    #[test_case("application/zip", false, PreviewKind::Unsupported ; "archive")]
+    #[test_case("inode/directory", true, PreviewKind::Folder ; "folder")]
+    fn preview_kind(mime_type: &str, is_dir: bool, expected: PreviewKind) {
+        let listing = RcloneFileListing {
+            path: RclonePath::from("foo:file"),
+            name: String::from("file"),
+            size: 10,
+            mime_type: String::from(mime_type),
+            mod_time: OffsetDateTime::UNIX_EPOCH,
+            is_dir,
+            is_bucket: None,
+        };
+        assert_eq!(PreviewKind::of(&listing), expected);
+    }
+
+    #[test]
+    fn text_preview_of_text() {
+        assert_eq!(text_preview(b"hello\n").unwrap(), "hello\n");
+        // "é" cut in half at the end of the fetched range
+        assert_eq!(text_preview(b"caf\xc3").unwrap(), "caf\u{fffd}");
+    }
+
+    #[test]
+    fn text_preview_of_binary() {
+        assert!(text_preview(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").is_none());
+        assert!(text_preview(&[0xff; 64]).is_none());
+    }
+}
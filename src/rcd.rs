@@ -0,0 +1,690 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    io::{Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+use crate::{
+    client::{
+        flatten_metadata, parse_hashsum, FileMetadata, FolderSize, ImportedBackendFeatures,
+        ImportedConfigState, ImportedFileListing, MkdirError, ProcessHandle, RcloneError,
+        RcloneFileListing, RemoteConfig, RemoteProvider, RemoteQuota, SearchQuery, StorageClient,
+        TransferStats,
+    },
+    path_tools::RclonePath,
+};
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many ports to try before giving up on starting `rclone rcd`.
+const START_ATTEMPTS: usize = 3;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize)]
+struct ImportedJobStatus {
+    finished: bool,
+    success: bool,
+    error: String,
+}
+
+#[derive(Deserialize)]
+struct ImportedStat {
+    item: Option<ImportedFileListing>,
+}
+
+#[derive(Debug)]
+struct RcdSession {
+    child: Mutex<Child>,
+    address: String,
+    authorization: String,
+}
+
+impl Drop for RcdSession {
+    fn drop(&mut self) {
+        if let Ok(mut child) = self.child.lock() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Talks to a single `rclone rcd` process through its remote control API, instead of starting
+/// a new rclone process for every operation.
+#[derive(Debug, Clone)]
+pub struct RcdClient {
+    session: Arc<RcdSession>,
+}
+
+impl RcdClient {
+    /// Starts `rclone rcd` on a free loopback port with throwaway credentials. `build_command`
+    /// should return a bare rclone command that already carries the config location and
+    /// password.
+    ///
+    /// Another program may take the port between finding it free and rclone listening on it. In
+    /// that case rclone exits, and it is started again on another port.
+    pub fn start(build_command: impl Fn() -> Command) -> Result<Self, RcloneError> {
+        let mut attempts = 1;
+        loop {
+            let client = Self::spawn(build_command())?;
+            match client.wait_until_ready() {
+                Ok(()) => return Ok(client),
+                Err(_) if attempts < START_ATTEMPTS && client.has_exited() => attempts += 1,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn spawn(mut command: Command) -> Result<Self, RcloneError> {
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| listener.local_addr())
+            .map_err(|err| RcloneError::Unknown(format!("No free port for rclone rcd. {}", err)))?
+            .port();
+        let address = format!("127.0.0.1:{}", port);
+        let user = Uuid::new_v4().simple().to_string();
+        let pass = Uuid::new_v4().simple().to_string();
+
+        // Arguments show up in the process list for every user, the environment doesn't
+        let child = command
+            .args(["rcd", &format!("--rc-addr={}", address)])
+            .env("RCLONE_RC_USER", &user)
+            .env("RCLONE_RC_PASS", &pass)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|_| RcloneError::Unknown(String::from("Command did not start")))?;
+        Ok(Self {
+            session: Arc::new(RcdSession {
+                child: Mutex::new(child),
+                address,
+                authorization: format!("Basic {}", encode_base64(&format!("{}:{}", user, pass))),
+            }),
+        })
+    }
+
+    fn has_exited(&self) -> bool {
+        matches!(self.session.child.lock().unwrap().try_wait(), Ok(Some(_)))
+    }
+
+    fn wait_until_ready(&self) -> Result<(), RcloneError> {
+        let started_at = Instant::now();
+        loop {
+            if self.call("rc/noop", json!({})).is_ok() {
+                return Ok(());
+            }
+            let exited = self.session.child.lock().unwrap().try_wait();
+            if let Ok(Some(status)) = exited {
+                return Err(RcloneError::Unknown(format!(
+                    "rclone rcd exited with {}",
+                    status
+                )));
+            }
+            if started_at.elapsed() > STARTUP_TIMEOUT {
+                return Err(RcloneError::Unknown(String::from(
+                    "rclone rcd did not respond in time",
+                )));
+            }
+            sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Calls an rc method and returns its JSON response.
+    fn call(&self, method: &str, params: Value) -> Result<Value, RcloneError> {
+        parse_response(&self.request(method, params)?)
+    }
+
+    /// Sends a request for an rc method and returns the raw HTTP response.
+    fn request(&self, method: &str, params: Value) -> Result<Vec<u8>, RcloneError> {
+        let body = params.to_string();
+        // HTTP/1.0 keeps the response free of chunked encoding, so it can be read to the end
+        let request = format!(
+            "POST /{} HTTP/1.0\r\nHost: {}\r\nAuthorization: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            self.session.address,
+            self.session.authorization,
+            body.len(),
+            body
+        );
+        let mut stream = TcpStream::connect(&self.session.address)
+            .map_err(|err| RcloneError::Unknown(format!("Could not reach rclone rcd. {}", err)))?;
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|err| RcloneError::Unknown(format!("Could not reach rclone rcd. {}", err)))?;
+        let mut response = vec![];
+        stream
+            .read_to_end(&mut response)
+            .map_err(|err| RcloneError::Unknown(format!("No answer from rclone rcd. {}", err)))?;
+        Ok(response)
+    }
+
+    /// Runs an rc method as a background job, reporting its stats until it is done.
+    fn run_job(
+        &self,
+        method: &str,
+        mut params: Value,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        if process.is_cancelled() {
+            return Err(RcloneError::Unknown(String::from("Cancelled before start")));
+        }
+        params["_async"] = json!(true);
+        let job_id = self.call(method, params)?["jobid"]
+            .as_i64()
+            .ok_or_else(|| {
+                RcloneError::Unknown(String::from("rclone rcd did not return a job ID"))
+            })?;
+
+        loop {
+            sleep(POLL_INTERVAL);
+            if process.is_cancelled() {
+                let _ = self.call("job/stop", json!({ "jobid": job_id }));
+                return Err(RcloneError::Unknown(String::from("Cancelled")));
+            }
+            let stats = self.call("core/stats", json!({ "group": format!("job/{}", job_id) }));
+            if let Some(stats) = stats.ok().and_then(|s| serde_json::from_value(s).ok()) {
+                on_progress(stats);
+            }
+            let status: ImportedJobStatus =
+                serde_json::from_value(self.call("job/status", json!({ "jobid": job_id }))?)
+                    .map_err(|err| RcloneError::Unknown(err.to_string()))?;
+            if status.finished {
+                return match status.success {
+                    true => Ok(()),
+                    false => Err(RcloneError::from_message(&status.error)),
+                };
+            }
+        }
+    }
+
+    fn is_dir(&self, path: &RclonePath) -> Result<bool, RcloneError> {
+        let stat: ImportedStat = serde_json::from_value(self.call(
+            "operations/stat",
+            json!({
+                "fs": path.resolve_to_parent().to_string(),
+                "remote": path.filename(),
+            }),
+        )?)
+        .map_err(|err| RcloneError::Unknown(err.to_string()))?;
+        match stat.item {
+            Some(item) => Ok(item.is_dir),
+            None => Err(RcloneError::NotFound(format!("{} not found", path))),
+        }
+    }
+}
+
+impl StorageClient for RcdClient {
+    fn list_remotes(&self) -> Result<Vec<String>, RcloneError> {
+        let response = self.call("config/listremotes", json!({}))?;
+        Ok(response["remotes"]
+            .as_array()
+            .map(|remotes| {
+                remotes
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|remote| format!("{}:", remote))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn ls(&self, path: &RclonePath) -> Result<Vec<RcloneFileListing>, RcloneError> {
+        let mut response = self.call(
+            "operations/list",
+            json!({ "fs": path.to_string(), "remote": "" }),
+        )?;
+        let imported_listings: Vec<ImportedFileListing> =
+            serde_json::from_value(response["list"].take()).map_err(|err| {
+                RcloneError::Unknown(format!("Could not decode listing. {}", err))
+            })?;
+        Ok(imported_listings
+            .iter()
+            .map(|l| RcloneFileListing::from(l, path))
+            .collect())
+    }
+
+    fn copy(
+        &self,
+        source_path: &RclonePath,
+        target_path: &RclonePath,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        if self.is_dir(source_path)? {
+            self.run_job(
+                "sync/copy",
+                json!({
+                    "srcFs": source_path.to_string(),
+                    "dstFs": target_path.to_string(),
+                }),
+                process,
+                on_progress,
+            )
+        } else {
+            self.run_job(
+                "operations/copyfile",
+                file_transfer_params(source_path, target_path),
+                process,
+                on_progress,
+            )
+        }
+    }
+
+    fn mv(
+        &self,
+        source_path: &RclonePath,
+        target_path: &RclonePath,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        if self.is_dir(source_path)? {
+            self.run_job(
+                "sync/move",
+                json!({
+                    "srcFs": source_path.to_string(),
+                    "dstFs": target_path.to_string(),
+                    "deleteEmptySrcDirs": true,
+                }),
+                process,
+                on_progress,
+            )
+        } else {
+            self.run_job(
+                "operations/movefile",
+                file_transfer_params(source_path, target_path),
+                process,
+                on_progress,
+            )
+        }
+    }
+
+    fn rename(
+        &self,
+        source_path: &RclonePath,
+        new_filename: &str,
+        process: &ProcessHandle,
+        on_progress: &dyn Fn(TransferStats),
+    ) -> Result<(), RcloneError> {
+        let target_path = source_path.resolve_to_parent().join(new_filename);
+        if self.is_dir(source_path)? {
+            self.run_job(
+                "sync/move",
+                json!({
+                    "srcFs": source_path.to_string(),
+                    "dstFs": target_path.to_string(),
+                    "deleteEmptySrcDirs": true,
+                }),
+                process,
+                on_progress,
+            )
+        } else {
+            self.run_job(
+                "operations/movefile",
+                file_transfer_params(source_path, &target_path),
+                process,
+                on_progress,
+            )
+        }
+    }
+
+    fn rm(
+        &self,
+        path: &RclonePath,
+        is_dir: bool,
+        process: &ProcessHandle,
+    ) -> Result<(), RcloneError> {
+        let (method, params) = match is_dir {
+            true => (
+                "operations/purge",
+                json!({ "fs": path.to_string(), "remote": "" }),
+            ),
+            false => (
+                "operations/deletefile",
+                json!({
+                    "fs": path.resolve_to_parent().to_string(),
+                    "remote": path.filename(),
+                }),
+            ),
+        };
+        self.run_job(method, params, process, &|_| {})
+    }
+
+    fn mkdir(&self, path: &RclonePath) -> Result<(), MkdirError> {
+        let fs_info = self
+            .call("operations/fsinfo", json!({ "fs": path.to_string() }))
+            .map_err(MkdirError::Generic)?;
+        if fs_info["Features"]["CanHaveEmptyDirectories"] == json!(false) {
+            return Err(MkdirError::NotAvailableHere);
+        }
+        self.call(
+            "operations/mkdir",
+            json!({ "fs": path.to_string(), "remote": "" }),
+        )
+        .map(|_| ())
+        .map_err(MkdirError::Generic)
+    }
+
+    /// The rc API has no `cat`, so this runs the command through `core/command` and streams
+    /// its output back.
+    fn cat(&self, path: &RclonePath, max_bytes: u64) -> Result<Vec<u8>, RcloneError> {
+        parse_stream_response(&self.request(
+            "core/command",
+            json!({
+                "command": "cat",
+                "arg": [path.to_string()],
+                "opt": { "count": max_bytes.to_string() },
+                "returnType": "STREAM_ONLY_STDOUT",
+            }),
+        )?)
+    }
+
+    fn stat(&self, path: &RclonePath) -> Result<FileMetadata, RcloneError> {
+        let response = self.call(
+            "operations/stat",
+            json!({
+                "fs": path.resolve_to_parent().to_string(),
+                "remote": path.filename(),
+                "opt": { "metadata": true },
+            }),
+        )?;
+        match &response["item"] {
+            Value::Null => Err(RcloneError::NotFound(format!("{} not found", path))),
+            item => Ok(flatten_metadata(item)),
+        }
+    }
+
+    fn hash_types(&self, path: &RclonePath) -> Result<Vec<String>, RcloneError> {
+        let remote = path.remote().unwrap_or_else(|| path.to_string());
+        let features: ImportedBackendFeatures =
+            serde_json::from_value(self.call("operations/fsinfo", json!({ "fs": remote }))?)
+                .map_err(|err| RcloneError::Unknown(err.to_string()))?;
+        Ok(features.hashes)
+    }
+
+    fn hashsum(&self, path: &RclonePath, hash_type: &str) -> Result<String, RcloneError> {
+        let response = self.call(
+            "operations/hashsum",
+            json!({ "fs": path.to_string(), "hashType": hash_type }),
+        )?;
+        parse_hashsum(response["hashsum"][0].as_str().unwrap_or_default())
+    }
+
+    fn size(&self, path: &RclonePath) -> Result<FolderSize, RcloneError> {
+        serde_json::from_value(self.call("operations/size", json!({ "fs": path.to_string() }))?)
+            .map_err(|err| RcloneError::Unknown(err.to_string()))
+    }
+
+    fn about(&self, remote: &str) -> Result<RemoteQuota, RcloneError> {
+        serde_json::from_value(self.call("operations/about", json!({ "fs": remote }))?)
+            .map_err(|err| RcloneError::Unknown(err.to_string()))
+    }
+
+    /// The rc API answers with the whole listing at once, so hits only come in at the end and
+    /// cancelling merely drops them.
+    fn search(
+        &self,
+        path: &RclonePath,
+        query: &SearchQuery,
+        process: &ProcessHandle,
+        on_hit: &dyn Fn(RcloneFileListing),
+    ) -> Result<(), RcloneError> {
+        let name_regex = query.name_regex()?;
+        if process.is_cancelled() {
+            return Err(RcloneError::Unknown(String::from("Cancelled before start")));
+        }
+        let mut response = self.call(
+            "operations/list",
+            json!({
+                "fs": path.to_string(),
+                "remote": "",
+                "opt": { "recurse": true },
+                "_filter": {
+                    "IncludeRule": [query.glob()],
+                    "IgnoreCase": true,
+                    "MaxDepth": query.max_depth.map_or(-1, i64::from),
+                },
+            }),
+        )?;
+        if process.is_cancelled() {
+            return Err(RcloneError::Unknown(String::from("Cancelled")));
+        }
+        let imported_listings: Vec<ImportedFileListing> =
+            serde_json::from_value(response["list"].take()).map_err(|err| {
+                RcloneError::Unknown(format!("Could not decode listing. {}", err))
+            })?;
+        for listing in imported_listings
+            .iter()
+            .filter(|l| name_regex.is_match(&l.name))
+        {
+            on_hit(RcloneFileListing::from(listing, path));
+        }
+        Ok(())
+    }
+
+    fn providers(&self) -> Result<Vec<RemoteProvider>, RcloneError> {
+        let mut response = self.call("config/providers", json!({}))?;
+        serde_json::from_value(response["providers"].take())
+            .map_err(|err| RcloneError::Unknown(format!("Could not decode providers. {}", err)))
+    }
+
+    fn remote_config(&self, remote: &str) -> Result<RemoteConfig, RcloneError> {
+        let response = self.call("config/get", json!({ "name": remote }))?;
+        let config: RemoteConfig = serde_json::from_value(response).map_err(|err| {
+            RcloneError::Unknown(format!("Could not decode remote config. {}", err))
+        })?;
+        match config.is_empty() {
+            true => Err(RcloneError::NotFound(format!(
+                "Remote {} not found",
+                remote
+            ))),
+            false => Ok(config),
+        }
+    }
+
+    fn create_remote(
+        &self,
+        remote: &str,
+        provider: &str,
+        values: &RemoteConfig,
+        values_obscured: bool,
+    ) -> Result<(), RcloneError> {
+        let response = self.call(
+            "config/create",
+            json!({
+                "name": remote,
+                "type": provider,
+                "parameters": values,
+                "opt": {
+                    "nonInteractive": true,
+                    "obscure": !values_obscured,
+                    "noObscure": values_obscured,
+                },
+            }),
+        )?;
+        config_state(response)
+    }
+
+    fn update_remote(&self, remote: &str, values: &RemoteConfig) -> Result<(), RcloneError> {
+        let response = self.call(
+            "config/update",
+            json!({
+                "name": remote,
+                "parameters": values,
+                "opt": { "nonInteractive": true, "obscure": true },
+            }),
+        )?;
+        config_state(response)
+    }
+
+    fn delete_remote(&self, remote: &str) -> Result<(), RcloneError> {
+        self.call("config/delete", json!({ "name": remote }))
+            .map(|_| ())
+    }
+}
+
+fn config_state(response: Value) -> Result<(), RcloneError> {
+    match serde_json::from_value::<ImportedConfigState>(response) {
+        Ok(state) => state.into_result(),
+        Err(_) => Ok(()),
+    }
+}
+
+fn file_transfer_params(source_path: &RclonePath, target_path: &RclonePath) -> Value {
+    json!({
+        "srcFs": source_path.resolve_to_parent().to_string(),
+        "srcRemote": source_path.filename(),
+        "dstFs": target_path.resolve_to_parent().to_string(),
+        "dstRemote": target_path.filename(),
+    })
+}
+
+/// Splits a raw HTTP response into its status code and body.
+fn split_response(raw: &[u8]) -> Result<(u16, &[u8]), RcloneError> {
+    let malformed = || RcloneError::Unknown(String::from("Malformed response from rclone rcd"));
+    let separator = raw
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let status = String::from_utf8_lossy(&raw[..separator])
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(malformed)?;
+    Ok((status, &raw[separator + 4..]))
+}
+
+/// Turns a raw HTTP response into its JSON body, turning rc error replies into an `Err`.
+fn parse_response(raw: &[u8]) -> Result<Value, RcloneError> {
+    let (status, body) = split_response(raw)?;
+    let value: Value = serde_json::from_slice(body)
+        .map_err(|_| RcloneError::Unknown(String::from_utf8_lossy(body).trim().to_owned()))?;
+    match status {
+        200 => Ok(value),
+        _ => Err(match value["error"].as_str() {
+            Some(error) => RcloneError::from_message(error),
+            None => RcloneError::Unknown(format!("rclone rcd answered with status {}", status)),
+        }),
+    }
+}
+
+/// Returns the body of a response that streams raw output, or the error it reports instead.
+fn parse_stream_response(raw: &[u8]) -> Result<Vec<u8>, RcloneError> {
+    match split_response(raw)? {
+        (200, body) => Ok(body.to_vec()),
+        _ => parse_response(raw).map(|_| vec![]),
+    }
+}
+
+fn encode_base64(raw: &str) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in raw.as_bytes().chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let triple = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use crate::client::{ProcessHandle, RcloneError, StorageClient};
+    use crate::path_tools::RclonePath;
+    use crate::rcd::{encode_base64, parse_response, parse_stream_response, RcdClient};
+    use test_case::test_case;
+
+    #[test_case("", ""; "empty")]
+    #[test_case("f", "Zg=="; "one byte")]
+    #[test_case("fo", "Zm8="; "two bytes")]
+    #[test_case("foo", "Zm9v"; "three bytes")]
+    #[test_case("user:pass", "dXNlcjpwYXNz"; "credentials")]
+    fn base64(raw: &str, encoded: &str) {
+        assert_eq!(encode_base64(raw), encoded);
+    }
+
+    #[test]
+    fn parse_successful_response() {
+        let raw = "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{\"jobid\": 4}";
+        assert_eq!(parse_response(raw.as_bytes()).unwrap()["jobid"], 4);
+    }
+
+    #[test]
+    fn parse_error_response() {
+        let raw = "HTTP/1.0 500 Internal Server Error\r\n\r\n{\"error\": \"directory not found\", \"status\": 500}";
+        assert_eq!(
+            parse_response(raw.as_bytes()).unwrap_err(),
+            RcloneError::NotFound(String::from("directory not found"))
+        );
+    }
+
+    #[test]
+    fn parse_stream_responses() {
+        let raw = b"HTTP/1.0 200 OK\r\n\r\n\x89PNG\r\n\r\n";
+        assert_eq!(parse_stream_response(raw).unwrap(), b"\x89PNG\r\n\r\n");
+        let raw = b"HTTP/1.0 404 Not Found\r\n\r\n{\"error\": \"object not found\"}";
+        assert_eq!(
+            parse_stream_response(raw).unwrap_err(),
+            RcloneError::NotFound(String::from("object not found"))
+        );
+    }
+
+    /// Runs a file round trip against a real `rclone rcd` on the local file system.
+    #[test]
+    #[ignore = "needs rclone"]
+    fn local_round_trip() {
+        let root = std::env::temp_dir().join(format!("rclone-shuttle-rcd-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("a.txt"), "hello").unwrap();
+        let root_path = RclonePath::from(root.to_str().unwrap());
+        let process = ProcessHandle::default();
+
+        let client = RcdClient::start(|| Command::new("rclone")).unwrap();
+        client.mkdir(&root_path.join("folder")).unwrap();
+        client
+            .copy(
+                &root_path.join("a.txt"),
+                &root_path.join("folder/b.txt"),
+                &process,
+                &|_| {},
+            )
+            .unwrap();
+        client
+            .rename(&root_path.join("a.txt"), "c.txt", &process, &|_| {})
+            .unwrap();
+        let mut names = client
+            .ls(&root_path)
+            .unwrap()
+            .into_iter()
+            .map(|l| l.name)
+            .collect::<Vec<String>>();
+        names.sort();
+        assert_eq!(names, vec!["c.txt", "folder"]);
+        assert_eq!(
+            std::fs::read_to_string(root.join("folder/b.txt")).unwrap(),
+            "hello"
+        );
+
+        client
+            .rm(&root_path.join("folder"), true, &process)
+            .unwrap();
+        assert!(!root.join("folder").exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
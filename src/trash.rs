@@ -0,0 +1,132 @@
+use crate::client::{RcloneError, RcloneFileListing, StorageClient};
+use crate::path_tools::RclonePath;
+
+/// A deleted file or folder, waiting in the trash of its remote.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrashedItem {
+    pub listing: RcloneFileListing,
+    /// Where the item is put back when restored.
+    pub original_path: RclonePath,
+    /// The day the item was deleted, as named by its folder in the trash.
+    pub deleted_on: String,
+}
+
+/// The trash folder at the root of the remote that `path` is on.
+pub fn trash_root(path: &RclonePath, folder: &str) -> Option<RclonePath> {
+    Some(RclonePath::from(&path.remote()?).join(folder.trim_matches('/')))
+}
+
+/// Whether `path` is the trash folder of its remote or anything in it.
+pub fn is_in_trash(path: &RclonePath, folder: &str) -> bool {
+    let Some(root) = trash_root(path, folder) else {
+        return false;
+    };
+    let (path, root) = (path.to_string(), root.to_string());
+    path == root || path.starts_with(&format!("{}/", root))
+}
+
+/// Where `path` goes when it is deleted on `date`. The whole path is kept in one name, so that
+/// every direct child of a day's folder is an item that can be restored on its own.
+pub fn trash_path(path: &RclonePath, folder: &str, date: &str) -> Option<RclonePath> {
+    let remote = path.remote()?;
+    let relative = &path.to_string()[remote.len()..];
+    Some(
+        trash_root(path, folder)?
+            .join(date)
+            .join(&encode_name(relative.trim_matches('/'))),
+    )
+}
+
+/// Everything in the trash of `remote`, oldest first.
+pub fn list_trash(
+    client: &dyn StorageClient,
+    remote: &str,
+    folder: &str,
+) -> Result<Vec<TrashedItem>, RcloneError> {
+    let remote_root = RclonePath::from(remote);
+    let Some(root) = trash_root(&remote_root, folder) else {
+        return Ok(vec![]);
+    };
+    let days = match client.ls(&root) {
+        Ok(days) => days,
+        Err(RcloneError::NotFound(_)) => return Ok(vec![]),
+        Err(error) => return Err(error),
+    };
+    let mut items = vec![];
+    for day in days.into_iter().filter(|day| day.is_dir) {
+        for listing in client.ls(&day.path)? {
+            items.push(TrashedItem {
+                original_path: remote_root.join(&decode_name(&listing.name)),
+                deleted_on: day.name.clone(),
+                listing,
+            });
+        }
+    }
+    items.sort_by_key(|item| (item.deleted_on.clone(), item.original_path.clone()));
+    Ok(items)
+}
+
+/// Turns a path into a single name. Percent signs are escaped first, so decoding is exact.
+fn encode_name(path: &str) -> String {
+    path.replace('%', "%25").replace('/', "%2F")
+}
+
+fn decode_name(name: &str) -> String {
+    name.replace("%2F", "/").replace("%25", "%")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory_client::MemoryClient;
+    use crate::path_tools::RclonePath;
+    use crate::trash::{decode_name, encode_name, is_in_trash, list_trash, trash_path};
+    use test_case::test_case;
+
+    #[test_case("foo:a.txt", "foo:.shuttle-trash/2024-05-01/a.txt" ; "file at the root")]
+    #[test_case("foo:docs/a.txt", "foo:.shuttle-trash/2024-05-01/docs%2Fa.txt" ; "nested file")]
+    #[test_case("foo:docs/", "foo:.shuttle-trash/2024-05-01/docs" ; "folder")]
+    #[test_case("foo:100%/a", "foo:.shuttle-trash/2024-05-01/100%25%2Fa" ; "percent sign")]
+    fn trash_paths(path: &str, expected: &str) {
+        let trashed = trash_path(&RclonePath::from(path), ".shuttle-trash", "2024-05-01");
+        assert_eq!(trashed, Some(RclonePath::from(expected)));
+    }
+
+    #[test_case("100%/a%2F/b" ; "escapes")]
+    #[test_case("docs/a.txt" ; "plain")]
+    fn names_round_trip(path: &str) {
+        assert_eq!(decode_name(&encode_name(path)), path);
+    }
+
+    #[test]
+    fn trash_is_recognized() {
+        assert!(is_in_trash(&RclonePath::from("foo:.trash"), ".trash"));
+        assert!(is_in_trash(&RclonePath::from("foo:.trash/x/a"), "/.trash/"));
+        assert!(!is_in_trash(&RclonePath::from("foo:.trash2/a"), ".trash"));
+        assert!(!is_in_trash(&RclonePath::from("foo:docs/.trash"), ".trash"));
+    }
+
+    #[test]
+    fn trash_is_listed() {
+        let client = MemoryClient::new(&["foo:"]).with_file("foo:a.txt", "a");
+        assert!(list_trash(&client, "foo:", ".trash").unwrap().is_empty());
+
+        let client = client
+            .with_file("foo:.trash/2024-05-02/a.txt", "a")
+            .with_file("foo:.trash/2024-05-01/docs%2Fb.txt", "b")
+            .with_file("foo:.trash/2024-05-01/docs/c.txt", "c");
+        let items = list_trash(&client, "foo:", ".trash").unwrap();
+        let summary: Vec<(&str, String)> = items
+            .iter()
+            .map(|item| (item.deleted_on.as_str(), item.original_path.to_string()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("2024-05-01", String::from("foo:docs")),
+                ("2024-05-01", String::from("foo:docs/b.txt")),
+                ("2024-05-02", String::from("foo:a.txt")),
+            ]
+        );
+        assert!(items[0].listing.is_dir);
+    }
+}